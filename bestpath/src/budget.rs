@@ -0,0 +1,584 @@
+//! Searches that stop after a number of expansions or at a deadline, and
+//! anytime search that finds a path quickly and then improves it.
+
+use std::cell::Cell;
+use std::time::{Duration, Instant};
+
+use search::{explore, Exploration, SearchState, TieBreak};
+use search_cells::SearchCells;
+use trace::Part;
+use {AStar, Graph, Grid, GridGraph, MovementModel, Position, SearchEvent,
+     SearchObserver};
+
+/// Limits on how much work a search may do. The default is no limit.
+///
+/// # Examples
+///
+/// ```
+/// use std::time::Duration;
+/// use bestpath::Budget;
+///
+/// let budget = Budget::default().with_max_expansions(1000)
+///                               .with_time_limit(Duration::from_millis(5));
+/// assert_eq!(Some(1000), budget.max_expansions());
+/// assert!(budget.deadline().is_some());
+/// ```
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug, Default)]
+pub struct Budget {
+    max_expansions: Option<usize>,
+    deadline: Option<Instant>,
+    // Only paths cheaper than this are looked for.
+    max_cost: Option<usize>,
+}
+
+impl Budget {
+    /// Stop after expanding `max_expansions` cells.
+    pub fn with_max_expansions(self, max_expansions: usize) -> Budget {
+        Budget { max_expansions: Some(max_expansions), ..self }
+    }
+
+    /// Stop once `deadline` has passed.
+    pub fn with_deadline(self, deadline: Instant) -> Budget {
+        Budget { deadline: Some(deadline), ..self }
+    }
+
+    /// Stop once `limit` has passed from now.
+    pub fn with_time_limit(self, limit: Duration) -> Budget {
+        self.with_deadline(Instant::now() + limit)
+    }
+
+    /// The most cells that may be expanded, if limited.
+    #[inline]
+    pub fn max_expansions(&self) -> Option<usize> {
+        self.max_expansions
+    }
+
+    /// When the search must stop, if limited.
+    #[inline]
+    pub fn deadline(&self) -> Option<Instant> {
+        self.deadline
+    }
+
+    /// What is left of the budget once `expanded` cells have been expanded.
+    fn remaining(&self, expanded: usize) -> Budget {
+        Budget {
+            max_expansions: self.max_expansions
+                .map(|max| max.saturating_sub(expanded)),
+            ..*self
+        }
+    }
+
+    /// Only look for paths that cost less than `cost`.
+    fn below_cost(self, cost: usize) -> Budget {
+        Budget { max_cost: Some(cost), ..self }
+    }
+
+    /// Returns true if a search that has expanded `expanded` cells must stop.
+    fn is_spent(&self, expanded: usize) -> bool {
+        self.max_expansions.is_some_and(|max| expanded >= max) ||
+            self.deadline.is_some_and(|deadline| Instant::now() >= deadline)
+    }
+}
+
+/// The outcome of a search with a `Budget`.
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct BoundedResult {
+    /// The path found and its cost. If the budget ran out before the goal
+    /// was reached, this is the path to the cell found so far that is
+    /// closest to the goal according to the movement model's heuristic,
+    /// the cheapest first if several are as close. `None` if the search
+    /// finished and there is no path.
+    pub path: Option<(Vec<Position>, usize)>,
+    /// True if the path reaches the goal, or if there is no path. False if
+    /// the budget ran out first.
+    pub complete: bool,
+    /// The number of cells expanded.
+    pub expanded: usize,
+}
+
+/// Passes every event on to `observer` while keeping the cell reached so far
+/// that is closest to `goal`, so that a search that runs out of budget knows
+/// where its partial path should end, and the cost of the cell being
+/// expanded, so that `Pruned` knows how much its steps cost in total.
+struct Closest<'o, O: ?Sized + 'o> {
+    movement: MovementModel,
+    goal: Position,
+    // The heuristic distance to the goal and cost of the closest cell, and
+    // the cell.
+    best: (usize, usize, Position),
+    expanding: &'o Cell<usize>,
+    observer: &'o mut O,
+}
+
+impl<O: SearchObserver + ?Sized> SearchObserver for Closest<'_, O> {
+    fn observe(&mut self, event: SearchEvent) {
+        match event {
+            SearchEvent::CameFrom { position, cost, .. } => {
+                let distance = self.movement.heuristic(position, self.goal);
+                if (distance, cost) < (self.best.0, self.best.1) {
+                    self.best = (distance, cost, position);
+                }
+            }
+            SearchEvent::Expanded { cost, .. } => self.expanding.set(cost),
+            _ => {}
+        }
+        self.observer.observe(event);
+    }
+}
+
+/// The steps of a grid, leaving out those that can't be on a path to `goal`
+/// that costs less than `max_cost` because the movement model's heuristic,
+/// which never overestimates, says the rest of the way costs too much.
+struct Pruned<'a> {
+    steps: GridGraph<'a>,
+    goal: Position,
+    max_cost: Option<usize>,
+    // The cost of the cell whose steps are being taken.
+    expanding: &'a Cell<usize>,
+}
+
+impl Graph for Pruned<'_> {
+    type Node = Position;
+
+    fn successors(&self, pos: Position) -> Vec<(Position, usize)> {
+        let mut steps = self.steps.successors(pos);
+        if let Some(max_cost) = self.max_cost {
+            let cost = self.expanding.get();
+            steps.retain(|&(next, step_cost)| {
+                cost + step_cost + self.steps.heuristic(next, self.goal) <
+                    max_cost
+            });
+        }
+        steps
+    }
+}
+
+/// Run A* guided by `heuristic` until it reaches `goal` or `budget` is
+/// spent, telling `observer` about every step.
+fn bounded<H, O>(grid: &Grid,
+                 start: Position,
+                 goal: Position,
+                 movement: MovementModel,
+                 heuristic: H,
+                 budget: &Budget,
+                 observer: &mut O) -> BoundedResult
+        where H: Fn(Position) -> usize,
+              O: SearchObserver + ?Sized {
+    // `explore` checks for the goal before expanding each cell, so that is
+    // also where to stop when the budget runs out.
+    let out_of_budget = Cell::new(false);
+    let explored = Cell::new(0);
+    let is_goal = |p: Position| {
+        if p == goal {
+            return true;
+        }
+        if budget.is_spent(explored.get()) {
+            out_of_budget.set(true);
+            return true;
+        }
+        explored.set(explored.get() + 1);
+        false
+    };
+    let expanding = Cell::new(0);
+    let mut closest = Closest {
+        movement,
+        goal,
+        best: (movement.heuristic(start, goal), 0, start),
+        expanding: &expanding,
+        observer,
+    };
+    let graph = Pruned { steps: GridGraph::new(grid, movement),
+                         goal,
+                         max_cost: budget.max_cost,
+                         expanding: &expanding };
+    // `explore` reports the cell it stopped at as reaching the goal, even
+    // when it stopped because the budget ran out.
+    let mut part = Part { position: |p| p, observer: &mut closest };
+    let Exploration { reached, state: cells, expanded } =
+        explore(&graph, SearchCells::new(grid, false), start, is_goal,
+                heuristic, TieBreak::default(), &mut part);
+    let (_, _, end) = closest.best;
+    let observer = closest.observer;
+    if out_of_budget.get() {
+        let cost = cells.cost(end).unwrap();
+        return BoundedResult { path: Some((cells.path_to(end), cost)),
+                               complete: false,
+                               expanded };
+    }
+    BoundedResult {
+        path: reached.map(|goal| {
+            let cost = cells.cost(goal).unwrap();
+            observer.observe(SearchEvent::ReachedGoal { position: goal,
+                                                        cost });
+            (cells.path_to(goal), cost)
+        }),
+        complete: true,
+        expanded,
+    }
+}
+
+impl AStar {
+    /// Like `search` but giving up once `budget` is spent, in which case the
+    /// path returned only leads towards the goal.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bestpath::{AStar, Budget, Grid, MovementModel, Position};
+    ///
+    /// let grid = Grid::filled(100, 100, ' ');
+    /// let (start, goal) = (Position{x: 0, y: 0}, Position{x: 99, y: 99});
+    /// let budget = Budget::default().with_max_expansions(50);
+    /// let result = AStar.search_bounded(&grid, start, goal,
+    ///                                   MovementModel::FourWay, budget);
+    /// assert!(!result.complete);
+    /// assert_eq!(50, result.expanded);
+    /// let (path, cost) = result.path.unwrap();
+    /// assert_eq!(start, path[0]);
+    /// assert_eq!(path.len() - 1, cost);
+    /// ```
+    pub fn search_bounded(&self,
+                          grid: &Grid,
+                          start: Position,
+                          goal: Position,
+                          movement: MovementModel,
+                          budget: Budget) -> BoundedResult {
+        self.search_bounded_observed(grid, start, goal, movement, budget,
+                                     &mut ())
+    }
+
+    /// Like `search_bounded` but telling `observer` about every step of the
+    /// search. `ReachedGoal` is only reported if the goal was reached.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bestpath::{AStar, Budget, Grid, MovementModel, Position,
+    ///                SearchStats};
+    ///
+    /// let grid = Grid::filled(100, 100, ' ');
+    /// let (start, goal) = (Position{x: 0, y: 0}, Position{x: 99, y: 99});
+    /// let mut stats = SearchStats::default();
+    /// let result = AStar.search_bounded_observed(
+    ///     &grid, start, goal, MovementModel::FourWay,
+    ///     Budget::default().with_max_expansions(50), &mut stats);
+    /// assert_eq!(result.expanded, stats.expanded);
+    /// ```
+    pub fn search_bounded_observed<O>(&self,
+                                      grid: &Grid,
+                                      start: Position,
+                                      goal: Position,
+                                      movement: MovementModel,
+                                      budget: Budget,
+                                      observer: &mut O) -> BoundedResult
+            where O: SearchObserver + ?Sized {
+        bounded(grid, start, goal, movement, |p| movement.heuristic(p, goal),
+                &budget, observer)
+    }
+}
+
+/// Restarting weighted A*, an anytime search. Searches first with the
+/// heuristic multiplied by a large weight, which finds a path after
+/// expanding few cells but may not find the cheapest, then again with
+/// smaller and smaller weights until the weight is 1 and the path is the
+/// cheapest, or the budget runs out.
+///
+/// A path found with weight `w` costs at most `w` times the cheapest path.
+///
+/// Each search after the first starts again from scratch, unlike ARA*, but
+/// only looks for paths cheaper than the best found so far. It never steps
+/// to a cell from which the movement model's heuristic says the goal can't
+/// be reached for less.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct AnytimeAStar {
+    /// The weight of the first search, at least 1.
+    pub initial_weight: f64,
+    /// How much smaller the weight of each search is than the last.
+    pub weight_step: f64,
+}
+
+impl Default for AnytimeAStar {
+    fn default() -> AnytimeAStar {
+        AnytimeAStar { initial_weight: 3.0, weight_step: 0.5 }
+    }
+}
+
+impl AnytimeAStar {
+    /// Find a path from `start` to `goal` and keep improving it until it is
+    /// the cheapest or `budget` is spent. Each time a cheaper path is found,
+    /// `improved` is called with it, its cost and the weight it was found
+    /// with. The result holds the cheapest path found, or the best partial
+    /// path if the first search ran out of budget.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `initial_weight` is less than 1 or `weight_step` isn't
+    /// positive.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bestpath::{AStar, AnytimeAStar, Budget, Grid, MovementModel,
+    ///                Pathfinder, Position};
+    ///
+    /// let grid = Grid::new(&[vec![' ', ' ', ' ', ' ', ' '],
+    ///                        vec![' ', '█', '█', '█', ' '],
+    ///                        vec![' ', ' ', ' ', '█', ' ']]).unwrap();
+    /// let (start, goal) = (Position{x: 2, y: 2}, Position{x: 4, y: 2});
+    /// let mut costs = Vec::new();
+    /// let result = AnytimeAStar::default()
+    ///     .search(&grid, start, goal, MovementModel::FourWay,
+    ///             Budget::default(),
+    ///             |_, cost, _| costs.push(cost));
+    /// let (_, cost) = AStar.search(&grid, start, goal,
+    ///                              MovementModel::FourWay).path.unwrap();
+    /// assert_eq!(Some(&cost), costs.last());
+    /// assert_eq!(cost, result.path.unwrap().1);
+    /// assert!(result.complete);
+    /// ```
+    pub fn search<F>(&self,
+                     grid: &Grid,
+                     start: Position,
+                     goal: Position,
+                     movement: MovementModel,
+                     budget: Budget,
+                     mut improved: F) -> BoundedResult
+            where F: FnMut(&[Position], usize, f64) {
+        assert!(self.initial_weight >= 1.0,
+                "the initial weight must be at least 1");
+        assert!(self.weight_step > 0.0, "the weight step must be positive");
+        let mut best: Option<(Vec<Position>, usize)> = None;
+        let mut expanded = 0;
+        let mut weight = self.initial_weight;
+        loop {
+            let heuristic = |p: Position| {
+                (movement.heuristic(p, goal) as f64 * weight) as usize
+            };
+            let mut remaining = budget.remaining(expanded);
+            if let Some((_, cost)) = best {
+                remaining = remaining.below_cost(cost);
+            }
+            let result = bounded(grid, start, goal, movement, heuristic,
+                                 &remaining, &mut ());
+            expanded += result.expanded;
+            let result = BoundedResult { expanded, ..result };
+            match result {
+                // Only paths cheaper than the best are looked for.
+                BoundedResult { path: Some((path, cost)),
+                                complete: true, .. } => {
+                    improved(&path, cost, weight);
+                    best = Some((path, cost));
+                }
+                // The budget ran out during the first search, so there is
+                // nothing better than a partial path to return.
+                BoundedResult { complete: false, .. } if best.is_none() => {
+                    return result;
+                }
+                BoundedResult { complete: false, .. } => break,
+                // A weighted search doesn't look at every way to a cell, so
+                // a smaller weight may still find a cheaper path.
+                BoundedResult { path: None, .. } if best.is_some() => {}
+                // No weight can find a path if this one couldn't.
+                BoundedResult { path: None, .. } => return result,
+            }
+            if weight <= 1.0 {
+                break;
+            }
+            weight = (weight - self.weight_step).max(1.0);
+        }
+        BoundedResult { path: best, complete: true, expanded }
+    }
+}
+
+#[cfg(test)]
+use mazes::random_grid;
+#[cfg(test)]
+use {mazes, Pathfinder};
+
+#[cfg(test)]
+fn assert_valid_path(grid: &Grid, path: &[Position], cost: usize,
+                     movement: MovementModel) {
+    assert_eq!(cost, ::search::path_cost(grid, path, movement));
+}
+
+#[test]
+fn unlimited_budget_matches_a_star() {
+    for seed in 0..6 {
+        let grid = random_grid(seed, 20, 15, 4);
+        for &movement in [MovementModel::FourWay,
+                          MovementModel::EightWay].iter() {
+            let (start, goal) = (Position { x: 0, y: 0 },
+                                 Position { x: 19, y: 14 });
+            let expected = AStar.search(&grid, start, goal, movement);
+            let result = AStar.search_bounded(&grid, start, goal, movement,
+                                              Budget::default());
+            assert!(result.complete);
+            assert_eq!(expected.expanded, result.expanded);
+            assert_eq!(expected.path, result.path);
+        }
+    }
+}
+
+#[test]
+fn running_out_of_expansions_gives_a_partial_path() {
+    let grid = random_grid(3, 40, 30, 5);
+    let (start, goal) = (Position { x: 0, y: 0 }, Position { x: 39, y: 29 });
+    for &max in [0, 1, 10, 100].iter() {
+        let budget = Budget::default().with_max_expansions(max);
+        let result = AStar.search_bounded(&grid, start, goal,
+                                          MovementModel::EightWay, budget);
+        assert!(!result.complete);
+        assert_eq!(max, result.expanded);
+        let (path, cost) = result.path.unwrap();
+        assert_eq!(start, path[0]);
+        assert_valid_path(&grid, &path, cost, MovementModel::EightWay);
+        if max > 0 {
+            let end = *path.last().unwrap();
+            assert!(MovementModel::EightWay.heuristic(end, goal) <
+                    MovementModel::EightWay.heuristic(start, goal));
+        }
+    }
+}
+
+#[test]
+fn a_partial_path_ends_at_the_closest_cell_reached() {
+    let grid = random_grid(5, 40, 30, 4);
+    let (start, goal) = (Position { x: 0, y: 0 }, Position { x: 39, y: 29 });
+    let movement = MovementModel::FourWay;
+    for &max in [3, 30, 300].iter() {
+        let mut events: Vec<SearchEvent> = Vec::new();
+        let result = AStar.search_bounded_observed(
+            &grid, start, goal, movement,
+            Budget::default().with_max_expansions(max), &mut events);
+        let closest = events.iter()
+            .filter_map(|event| match *event {
+                SearchEvent::CameFrom { position, .. } => {
+                    Some(movement.heuristic(position, goal))
+                }
+                _ => None,
+            })
+            .min()
+            .unwrap();
+        let (path, _) = result.path.unwrap();
+        assert_eq!(closest, movement.heuristic(*path.last().unwrap(), goal));
+    }
+}
+
+#[test]
+fn a_passed_deadline_stops_straight_away() {
+    let maze = mazes::single_walled_path();
+    let grid = maze.grid();
+    let budget = Budget::default().with_deadline(Instant::now());
+    let result = AStar.search_bounded(&grid, maze.start, maze.goal,
+                                      MovementModel::FourWay, budget);
+    assert_eq!(BoundedResult { path: Some((vec![maze.start], 0)),
+                               complete: false,
+                               expanded: 0 },
+               result);
+}
+
+#[test]
+fn no_path_is_complete() {
+    let maze = mazes::no_path();
+    let grid = maze.grid();
+    let result = AStar.search_bounded(&grid, maze.start, maze.goal,
+                                      MovementModel::FourWay,
+                                      Budget::default());
+    assert_eq!(None, result.path);
+    assert!(result.complete);
+    let result = AnytimeAStar::default()
+        .search(&grid, maze.start, maze.goal, MovementModel::FourWay,
+                Budget::default(), |_, _, _| panic!("no path to improve"));
+    assert_eq!(None, result.path);
+    assert!(result.complete);
+}
+
+#[test]
+fn anytime_search_improves_to_the_cheapest_path() {
+    for seed in 0..6 {
+        let grid = random_grid(seed, 30, 20, 4);
+        let (start, goal) = (Position { x: 0, y: 0 },
+                             Position { x: 29, y: 19 });
+        let movement = MovementModel::EightWay;
+        let mut found: Vec<(usize, f64)> = Vec::new();
+        let result = AnytimeAStar::default()
+            .search(&grid, start, goal, movement, Budget::default(),
+                    |path, cost, weight| {
+                        assert_valid_path(&grid, path, cost, movement);
+                        found.push((cost, weight));
+                    });
+        let expected = AStar.search(&grid, start, goal, movement).path;
+        match expected {
+            Some((_, cheapest)) => {
+                assert_eq!(Some(cheapest), result.path.map(|(_, c)| c));
+                assert!(found.windows(2).all(|w| w[1].0 < w[0].0));
+                assert_eq!(3.0, found[0].1);
+                for &(cost, weight) in found.iter() {
+                    assert!(cost as f64 <= cheapest as f64 * weight);
+                }
+            }
+            None => assert!(found.is_empty()),
+        }
+    }
+}
+
+#[test]
+fn anytime_search_keeps_the_best_path_when_the_budget_runs_out() {
+    let grid = random_grid(1, 60, 40, 8);
+    let (start, goal) = (Position { x: 0, y: 0 }, Position { x: 59, y: 39 });
+    let movement = MovementModel::EightWay;
+    let greedy = AnytimeAStar { initial_weight: 5.0, weight_step: 4.0 };
+    let mut first = None;
+    let unlimited = greedy.search(&grid, start, goal, movement,
+                                  Budget::default(),
+                                  |_, cost, _| {
+                                      first = first.or(Some(cost));
+                                  });
+    let first = first.unwrap();
+
+    // Enough for the first search but not the second.
+    let mut weights = Vec::new();
+    let mut max = 1;
+    let result = loop {
+        let result = greedy.search(&grid, start, goal, movement,
+                                   Budget::default().with_max_expansions(max),
+                                   |_, _, weight| weights.push(weight));
+        if result.complete {
+            break result;
+        }
+        weights.clear();
+        max += 1;
+    };
+    assert_eq!(vec![5.0], weights);
+    assert_eq!(Some(first), result.path.map(|(_, c)| c));
+    assert!(result.expanded < unlimited.expanded);
+}
+
+#[test]
+fn searches_below_a_cost_leave_out_costlier_steps() {
+    for seed in 0..6 {
+        let grid = random_grid(seed, 30, 20, 4);
+        let (start, goal) = (Position { x: 0, y: 0 },
+                             Position { x: 29, y: 19 });
+        let movement = MovementModel::EightWay;
+        let heuristic = |p: Position| movement.heuristic(p, goal);
+        let cheapest = match AStar.search(&grid, start, goal, movement).path {
+            Some((_, cost)) => cost,
+            None => continue,
+        };
+        for &max_cost in [cheapest, cheapest + 1, cheapest * 2].iter() {
+            let mut events: Vec<SearchEvent> = Vec::new();
+            let result = bounded(&grid, start, goal, movement, heuristic,
+                                 &Budget::default().below_cost(max_cost),
+                                 &mut events);
+            assert!(result.complete);
+            assert_eq!(Some(cheapest).filter(|&cost| cost < max_cost),
+                       result.path.map(|(_, cost)| cost));
+            for event in events {
+                if let SearchEvent::CameFrom { position, cost, .. } = event {
+                    assert!(cost + heuristic(position) < max_cost);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,266 @@
+//! Labelling the connected regions of open cells, so that whether two cells
+//! are connected can be answered without searching.
+
+use {Grid, MovementModel, Pathfinder, Position, SearchResult};
+
+/// A connected region of open cells.
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
+pub struct Region {
+    /// The label shared by every cell of the region.
+    pub label: usize,
+    /// The number of cells in the region.
+    pub size: usize,
+    /// The first cell of the region, reading each row from left to right.
+    pub first: Position,
+}
+
+/// The connected regions of the passable cells of a grid, for a movement
+/// model. Two cells are in the same region if a path can move between them.
+///
+/// Building the labels visits every cell once. After that, checking whether
+/// two cells are connected takes constant time.
+///
+/// # Examples
+///
+/// ```
+/// use bestpath::{AStar, Components, Grid, MovementModel, Position};
+///
+/// let grid = Grid::new(&[vec![' ', ' ', '█', ' '],
+///                        vec!['█', '█', '█', ' '],
+///                        vec![' ', '█', ' ', ' ']]).unwrap();
+/// let components = Components::new(&grid, MovementModel::FourWay);
+/// assert_eq!(3, components.regions().len());
+/// assert!(!components.connected(Position{x: 0, y: 0}, Position{x: 3, y: 0}));
+/// assert!(components.connected(Position{x: 2, y: 2}, Position{x: 3, y: 0}));
+///
+/// // Searching for a path between regions gives up straight away.
+/// let result = components.search(&AStar, &grid, Position{x: 0, y: 0},
+///                                Position{x: 3, y: 0});
+/// assert_eq!((None, 0), (result.path, result.expanded));
+/// ```
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct Components {
+    width: usize,
+    height: usize,
+    movement: MovementModel,
+    labels: Vec<Option<usize>>,
+    regions: Vec<Region>,
+}
+
+/// A union-find forest over the cells of a grid, with path halving and union
+/// by size.
+struct DisjointSets {
+    parent: Vec<usize>,
+    size: Vec<usize>,
+}
+
+impl DisjointSets {
+    fn new(n: usize) -> DisjointSets {
+        DisjointSets { parent: (0..n).collect(), size: vec![1; n] }
+    }
+
+    fn find(&mut self, mut i: usize) -> usize {
+        while self.parent[i] != i {
+            self.parent[i] = self.parent[self.parent[i]];
+            i = self.parent[i];
+        }
+        i
+    }
+
+    fn union(&mut self, a: usize, b: usize) {
+        let (mut a, mut b) = (self.find(a), self.find(b));
+        if a == b {
+            return;
+        }
+        if self.size[a] < self.size[b] {
+            ::std::mem::swap(&mut a, &mut b);
+        }
+        self.parent[b] = a;
+        self.size[a] += self.size[b];
+    }
+}
+
+impl Components {
+    /// Label the connected regions of the passable cells of `grid`, where
+    /// `movement` gives the steps between cells.
+    pub fn new(grid: &Grid, movement: MovementModel) -> Components {
+        let width = grid.width();
+        let index = |p: Position| p.y * width + p.x;
+        let mut sets = DisjointSets::new(width * grid.height());
+        for p in grid.positions().filter(|&p| grid.is_passable(p)) {
+            // Steps are symmetric, so each pair only needs joining once.
+            for (next, _) in movement.neighbours(grid, p) {
+                if index(next) > index(p) {
+                    sets.union(index(p), index(next));
+                }
+            }
+        }
+
+        // Number the regions in the order that their first cells appear.
+        let mut labels = vec![None; width * grid.height()];
+        let mut label_of_root = vec![None; width * grid.height()];
+        let mut regions: Vec<Region> = Vec::new();
+        for p in grid.positions().filter(|&p| grid.is_passable(p)) {
+            let root = sets.find(index(p));
+            let label = *label_of_root[root].get_or_insert_with(|| {
+                regions.push(Region { label: regions.len(),
+                                      size: 0,
+                                      first: p });
+                regions.len() - 1
+            });
+            regions[label].size += 1;
+            labels[index(p)] = Some(label);
+        }
+        Components { width,
+                     height: grid.height(),
+                     movement,
+                     labels,
+                     regions }
+    }
+
+    /// The movement model that the regions were labelled for.
+    #[inline]
+    pub fn movement(&self) -> MovementModel {
+        self.movement
+    }
+
+    /// The label of the region containing `pos`, or `None` if `pos` is
+    /// impassable or outside of the grid.
+    #[inline]
+    pub fn label(&self, pos: Position) -> Option<usize> {
+        if pos.x < self.width && pos.y < self.height {
+            self.labels[pos.y * self.width + pos.x]
+        } else {
+            None
+        }
+    }
+
+    /// Returns true if `a` and `b` are passable and a path can move between
+    /// them.
+    #[inline]
+    pub fn connected(&self, a: Position, b: Position) -> bool {
+        match (self.label(a), self.label(b)) {
+            (Some(a), Some(b)) => a == b,
+            _ => false,
+        }
+    }
+
+    /// Every region, ordered by label. Any region other than the one
+    /// containing a given cell is isolated from it.
+    #[inline]
+    pub fn regions(&self) -> &[Region] {
+        &self.regions
+    }
+
+    /// The region with the most cells, or `None` if there are no passable
+    /// cells. Ties go to the region with the smallest label.
+    pub fn largest(&self) -> Option<Region> {
+        self.regions.iter().cloned().rev().max_by_key(|r| r.size)
+    }
+
+    /// Search `grid`, which must be the grid that the regions were labelled
+    /// from, with `pathfinder`, after first checking that `goal` can be
+    /// reached from `start`. If it can't, no cells are expanded.
+    ///
+    /// Searches may start on an impassable cell, so `start` is treated as
+    /// being in the regions of each of the cells that it can step into.
+    pub fn search<P>(&self,
+                     pathfinder: &P,
+                     grid: &Grid,
+                     start: Position,
+                     goal: Position) -> SearchResult
+            where P: Pathfinder + ?Sized {
+        let reachable = start == goal ||
+            match self.label(goal) {
+                Some(goal_label) => self.label(start) == Some(goal_label) ||
+                    self.movement.neighbours(grid, start)
+                        .into_iter()
+                        .any(|(p, _)| self.label(p) == Some(goal_label)),
+                None => false,
+            };
+        if reachable {
+            pathfinder.search(grid, start, goal, self.movement)
+        } else {
+            SearchResult { path: None, expanded: 0 }
+        }
+    }
+}
+
+#[cfg(test)]
+use mazes::{random_grid, ALL_MODELS};
+#[cfg(test)]
+use {mazes, AStar, BreadthFirst};
+
+#[test]
+fn connected_matches_search() {
+    for seed in 0..6 {
+        let grid = random_grid(seed, 9, 7, 3);
+        for &movement in ALL_MODELS.iter() {
+            let components = Components::new(&grid, movement);
+            let goal = Position { x: 4, y: 3 };
+            for start in grid.positions() {
+                let found = BreadthFirst.search(&grid, start, goal, movement)
+                    .path.is_some();
+                if grid.is_passable(start) && grid.is_passable(goal) {
+                    assert_eq!(found, components.connected(start, goal),
+                               "seed {} {:?} {:?}", seed, movement, start);
+                }
+                assert_eq!(found,
+                           components.search(&BreadthFirst, &grid, start,
+                                             goal).path.is_some(),
+                           "seed {} {:?} {:?}", seed, movement, start);
+            }
+        }
+    }
+}
+
+#[test]
+fn regions_cover_every_open_cell() {
+    for seed in 0..6 {
+        let grid = random_grid(seed, 20, 12, 3);
+        for &movement in ALL_MODELS.iter() {
+            let components = Components::new(&grid, movement);
+            let open = grid.positions().filter(|&p| grid.is_passable(p))
+                .count();
+            let total: usize = components.regions().iter()
+                .map(|r| r.size)
+                .sum();
+            assert_eq!(open, total);
+            for (i, region) in components.regions().iter().enumerate() {
+                assert_eq!(i, region.label);
+                assert_eq!(Some(i), components.label(region.first));
+            }
+            for p in grid.positions() {
+                assert_eq!(grid.is_passable(p), components.label(p).is_some());
+            }
+        }
+    }
+}
+
+#[test]
+fn diagonal_steps_join_regions() {
+    let grid = Grid::new(&[vec![' ', '█'],
+                           vec!['█', ' ']]).unwrap();
+    assert_eq!(2, Components::new(&grid, MovementModel::FourWay)
+                      .regions().len());
+    assert_eq!(1, Components::new(&grid, MovementModel::EightWay)
+                      .regions().len());
+    assert_eq!(2, Components::new(&grid,
+                                  MovementModel::EightWayNoCornerCutting)
+                      .regions().len());
+}
+
+#[test]
+fn unreachable_goals_need_no_search() {
+    let maze = mazes::no_path();
+    let grid = maze.grid();
+    let components = Components::new(&grid, MovementModel::FourWay);
+    let result = components.search(&AStar, &grid, maze.start, maze.goal);
+    assert_eq!(None, result.path);
+    assert_eq!(0, result.expanded);
+    assert!(AStar.search(&grid, maze.start, maze.goal,
+                         MovementModel::FourWay).expanded > 0);
+    assert_ne!(components.label(maze.start), components.label(maze.goal));
+    let largest = components.largest().unwrap();
+    assert!(components.regions().iter().all(|r| r.size <= largest.size));
+}
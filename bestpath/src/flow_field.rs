@@ -0,0 +1,251 @@
+//! Distance and direction fields towards a single goal, so that any number of
+//! agents can find their way to it from a single search.
+
+use search::{explore, SearchState};
+use search_cells::SearchCells;
+use {Graph, Grid, MovementModel, Position, SearchObserver, TieBreak,
+     GOAL_MARKER};
+
+/// The cost of the cheapest path from every cell of a grid to a goal, and the
+/// first step of that path.
+///
+/// # Examples
+///
+/// ```
+/// use bestpath::{flow_field, Grid, MovementModel, Position};
+///
+/// let grid = Grid::new(&[vec![' ', ' ', ' '],
+///                        vec![' ', '█', ' ']]).unwrap();
+/// let field = flow_field(&grid, Position{x: 2, y: 1}, MovementModel::FourWay);
+/// assert_eq!(Some(3), field.distance(Position{x: 0, y: 0}));
+/// assert_eq!(Some(Position{x: 1, y: 0}), field.next(Position{x: 0, y: 0}));
+/// assert_eq!(None, field.distance(Position{x: 1, y: 1}));
+/// assert_eq!(5, field.path_from(Position{x: 0, y: 1}).unwrap().len());
+/// ```
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct FlowField {
+    width: usize,
+    height: usize,
+    goal: Position,
+    distances: Vec<Option<usize>>,
+    next: Vec<Option<Position>>,
+}
+
+/// The steps between the cells of a grid followed backwards, so that a
+/// search outwards from a goal finds the cheapest path to it from every cell.
+struct ReverseSteps<'a> {
+    grid: &'a Grid<'a>,
+    movement: MovementModel,
+}
+
+impl Graph for ReverseSteps<'_> {
+    type Node = Position;
+
+    fn successors(&self, pos: Position) -> Vec<(Position, usize)> {
+        self.movement.reverse_neighbours(self.grid, pos)
+    }
+}
+
+/// Compute the `FlowField` towards `goal` with a single Dijkstra search
+/// outwards from the goal, following steps in reverse.
+pub fn flow_field(grid: &Grid, goal: Position, movement: MovementModel)
+        -> FlowField {
+    flow_field_observed(grid, goal, movement, &mut ())
+}
+
+/// Like `flow_field` but telling `observer` about every step of the search
+/// outwards from the goal.
+///
+/// # Examples
+///
+/// ```
+/// use bestpath::{flow_field_observed, Grid, MovementModel, Position,
+///                SearchStats};
+///
+/// let grid = Grid::filled(3, 2, ' ');
+/// let mut stats = SearchStats::default();
+/// flow_field_observed(&grid, Position{x: 0, y: 0}, MovementModel::FourWay,
+///                     &mut stats);
+/// assert_eq!(6, stats.expanded);
+/// ```
+pub fn flow_field_observed<O>(grid: &Grid,
+                              goal: Position,
+                              movement: MovementModel,
+                              observer: &mut O) -> FlowField
+        where O: SearchObserver + ?Sized {
+    let width = grid.width();
+    let cells = width * grid.height();
+    let mut field = FlowField { width,
+                                height: grid.height(),
+                                goal,
+                                distances: vec![None; cells],
+                                next: vec![None; cells] };
+    if !grid.contains(goal) {
+        return field;
+    }
+
+    // No cell is a goal, so every cell that can reach the goal is explored.
+    let state = explore(&ReverseSteps { grid, movement },
+                        SearchCells::new(grid, false),
+                        goal,
+                        |_| false,
+                        |_| 0,
+                        TieBreak::default(),
+                        observer).state;
+    for (i, pos) in grid.positions().enumerate() {
+        field.distances[i] = state.cost(pos);
+        field.next[i] = state.came_from(pos);
+    }
+    field
+}
+
+impl FlowField {
+    #[inline]
+    fn index(&self, pos: Position) -> Option<usize> {
+        if pos.x < self.width && pos.y < self.height {
+            Some(pos.y * self.width + pos.x)
+        } else {
+            None
+        }
+    }
+
+    /// The position that every path in the field leads to.
+    #[inline]
+    pub fn goal(&self) -> Position {
+        self.goal
+    }
+
+    /// The cost of the cheapest path from `pos` to the goal, or `None` if
+    /// there is no path or `pos` is impassable.
+    #[inline]
+    pub fn distance(&self, pos: Position) -> Option<usize> {
+        self.index(pos).and_then(|i| self.distances[i])
+    }
+
+    /// The first step of the cheapest path from `pos` to the goal, or `None`
+    /// if `pos` is the goal or there is no path.
+    #[inline]
+    pub fn next(&self, pos: Position) -> Option<Position> {
+        self.index(pos).and_then(|i| self.next[i])
+    }
+
+    /// The direction of the first step of the cheapest path from `pos` to the
+    /// goal, as an (x, y) offset, or `None` if `pos` is the goal or there is
+    /// no path.
+    pub fn direction(&self, pos: Position) -> Option<(isize, isize)> {
+        self.next(pos).map(|next| {
+            (next.x as isize - pos.x as isize,
+             next.y as isize - pos.y as isize)
+        })
+    }
+
+    /// Follow the field from `start` to the goal and return the path, or
+    /// `None` if there is no path.
+    pub fn path_from(&self, start: Position) -> Option<Vec<Position>> {
+        self.distance(start)?;
+        let mut path = vec![start];
+        let mut current = start;
+        while let Some(next) = self.next(current) {
+            path.push(next);
+            current = next;
+        }
+        Some(path)
+    }
+
+    /// Generate a copy of `grid` with an arrow in every cell that can reach
+    /// the goal pointing along its first step, and a 'X' at the goal. For hex
+    /// grids the arrows point at the neighbouring cell's offset coordinates.
+    pub fn format<'c>(&self, grid: &Grid<'c>) -> Grid<'c> {
+        let mut m = grid.clone();
+        for p in grid.positions() {
+            if p == self.goal {
+                m[p] = GOAL_MARKER;
+            } else if let Some(d) = self.direction(p) {
+                m[p] = match d {
+                    (-1, -1) => '↖',
+                    (0, -1) => '↑',
+                    (1, -1) => '↗',
+                    (-1, 0) => '←',
+                    (1, 0) => '→',
+                    (-1, 1) => '↙',
+                    (0, 1) => '↓',
+                    (1, 1) => '↘',
+                    _ => unreachable!("{:?} is not a step", d),
+                };
+            }
+        }
+        m
+    }
+}
+
+#[cfg(test)]
+use mazes::{random_grid, ALL_MODELS};
+#[cfg(test)]
+use search::path_cost;
+#[cfg(test)]
+use {mazes, Dijkstra, Pathfinder};
+
+#[test]
+fn distances_match_dijkstra_from_every_cell() {
+    for seed in 0..8 {
+        let grid = random_grid(seed, 9, 7, 4);
+        let goal = Position { x: 4, y: 3 };
+        for &movement in ALL_MODELS.iter() {
+            let field = flow_field(&grid, goal, movement);
+            for start in grid.positions() {
+                let expected = match grid.is_passable(start) || start == goal {
+                    true => Dijkstra.search(&grid, start, goal, movement)
+                        .path.map(|(_, cost)| cost),
+                    false => None,
+                };
+                assert_eq!(expected, field.distance(start),
+                           "seed {} {:?} {:?}", seed, movement, start);
+            }
+        }
+    }
+}
+
+#[test]
+fn walking_the_field_follows_the_cheapest_path() {
+    for seed in 0..8 {
+        let grid = random_grid(seed, 9, 7, 4);
+        let goal = Position { x: 8, y: 0 };
+        for &movement in ALL_MODELS.iter() {
+            let field = flow_field(&grid, goal, movement);
+            for start in grid.positions() {
+                match field.path_from(start) {
+                    Some(path) => {
+                        assert_eq!(Some(&goal), path.last());
+                        assert_eq!(field.distance(start),
+                                   Some(path_cost(&grid, &path, movement)));
+                    }
+                    None => assert_eq!(None, field.distance(start)),
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn unreachable_cells_have_no_direction() {
+    let maze = mazes::no_path();
+    let field = flow_field(&maze.grid(), maze.goal, MovementModel::FourWay);
+    assert_eq!(None, field.distance(maze.start));
+    assert_eq!(None, field.direction(maze.start));
+    assert_eq!(None, field.path_from(maze.start));
+    assert_eq!(Some(vec![maze.goal]), field.path_from(maze.goal));
+    assert_eq!(None, field.distance(Position { x: 7, y: 0 }));
+}
+
+#[test]
+fn formats_arrows_towards_goal() {
+    let grid = Grid::new(&[vec![' ', ' ', ' ', ' '],
+                           vec![' ', '█', '█', ' '],
+                           vec![' ', ' ', '█', ' ']]).unwrap();
+    let field = flow_field(&grid, Position { x: 3, y: 2 },
+                           MovementModel::EightWayNoCornerCutting);
+    assert_eq!("→→→↓\n\
+                ↑██↓\n\
+                ↑←█X\n",
+               field.format(&grid).to_string());
+}
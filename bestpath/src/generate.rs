@@ -0,0 +1,390 @@
+//! Seeded maze generators, for fuzzing searches and building large maps
+//! that are the same every time.
+
+use {BreadthFirst, Grid, MovementModel, Pathfinder, Position};
+
+/// The character that generators use for walls. Open cells are spaces.
+pub const WALL: char = '█';
+
+/// A small pseudo-random number generator (SplitMix64), so that the same
+/// seed always produces the same maze on every platform.
+#[derive(Clone, Debug)]
+pub(crate) struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    pub fn new(seed: u64) -> Rng {
+        Rng { state: seed }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    /// A number in `0..n`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `n` is zero.
+    pub fn below(&mut self, n: usize) -> usize {
+        (self.next_u64() % n as u64) as usize
+    }
+
+    /// A number in `low..=high`.
+    pub fn between(&mut self, low: usize, high: usize) -> usize {
+        low + self.below(high - low + 1)
+    }
+
+    /// Returns true with the given probability.
+    pub fn chance(&mut self, probability: f64) -> bool {
+        // The top 53 bits fill the mantissa of a float in 0..1.
+        ((self.next_u64() >> 11) as f64 / (1u64 << 53) as f64) < probability
+    }
+}
+
+/// Builds a grid of walls (`WALL`) and open cells (spaces), with the
+/// uniform cost function. The same seed always builds the same grid.
+///
+/// # Examples
+///
+/// ```
+/// use bestpath::{find_path, MazeGenerator, Position, RecursiveBacktracker};
+///
+/// let start = Position{x: 0, y: 0};
+/// let goal = Position{x: 30, y: 20};
+/// let grid = RecursiveBacktracker.generate_solvable(31, 21, start, goal, 7);
+/// assert_eq!(grid.to_rows(),
+///            RecursiveBacktracker.generate_solvable(31, 21, start, goal, 7)
+///                .to_rows());
+/// assert!(find_path(&grid.to_rows(), start, goal).is_some());
+/// ```
+pub trait MazeGenerator {
+    /// Build a `width` x `height` grid from `seed`.
+    fn generate(&self, width: usize, height: usize, seed: u64) -> Grid<'static>;
+
+    /// Build a `width` x `height` grid from `seed` and then make sure that
+    /// `start` and `goal` are open and joined, by carving a winding corridor
+    /// between them if they aren't.
+    ///
+    /// The cells are joined by orthogonal steps, so there is a path between
+    /// them with every `MovementModel`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `start` or `goal` is outside of the grid.
+    fn generate_solvable(&self,
+                         width: usize,
+                         height: usize,
+                         start: Position,
+                         goal: Position,
+                         seed: u64) -> Grid<'static> {
+        let mut grid = self.generate(width, height, seed);
+        assert!(grid.contains(start) && grid.contains(goal),
+                "start and goal must be inside the grid");
+        grid[start] = ' ';
+        grid[goal] = ' ';
+        let joined = BreadthFirst.search(&grid, start, goal,
+                                         MovementModel::FourWay)
+            .path.is_some();
+        if !joined {
+            carve_corridor(&mut grid, start, goal,
+                           &mut Rng::new(!seed));
+        }
+        grid
+    }
+}
+
+/// Open every cell of a random walk from `from` to `to` that only ever
+/// steps towards `to`.
+fn carve_corridor(grid: &mut Grid, from: Position, to: Position,
+                  rng: &mut Rng) {
+    let mut p = from;
+    grid[p] = ' ';
+    while p != to {
+        let step_x = p.x != to.x && (p.y == to.y || rng.chance(0.5));
+        if step_x {
+            p.x = if to.x > p.x { p.x + 1 } else { p.x - 1 };
+        } else {
+            p.y = if to.y > p.y { p.y + 1 } else { p.y - 1 };
+        }
+        grid[p] = ' ';
+    }
+}
+
+/// A perfect maze, with exactly one path between any two open cells, carved
+/// by a depth-first walk that backs up when it gets stuck. The passages are
+/// long and winding.
+///
+/// Open cells are at even coordinates, with the cells between them opened to
+/// join them, so odd widths and heights leave no dead border.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct RecursiveBacktracker;
+
+/// A perfect maze grown outwards from the top left corner by opening a
+/// random wall on its border each time (randomized Prim's algorithm). The
+/// passages branch often and have many short dead ends.
+///
+/// Open cells are laid out as for `RecursiveBacktracker`.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct Prim;
+
+/// Each cell is a wall with probability `density`, independently.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct RandomObstacles {
+    pub density: f64,
+}
+
+/// Rectangular rooms that don't touch, each joined to the one placed before
+/// it by a corridor with a single bend, so every open cell is reachable.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct RoomsAndCorridors;
+
+/// The cells of a perfect maze next to `cell`, two steps away in each
+/// direction, along with the cell between them.
+fn maze_neighbours(grid: &Grid, cell: Position)
+        -> Vec<(Position, Position)> {
+    [(0, -1), (1, 0), (0, 1), (-1, 0)]
+        .iter()
+        .filter_map(|&(dx, dy)| {
+            let between = grid.offset(cell, (dx, dy))?;
+            grid.offset(between, (dx, dy)).map(|next| (next, between))
+        })
+        .collect()
+}
+
+impl MazeGenerator for RecursiveBacktracker {
+    fn generate(&self, width: usize, height: usize, seed: u64)
+            -> Grid<'static> {
+        let mut grid = Grid::filled(width, height, WALL);
+        if width == 0 || height == 0 {
+            return grid;
+        }
+        let mut rng = Rng::new(seed);
+        let start = Position { x: 0, y: 0 };
+        grid[start] = ' ';
+        let mut stack = vec![start];
+        while let Some(&cell) = stack.last() {
+            let unvisited: Vec<(Position, Position)> =
+                maze_neighbours(&grid, cell)
+                    .into_iter()
+                    .filter(|&(next, _)| grid[next] == WALL)
+                    .collect();
+            if unvisited.is_empty() {
+                stack.pop();
+                continue;
+            }
+            let (next, between) = unvisited[rng.below(unvisited.len())];
+            grid[between] = ' ';
+            grid[next] = ' ';
+            stack.push(next);
+        }
+        grid
+    }
+}
+
+impl MazeGenerator for Prim {
+    fn generate(&self, width: usize, height: usize, seed: u64)
+            -> Grid<'static> {
+        let mut grid = Grid::filled(width, height, WALL);
+        if width == 0 || height == 0 {
+            return grid;
+        }
+        let mut rng = Rng::new(seed);
+        let start = Position { x: 0, y: 0 };
+        grid[start] = ' ';
+        let mut frontier = maze_neighbours(&grid, start);
+        while !frontier.is_empty() {
+            let i = rng.below(frontier.len());
+            let (next, between) = frontier.swap_remove(i);
+            if grid[next] != WALL {
+                continue;
+            }
+            grid[between] = ' ';
+            grid[next] = ' ';
+            frontier.extend(maze_neighbours(&grid, next)
+                .into_iter()
+                .filter(|&(p, _)| grid[p] == WALL));
+        }
+        grid
+    }
+}
+
+impl MazeGenerator for RandomObstacles {
+    fn generate(&self, width: usize, height: usize, seed: u64)
+            -> Grid<'static> {
+        let mut grid = Grid::filled(width, height, ' ');
+        let mut rng = Rng::new(seed);
+        for p in grid.clone().positions() {
+            if rng.chance(self.density) {
+                grid[p] = WALL;
+            }
+        }
+        grid
+    }
+}
+
+/// The smallest and largest sides of a room, walls not included.
+const ROOM_SIDES: (usize, usize) = (3, 8);
+
+impl MazeGenerator for RoomsAndCorridors {
+    fn generate(&self, width: usize, height: usize, seed: u64)
+            -> Grid<'static> {
+        let mut grid = Grid::filled(width, height, WALL);
+        let mut rng = Rng::new(seed);
+        // Each room is (left, top, width, height).
+        let mut rooms: Vec<(usize, usize, usize, usize)> = Vec::new();
+        let attempts = (width * height / 16).max(1);
+        for _ in 0..attempts {
+            let max_w = ROOM_SIDES.1.min(width.saturating_sub(2));
+            let max_h = ROOM_SIDES.1.min(height.saturating_sub(2));
+            if max_w < ROOM_SIDES.0 || max_h < ROOM_SIDES.0 {
+                break;
+            }
+            let w = rng.between(ROOM_SIDES.0, max_w);
+            let h = rng.between(ROOM_SIDES.0, max_h);
+            let x = rng.between(1, width - w - 1);
+            let y = rng.between(1, height - h - 1);
+            // Keep at least one wall between rooms.
+            let overlaps = rooms.iter().any(|&(rx, ry, rw, rh)| {
+                x <= rx + rw && rx <= x + w && y <= ry + rh && ry <= y + h
+            });
+            if overlaps {
+                continue;
+            }
+            for ry in y..y + h {
+                for rx in x..x + w {
+                    grid[Position { x: rx, y: ry }] = ' ';
+                }
+            }
+            let centre = Position { x: x + w / 2, y: y + h / 2 };
+            if let Some(&(px, py, pw, ph)) = rooms.last() {
+                let previous = Position { x: px + pw / 2, y: py + ph / 2 };
+                let bend = if rng.chance(0.5) {
+                    Position { x: centre.x, y: previous.y }
+                } else {
+                    Position { x: previous.x, y: centre.y }
+                };
+                // Each leg shares a row or column, so it is straight.
+                carve_corridor(&mut grid, previous, bend, &mut rng);
+                carve_corridor(&mut grid, bend, centre, &mut rng);
+            }
+            rooms.push((x, y, w, h));
+        }
+        grid
+    }
+}
+
+#[cfg(test)]
+use std::collections::VecDeque;
+
+/// The number of open cells that can be reached from the first open cell
+/// with orthogonal steps, and the number of open cells.
+#[cfg(test)]
+fn reachable(grid: &Grid) -> (usize, usize) {
+    let open: Vec<Position> = grid.positions()
+        .filter(|&p| grid.is_passable(p))
+        .collect();
+    let mut seen = vec![false; grid.width() * grid.height()];
+    let mut queue: VecDeque<Position> = open.first().cloned().into_iter()
+        .collect();
+    let mut count = 0;
+    while let Some(p) = queue.pop_front() {
+        if seen[p.y * grid.width() + p.x] {
+            continue;
+        }
+        seen[p.y * grid.width() + p.x] = true;
+        count += 1;
+        for (next, _) in MovementModel::FourWay.neighbours(grid, p) {
+            queue.push_back(next);
+        }
+    }
+    (count, open.len())
+}
+
+#[cfg(test)]
+use mazes::ALL_MODELS;
+
+#[cfg(test)]
+fn all_generators() -> Vec<Box<dyn MazeGenerator>> {
+    vec![Box::new(RecursiveBacktracker),
+         Box::new(Prim),
+         Box::new(RandomObstacles { density: 0.35 }),
+         Box::new(RoomsAndCorridors)]
+}
+
+#[test]
+fn same_seed_same_grid() {
+    for generator in all_generators() {
+        let a = generator.generate(25, 15, 42).to_rows();
+        assert_eq!(a, generator.generate(25, 15, 42).to_rows());
+        assert_ne!(a, generator.generate(25, 15, 43).to_rows());
+    }
+}
+
+#[test]
+fn perfect_mazes_form_a_tree() {
+    let generators: [&dyn MazeGenerator; 2] = [&RecursiveBacktracker, &Prim];
+    for generator in generators.iter() {
+        for seed in 0..5 {
+            let grid = generator.generate(21, 15, seed);
+            let (reached, open) = reachable(&grid);
+            assert_eq!(open, reached);
+            // Every cell at even coordinates is open, and a tree joining
+            // them has one fewer passage than cells.
+            let cells = 11 * 8;
+            assert_eq!(cells + cells - 1, open);
+        }
+    }
+}
+
+#[test]
+fn rooms_are_all_joined() {
+    for seed in 0..10 {
+        let grid = RoomsAndCorridors.generate(40, 30, seed);
+        let (reached, open) = reachable(&grid);
+        assert_eq!(open, reached);
+        assert!(open > 40 * 30 / 5);
+    }
+}
+
+#[test]
+fn obstacle_density_is_roughly_respected() {
+    let grid = RandomObstacles { density: 0.25 }.generate(100, 100, 1);
+    let walls = grid.positions().filter(|&p| grid[p] == WALL).count();
+    assert!(walls > 2300 && walls < 2700, "{} walls", walls);
+    let empty = RandomObstacles { density: 0.0 }.generate(10, 10, 1);
+    assert!(empty.positions().all(|p| empty.is_passable(p)));
+}
+
+#[test]
+fn solvable_mazes_have_paths_with_every_movement_model() {
+    let start = Position { x: 1, y: 2 };
+    let goal = Position { x: 22, y: 13 };
+    for generator in all_generators()
+            .into_iter()
+            .chain(Some(Box::new(RandomObstacles { density: 0.9 })
+                        as Box<dyn MazeGenerator>)) {
+        for seed in 0..8 {
+            let grid = generator.generate_solvable(24, 15, start, goal, seed);
+            for &movement in ALL_MODELS.iter() {
+                assert!(BreadthFirst.search(&grid, start, goal, movement)
+                            .path.is_some());
+            }
+        }
+    }
+}
+
+#[test]
+fn degenerate_sizes() {
+    for generator in all_generators() {
+        assert_eq!(0, generator.generate(0, 0, 1).width());
+        let grid = generator.generate_solvable(1, 1, Position { x: 0, y: 0 },
+                                               Position { x: 0, y: 0 }, 1);
+        assert!(grid.is_passable(Position { x: 0, y: 0 }));
+    }
+}
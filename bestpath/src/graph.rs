@@ -0,0 +1,334 @@
+//! A* over any graph, not just grids of characters.
+
+use std::collections::HashMap;
+use std::hash::Hash;
+
+use search::{best_first, SearchState};
+use {AStar, Grid, MovementModel, Position, SearchObserver, SearchResult,
+     TieBreak};
+
+/// A graph that `AStar::search_graph` can find paths through.
+///
+/// # Examples
+///
+/// ```
+/// use bestpath::{AStar, Graph};
+///
+/// // Towns joined by roads, with the length of each road.
+/// struct Roads(Vec<Vec<(usize, usize)>>);
+///
+/// impl Graph for Roads {
+///     type Node = usize;
+///
+///     fn successors(&self, town: usize) -> Vec<(usize, usize)> {
+///         self.0[town].clone()
+///     }
+/// }
+///
+/// let roads = Roads(vec![vec![(1, 7), (2, 2)],
+///                        vec![(3, 1)],
+///                        vec![(1, 3), (3, 8)],
+///                        vec![]]);
+/// let (path, cost) = AStar.search_graph(&roads, 0, 3).path.unwrap();
+/// assert_eq!(vec![0, 2, 1, 3], path);
+/// assert_eq!(6, cost);
+/// ```
+pub trait Graph {
+    /// Identifies a node of the graph.
+    type Node: Copy + Eq + Hash;
+
+    /// The nodes that can be reached from `node` by following a single edge,
+    /// along with the cost of each edge.
+    fn successors(&self, node: Self::Node) -> Vec<(Self::Node, usize)>;
+
+    /// An estimate of the cost of the cheapest path from `node` to `goal`.
+    /// For the path found to be the cheapest it must never be more than the
+    /// true cost. The default of zero makes A* a Dijkstra search.
+    fn heuristic(&self, _node: Self::Node, _goal: Self::Node) -> usize {
+        0
+    }
+}
+
+/// A `Grid` seen as a graph, where `movement` gives the edges between cells.
+///
+/// # Examples
+///
+/// ```
+/// use bestpath::{AStar, Grid, GridGraph, MovementModel, Pathfinder,
+///                Position};
+///
+/// let grid = Grid::new(&[vec![' ', ' ', ' '],
+///                        vec![' ', '█', ' '],
+///                        vec![' ', ' ', ' ']]).unwrap();
+/// let graph = GridGraph::new(&grid, MovementModel::FourWay);
+/// let start = Position{x: 0, y: 0};
+/// let goal = Position{x: 2, y: 2};
+/// let (_, cost) = AStar.search(&grid, start, goal, MovementModel::FourWay)
+///     .path.unwrap();
+/// let (path, graph_cost) = AStar.search_graph(&graph, start, goal)
+///     .path.unwrap();
+/// assert_eq!(cost, graph_cost);
+/// assert_eq!(5, path.len());
+/// ```
+#[derive(Copy, Clone, Debug)]
+pub struct GridGraph<'a> {
+    pub grid: &'a Grid<'a>,
+    pub movement: MovementModel,
+}
+
+impl<'a> GridGraph<'a> {
+    pub fn new(grid: &'a Grid, movement: MovementModel) -> GridGraph<'a> {
+        GridGraph { grid, movement }
+    }
+}
+
+impl Graph for GridGraph<'_> {
+    type Node = Position;
+
+    fn successors(&self, pos: Position) -> Vec<(Position, usize)> {
+        self.movement.neighbours(self.grid, pos)
+    }
+
+    fn heuristic(&self, pos: Position, goal: Position) -> usize {
+        self.movement.heuristic(pos, goal)
+    }
+}
+
+/// What `explore` remembers about a node of a graph.
+struct NodeEntry<N> {
+    cost: usize,
+    came_from: Option<N>,
+    closed: bool,
+    // The number of nodes reached before this one.
+    order: usize,
+}
+
+/// What `explore` remembers about the nodes of any graph, which can only be
+/// hashed. Nodes are ordered by when they were first reached.
+pub(crate) struct NodeMap<N> {
+    nodes: HashMap<N, NodeEntry<N>>,
+}
+
+impl<N: Copy + Eq + Hash> NodeMap<N> {
+    pub fn new() -> NodeMap<N> {
+        NodeMap { nodes: HashMap::new() }
+    }
+}
+
+impl<N: Copy + Eq + Hash> SearchState<N> for NodeMap<N> {
+    #[inline]
+    fn cost(&self, node: N) -> Option<usize> {
+        self.nodes.get(&node).map(|entry| entry.cost)
+    }
+
+    #[inline]
+    fn came_from(&self, node: N) -> Option<N> {
+        self.nodes.get(&node).and_then(|entry| entry.came_from)
+    }
+
+    fn reach(&mut self, node: N, cost: usize, came_from: Option<N>) {
+        let order = self.nodes.len();
+        let entry = self.nodes.entry(node).or_insert(NodeEntry {
+            cost,
+            came_from,
+            closed: false,
+            order,
+        });
+        entry.cost = cost;
+        entry.came_from = came_from;
+    }
+
+    fn close(&mut self, node: N) -> bool {
+        match self.nodes.get_mut(&node) {
+            Some(entry) if !entry.closed => {
+                entry.closed = true;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    #[inline]
+    fn is_closed(&self, node: N) -> bool {
+        self.nodes.get(&node).is_some_and(|entry| entry.closed)
+    }
+
+    #[inline]
+    fn order(&self, node: N) -> [usize; 2] {
+        [self.nodes[&node].order, 0]
+    }
+}
+
+impl AStar {
+    /// Find the cheapest path from `start` to `goal` through `graph`, guided
+    /// by the graph's heuristic. `expanded` counts the nodes expanded.
+    pub fn search_graph<G>(&self, graph: &G, start: G::Node, goal: G::Node)
+            -> SearchResult<G::Node>
+            where G: Graph + ?Sized {
+        self.search_graph_with(graph, start, goal, TieBreak::default(),
+                               &mut ())
+    }
+
+    /// Like `search_graph` but breaking ties between equally promising nodes
+    /// with `tie_break` and telling `observer` about every step of the
+    /// search.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bestpath::{AStar, Graph, SearchStats, TieBreak};
+    ///
+    /// // The numbers from 0 to 99, where a number leads to its double and
+    /// // to the next number.
+    /// struct Numbers;
+    ///
+    /// impl Graph for Numbers {
+    ///     type Node = u32;
+    ///
+    ///     fn successors(&self, n: u32) -> Vec<(u32, usize)> {
+    ///         [n + 1, n * 2].iter()
+    ///             .filter(|&&m| m < 100)
+    ///             .map(|&m| (m, 1))
+    ///             .collect()
+    ///     }
+    /// }
+    ///
+    /// let mut stats = SearchStats::default();
+    /// let result = AStar.search_graph_with(&Numbers, 1, 40,
+    ///                                      TieBreak::default(), &mut stats);
+    /// assert_eq!(vec![1, 2, 4, 5, 10, 20, 40], result.path.unwrap().0);
+    /// assert_eq!(result.expanded, stats.expanded);
+    /// ```
+    pub fn search_graph_with<G, O>(&self,
+                                   graph: &G,
+                                   start: G::Node,
+                                   goal: G::Node,
+                                   tie_break: TieBreak,
+                                   observer: &mut O) -> SearchResult<G::Node>
+            where G: Graph + ?Sized,
+                  O: SearchObserver<G::Node> + ?Sized {
+        best_first(graph, NodeMap::new(), start, |node| node == goal,
+                   |node| graph.heuristic(node, goal), tie_break, observer)
+    }
+}
+
+#[cfg(test)]
+use mazes::{random_grid, ALL_MODELS};
+#[cfg(test)]
+use {mazes, Pathfinder, SearchEvent};
+
+#[test]
+fn grid_graph_matches_grid_search() {
+    for seed in 0..6 {
+        let grid = random_grid(seed, 12, 9, 4);
+        for &movement in ALL_MODELS.iter() {
+            let graph = GridGraph::new(&grid, movement);
+            for &goal in [Position { x: 11, y: 8 },
+                          Position { x: 5, y: 0 }].iter() {
+                let start = Position { x: 0, y: 2 };
+                let expected = AStar.search(&grid, start, goal, movement);
+                let result = AStar.search_graph(&graph, start, goal);
+                assert_eq!(expected.path.map(|(_, cost)| cost),
+                           result.path.map(|(_, cost)| cost),
+                           "seed {} {:?}", seed, movement);
+            }
+        }
+    }
+}
+
+#[test]
+fn no_path_in_graph() {
+    let maze = mazes::no_path();
+    let grid = maze.grid();
+    let graph = GridGraph::new(&grid, MovementModel::FourWay);
+    assert_eq!(None, AStar.search_graph(&graph, maze.start, maze.goal).path);
+    assert_eq!(Some((vec![maze.start], 0)),
+               AStar.search_graph(&graph, maze.start, maze.start).path);
+}
+
+/// A solid block of voxels with some filled in, moving along the three
+/// axes.
+#[cfg(test)]
+struct Voxels {
+    size: i32,
+    solid: Vec<(i32, i32, i32)>,
+}
+
+#[cfg(test)]
+impl Graph for Voxels {
+    type Node = (i32, i32, i32);
+
+    fn successors(&self, (x, y, z): (i32, i32, i32))
+            -> Vec<((i32, i32, i32), usize)> {
+        [(1, 0, 0), (-1, 0, 0), (0, 1, 0), (0, -1, 0), (0, 0, 1), (0, 0, -1)]
+            .iter()
+            .map(|&(dx, dy, dz)| (x + dx, y + dy, z + dz))
+            .filter(|&(x, y, z)| {
+                [x, y, z].iter().all(|&c| c >= 0 && c < self.size)
+            })
+            .filter(|v| !self.solid.contains(v))
+            .map(|v| (v, 1))
+            .collect()
+    }
+
+    fn heuristic(&self, (x, y, z): (i32, i32, i32),
+                 (gx, gy, gz): (i32, i32, i32)) -> usize {
+        ((x - gx).abs() + (y - gy).abs() + (z - gz).abs()) as usize
+    }
+}
+
+#[test]
+fn searches_in_three_dimensions() {
+    // A floor at z = 1 with one hole in a corner.
+    let mut solid = Vec::new();
+    for x in 0..4 {
+        for y in 0..4 {
+            if (x, y) != (3, 3) {
+                solid.push((x, y, 1));
+            }
+        }
+    }
+    let voxels = Voxels { size: 4, solid };
+    let (path, cost) = AStar.search_graph(&voxels, (0, 0, 0), (0, 0, 2))
+        .path.unwrap();
+    assert_eq!(14, cost);
+    assert!(path.contains(&(3, 3, 1)));
+
+    let guided = AStar.search_graph(&voxels, (0, 0, 0), (3, 0, 0));
+    let dijkstra = AStar.search_graph(&ZeroHeuristic(&voxels), (0, 0, 0),
+                                      (3, 0, 0));
+    assert_eq!(dijkstra.path.map(|(_, c)| c), guided.path.map(|(_, c)| c));
+    assert!(dijkstra.expanded > guided.expanded);
+}
+
+#[test]
+fn graph_searches_are_observed_with_any_tie_break() {
+    let voxels = Voxels { size: 3, solid: vec![(1, 1, 1)] };
+    for &tie_break in [TieBreak::LargerCost, TieBreak::Position,
+                       TieBreak::FewerTurns].iter() {
+        let mut events = Vec::new();
+        let result = AStar.search_graph_with(&voxels, (0, 0, 0), (2, 2, 2),
+                                             tie_break, &mut events);
+        assert_eq!(Some(6), result.path.map(|(_, cost)| cost));
+        let expanded = events.iter()
+            .filter(|e| matches!(e, SearchEvent::Expanded { .. }))
+            .count();
+        assert_eq!(result.expanded, expanded);
+        assert_eq!(Some(&SearchEvent::ReachedGoal { position: (2, 2, 2),
+                                                   cost: 6 }),
+                   events.last());
+    }
+}
+
+/// Another graph's edges without its heuristic.
+#[cfg(test)]
+struct ZeroHeuristic<'a, G: 'a>(&'a G);
+
+#[cfg(test)]
+impl<G: Graph> Graph for ZeroHeuristic<'_, G> {
+    type Node = G::Node;
+
+    fn successors(&self, node: G::Node) -> Vec<(G::Node, usize)> {
+        self.0.successors(node)
+    }
+}
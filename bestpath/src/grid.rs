@@ -0,0 +1,502 @@
+//! A rectangular grid of characters with a configurable cost for entering
+//! each cell.
+
+use std::convert::TryFrom;
+use std::error::Error;
+use std::fmt;
+use std::ops::{Index, IndexMut};
+use std::sync::Arc;
+
+use {uniform_cost, Position, GOAL_MARKER, START_MARKER};
+
+/// Returned when building a `Grid` from rows that are not all the same length.
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub struct RaggedRowsError {
+    /// The index of the first row whose length differs from the first row.
+    pub row: usize,
+    /// The length of the first row.
+    pub expected: usize,
+    /// The length of the offending row.
+    pub found: usize,
+}
+
+impl fmt::Display for RaggedRowsError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "row {} has {} cells but row 0 has {}",
+               self.row, self.found, self.expected)
+    }
+}
+
+impl Error for RaggedRowsError {}
+
+/// The cost of moving into a cell given its character, or `None` if it is
+/// impassable.
+type CostFn<'c> = dyn Fn(char) -> Option<usize> + Send + Sync + 'c;
+
+/// A rectangular, 2-dimensional grid of characters stored row by row in a
+/// single `Vec`.
+///
+/// Each grid has a cost function that gives the cost of moving into a cell
+/// given its character, or `None` if the cell is an impassable obstacle. By
+/// default this is `uniform_cost` i.e. spaces are open and everything else is
+/// an obstacle. The cost function can borrow data that lives for `'c`, such
+/// as a table of terrain costs.
+///
+/// # Examples
+///
+/// ```
+/// use bestpath::{terrain_cost, Grid, Position};
+///
+/// let grid = Grid::new(&[vec![' ', '█'],
+///                        vec!['7', ' ']]).unwrap().with_cost(terrain_cost);
+/// assert_eq!((2, 2), (grid.width(), grid.height()));
+/// assert_eq!(Some(7), grid.cost(Position{x: 0, y: 1}));
+/// assert!(!grid.is_passable(Position{x: 1, y: 0}));
+/// assert_eq!(vec![vec![' ', '█'], vec!['7', ' ']], grid.to_rows());
+/// ```
+#[derive(Clone)]
+pub struct Grid<'c> {
+    width: usize,
+    height: usize,
+    cells: Vec<char>,
+    // The length of each row when some are shorter than `width`, in which
+    // case the cells past the end of a row are missing from the grid.
+    lengths: Option<Vec<usize>>,
+    cost: Arc<CostFn<'c>>,
+}
+
+impl<'c> fmt::Debug for Grid<'c> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Grid")
+         .field("width", &self.width)
+         .field("height", &self.height)
+         .field("rows", &self.to_rows())
+         .finish()
+    }
+}
+
+impl<'c> fmt::Display for Grid<'c> {
+    /// Writes the grid one row per line.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for row in self.to_rows() {
+            writeln!(f, "{}", row.into_iter().collect::<String>())?;
+        }
+        Ok(())
+    }
+}
+
+impl Grid<'static> {
+    /// Constructs a grid from a list of rows, which must all be the same
+    /// length.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bestpath::Grid;
+    ///
+    /// assert!(Grid::new(&[vec![' ', ' '], vec![' ', ' ']]).is_ok());
+    /// let err = Grid::new(&[vec![' ', ' '], vec![' ']]).unwrap_err();
+    /// assert_eq!((1, 2, 1), (err.row, err.expected, err.found));
+    /// ```
+    pub fn new(rows: &[Vec<char>]) -> Result<Grid<'static>, RaggedRowsError> {
+        let width = rows.first().map_or(0, |row| row.len());
+        let mut cells = Vec::with_capacity(width * rows.len());
+        for (y, row) in rows.iter().enumerate() {
+            if row.len() != width {
+                return Err(RaggedRowsError { row: y,
+                                             expected: width,
+                                             found: row.len() });
+            }
+            cells.extend_from_slice(row);
+        }
+        Ok(Grid { width,
+                  height: rows.len(),
+                  cells,
+                  lengths: None,
+                  cost: Arc::new(uniform_cost) })
+    }
+
+    /// Constructs a grid from a list of rows that may be different lengths.
+    /// The grid is as wide as the longest row, and the cells missing from
+    /// the ends of shorter rows are outside of it, so they are impassable
+    /// whatever the cost function.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bestpath::{Grid, Position};
+    ///
+    /// let grid = Grid::from_ragged(&[vec!['.', '.'], vec!['.']])
+    ///     .with_passable(|_| true);
+    /// assert_eq!((2, 2), (grid.width(), grid.height()));
+    /// assert!(grid.is_passable(Position{x: 0, y: 1}));
+    /// assert!(!grid.is_passable(Position{x: 1, y: 1}));
+    /// assert_eq!(vec![vec!['.', '.'], vec!['.']], grid.to_rows());
+    /// ```
+    pub fn from_ragged(rows: &[Vec<char>]) -> Grid<'static> {
+        let width = rows.iter().map(|row| row.len()).max().unwrap_or(0);
+        let mut grid = Grid::filled(width, rows.len(), ' ');
+        for (y, row) in rows.iter().enumerate() {
+            let start = y * width;
+            grid.cells[start..start + row.len()].copy_from_slice(row);
+        }
+        if rows.iter().any(|row| row.len() != width) {
+            grid.lengths = Some(rows.iter().map(|row| row.len()).collect());
+        }
+        grid
+    }
+
+    /// Constructs a `width` x `height` grid with every cell set to `ch`.
+    pub fn filled(width: usize, height: usize, ch: char) -> Grid<'static> {
+        Grid { width,
+               height,
+               cells: vec![ch; width * height],
+               lengths: None,
+               cost: Arc::new(uniform_cost) }
+    }
+}
+
+impl<'c> Grid<'c> {
+    /// Replace the cost function. `cost` gives the cost of moving into a cell
+    /// given its character, or `None` if the cell is impassable. Costs must be
+    /// at least 1 or searches may not find the cheapest path.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::collections::HashMap;
+    /// use bestpath::{Grid, Position};
+    ///
+    /// let terrain: HashMap<char, usize> =
+    ///     vec![('.', 1), ('~', 4)].into_iter().collect();
+    /// let grid = Grid::new(&[vec!['.', '~', '#']]).unwrap()
+    ///     .with_cost(|ch| terrain.get(&ch).cloned());
+    /// assert_eq!(Some(4), grid.cost(Position{x: 1, y: 0}));
+    /// assert_eq!(None, grid.cost(Position{x: 2, y: 0}));
+    /// ```
+    pub fn with_cost<'d, F>(self, cost: F) -> Grid<'d>
+            where F: Fn(char) -> Option<usize> + Send + Sync + 'd {
+        Grid { width: self.width,
+               height: self.height,
+               cells: self.cells,
+               lengths: self.lengths,
+               cost: Arc::new(cost) }
+    }
+
+    /// The cost function, so that one derived from it can be built.
+    pub(crate) fn cost_function(&self) -> Arc<CostFn<'c>> {
+        self.cost.clone()
+    }
+
+    /// Replace the cost function with one where the cells for which
+    /// `passable` returns true cost 1 and all others are impassable.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bestpath::{Grid, Position};
+    ///
+    /// let grid = Grid::new(&[vec!['.', '#']]).unwrap()
+    ///     .with_passable(|ch| ch != '#');
+    /// assert_eq!(Some(1), grid.cost(Position{x: 0, y: 0}));
+    /// assert_eq!(None, grid.cost(Position{x: 1, y: 0}));
+    /// ```
+    pub fn with_passable<'d, P>(self, passable: P) -> Grid<'d>
+            where P: Fn(char) -> bool + Send + Sync + 'd {
+        self.with_cost(move |ch| if passable(ch) { Some(1) } else { None })
+    }
+
+    /// Returns the number of columns in the grid.
+    #[inline]
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    /// Returns the number of rows in the grid.
+    #[inline]
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// Returns true if `pos` is inside the grid.
+    #[inline]
+    pub fn contains(&self, pos: Position) -> bool {
+        pos.x < self.width && pos.y < self.height
+    }
+
+    /// Returns the number of cells in row `y`, which is less than the width
+    /// for the short rows of a grid built with `from_ragged`.
+    #[inline]
+    fn row_length(&self, y: usize) -> usize {
+        self.lengths.as_ref().map_or(self.width, |lengths| lengths[y])
+    }
+
+    /// Returns the character at `pos`, or `None` if it is outside the grid
+    /// or missing from the end of a short row.
+    #[inline]
+    pub fn get(&self, pos: Position) -> Option<char> {
+        if self.contains(pos) && pos.x < self.row_length(pos.y) {
+            Some(self.cells[pos.y * self.width + pos.x])
+        } else {
+            None
+        }
+    }
+
+    /// Returns the cost of moving into `pos`, or `None` if it is impassable or
+    /// outside the grid.
+    #[inline]
+    pub fn cost(&self, pos: Position) -> Option<usize> {
+        self.get(pos).and_then(|ch| (self.cost)(ch))
+    }
+
+    /// Returns true if `pos` is inside the grid and can be moved into.
+    #[inline]
+    pub fn is_passable(&self, pos: Position) -> bool {
+        self.cost(pos).is_some()
+    }
+
+    /// Returns the position offset from `pos` by (dx, dy), or `None` if it is
+    /// outside the grid.
+    #[inline]
+    pub fn offset(&self, pos: Position, (dx, dy): (isize, isize))
+            -> Option<Position> {
+        let x = pos.x as isize + dx;
+        let y = pos.y as isize + dy;
+        if x < 0 || y < 0 {
+            return None;
+        }
+        let p = Position { x: x as usize, y: y as usize };
+        if self.contains(p) { Some(p) } else { None }
+    }
+
+    /// Returns every position in the grid, row by row.
+    pub fn positions(&self) -> impl Iterator<Item = Position> {
+        let width = self.width;
+        (0..self.width * self.height).map(move |i| {
+            Position { x: i % width, y: i / width }
+        })
+    }
+
+    /// Returns the rows of the grid, without the cells missing from the ends
+    /// of short rows.
+    pub fn rows(&self) -> impl Iterator<Item = &[char]> {
+        // `chunks` panics on 0 so handle zero-width grids separately.
+        self.cells.chunks(self.width.max(1)).take(self.height).enumerate()
+            .map(move |(y, row)| &row[..self.row_length(y)])
+    }
+
+    /// Returns the `width` x `height` part of the grid whose top left corner
+    /// is at `origin`, with the same cost function. The part is clipped to
+    /// the edges of the grid.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bestpath::{Grid, Position};
+    ///
+    /// let grid = Grid::new(&[vec!['a', 'b', 'c'],
+    ///                        vec!['d', 'e', 'f']]).unwrap();
+    /// assert_eq!(vec![vec!['b', 'c'], vec!['e', 'f']],
+    ///            grid.crop(Position{x: 1, y: 0}, 5, 5).to_rows());
+    /// ```
+    pub fn crop(&self, origin: Position, width: usize, height: usize)
+            -> Grid<'c> {
+        let width = width.min(self.width.saturating_sub(origin.x));
+        let height = height.min(self.height.saturating_sub(origin.y));
+        let mut cells = Vec::with_capacity(width * height);
+        for y in origin.y..origin.y + height {
+            let start = y * self.width + origin.x;
+            cells.extend_from_slice(&self.cells[start..start + width]);
+        }
+        let lengths = self.lengths.as_ref().map(|lengths| {
+            lengths[origin.y..origin.y + height].iter()
+                .map(|&length| length.saturating_sub(origin.x).min(width))
+                .collect()
+        });
+        Grid { width, height, cells, lengths, cost: self.cost.clone() }
+    }
+
+    /// Generate a copy of the grid with the path filled in. The starting
+    /// position is shown with a '@' , the goal position with a 'X' and all
+    /// other points on the path with a 'o'.
+    pub fn format_path(&self, path: &[Position]) -> Grid<'c> {
+        let mut m = self.clone();
+        let last_path = path.len().saturating_sub(1);
+        for (i, &p) in path.iter().enumerate() {
+            m[p] = match i {
+                0 => START_MARKER,
+                _ if i == last_path => GOAL_MARKER,
+                _ => 'o',
+            }
+        }
+        m
+    }
+
+    /// Converts the grid into a list of rows.
+    pub fn to_rows(&self) -> Vec<Vec<char>> {
+        if self.width == 0 {
+            return vec![Vec::new(); self.height];
+        }
+        self.rows().map(|row| row.to_vec()).collect()
+    }
+}
+
+impl<'c> Index<Position> for Grid<'c> {
+    type Output = char;
+
+    /// Returns the character at `pos`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `pos` is outside of the grid.
+    #[inline]
+    fn index(&self, pos: Position) -> &char {
+        assert!(self.contains(pos), "{:?} is outside of the grid", pos);
+        &self.cells[pos.y * self.width + pos.x]
+    }
+}
+
+impl<'c> IndexMut<Position> for Grid<'c> {
+    #[inline]
+    fn index_mut(&mut self, pos: Position) -> &mut char {
+        assert!(self.contains(pos), "{:?} is outside of the grid", pos);
+        &mut self.cells[pos.y * self.width + pos.x]
+    }
+}
+
+impl<'a> TryFrom<&'a [Vec<char>]> for Grid<'static> {
+    type Error = RaggedRowsError;
+
+    fn try_from(rows: &'a [Vec<char>])
+            -> Result<Grid<'static>, RaggedRowsError> {
+        Grid::new(rows)
+    }
+}
+
+impl TryFrom<Vec<Vec<char>>> for Grid<'static> {
+    type Error = RaggedRowsError;
+
+    fn try_from(rows: Vec<Vec<char>>)
+            -> Result<Grid<'static>, RaggedRowsError> {
+        Grid::new(&rows)
+    }
+}
+
+impl<'c> From<Grid<'c>> for Vec<Vec<char>> {
+    fn from(grid: Grid<'c>) -> Vec<Vec<char>> {
+        grid.to_rows()
+    }
+}
+
+#[cfg(test)]
+use terrain_cost;
+
+#[test]
+fn round_trips_rows() {
+    let rows = vec![vec![' ', '█', ' '],
+                    vec!['1', ' ', '9']];
+    let grid = Grid::try_from(rows.clone()).unwrap();
+    assert_eq!(3, grid.width());
+    assert_eq!(2, grid.height());
+    assert_eq!(rows, Vec::from(grid));
+}
+
+#[test]
+fn rejects_ragged_rows() {
+    let rows = vec![vec![' ', ' ', ' '],
+                    vec![' ', ' ', ' '],
+                    vec![' ', ' ', ' ', ' ']];
+    assert_eq!(Err(RaggedRowsError { row: 2, expected: 3, found: 4 }),
+               Grid::new(&rows).map(|_| ()));
+}
+
+#[test]
+fn empty_grids() {
+    let grid = Grid::new(&[]).unwrap();
+    assert_eq!((0, 0), (grid.width(), grid.height()));
+    assert_eq!(0, grid.positions().count());
+    assert!(grid.to_rows().is_empty());
+
+    let grid = Grid::new(&[vec![], vec![]]).unwrap();
+    assert_eq!((0, 2), (grid.width(), grid.height()));
+    assert_eq!(vec![Vec::<char>::new(), Vec::new()], grid.to_rows());
+}
+
+#[test]
+fn out_of_bounds_is_impassable() {
+    let grid = Grid::filled(2, 2, ' ');
+    assert_eq!(None, grid.get(Position { x: 2, y: 0 }));
+    assert!(!grid.is_passable(Position { x: 0, y: 2 }));
+    assert_eq!(None, grid.offset(Position { x: 0, y: 0 }, (-1, 0)));
+    assert_eq!(Some(Position { x: 1, y: 1 }),
+               grid.offset(Position { x: 0, y: 0 }, (1, 1)));
+}
+
+#[test]
+fn cost_function_is_configurable() {
+    let grid = Grid::new(&[vec![' ', '3', '#']]).unwrap();
+    let costs = |grid: &Grid| -> Vec<Option<usize>> {
+        grid.positions().map(|p| grid.cost(p)).collect()
+    };
+    assert_eq!(vec![Some(1), None, None], costs(&grid));
+    let grid = grid.with_cost(terrain_cost);
+    assert_eq!(vec![Some(1), Some(3), None], costs(&grid));
+    let grid = grid.with_passable(|ch| ch != '#');
+    assert_eq!(vec![Some(1), Some(1), None], costs(&grid));
+}
+
+#[test]
+fn displays_one_row_per_line() {
+    let grid = Grid::new(&[vec!['@', '█'], vec!['o', 'X']]).unwrap();
+    assert_eq!("@█\noX\n", grid.to_string());
+}
+
+#[test]
+fn index_and_set_cells() {
+    let mut grid = Grid::filled(3, 2, ' ');
+    grid[Position { x: 2, y: 1 }] = '█';
+    assert_eq!('█', grid[Position { x: 2, y: 1 }]);
+    assert_eq!(vec![vec![' ', ' ', ' '],
+                    vec![' ', ' ', '█']],
+               grid.to_rows());
+}
+
+#[test]
+fn cost_function_can_borrow_local_data() {
+    use std::collections::HashMap;
+
+    let terrain: HashMap<char, usize> =
+        vec![('.', 1), ('~', 3)].into_iter().collect();
+    let grid = Grid::new(&[vec!['.', '~', '#']]).unwrap()
+        .with_cost(|ch| terrain.get(&ch).cloned());
+    assert_eq!(vec![Some(1), Some(3), None],
+               grid.positions().map(|p| grid.cost(p)).collect::<Vec<_>>());
+}
+
+#[test]
+fn grids_can_be_shared_between_threads() {
+    fn assert_shareable<T: Send + Sync>() {}
+    assert_shareable::<Grid>();
+
+    let grid = Grid::filled(3, 1, ' ');
+    let crossed = std::thread::spawn(move || grid.is_passable(
+        Position { x: 2, y: 0 })).join().unwrap();
+    assert!(crossed);
+}
+
+#[test]
+fn ragged_rows_are_missing_cells() {
+    let grid = Grid::from_ragged(&[vec!['a', 'b', 'c'],
+                                   vec!['d'],
+                                   vec![]]).with_passable(|_| true);
+    assert_eq!((3, 3), (grid.width(), grid.height()));
+    assert_eq!(Some('d'), grid.get(Position { x: 0, y: 1 }));
+    assert_eq!(None, grid.get(Position { x: 1, y: 1 }));
+    assert!(!grid.is_passable(Position { x: 0, y: 2 }));
+    assert_eq!(vec![vec!['a', 'b', 'c'], vec!['d'], vec![]], grid.to_rows());
+    assert_eq!(vec![vec!['b', 'c'], vec![]],
+               grid.crop(Position { x: 1, y: 0 }, 2, 2).to_rows());
+
+    let rows = vec![vec![' ', ' '], vec![' ', ' ']];
+    assert_eq!(rows, Grid::from_ragged(&rows).to_rows());
+}
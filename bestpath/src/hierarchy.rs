@@ -0,0 +1,627 @@
+//! Hierarchical pathfinding (HPA*) for large grids: the grid is divided into
+//! square clusters, the cheapest paths between the entrances of each cluster
+//! are found once, and searches then run over that much smaller graph of
+//! entrances before being refined into cells.
+//!
+//! Reference: Botea, Müller and Schaeffer, "Near Optimal Hierarchical
+//! Path-Finding", Journal of Game Development, 2004.
+
+use std::collections::HashMap;
+
+use graph::NodeMap;
+use search::{best_first, explore, Exploration, SearchState};
+use search_cells::SearchCells;
+use trace::Part;
+use {Graph, Grid, GridGraph, MovementModel, Position, SearchEvent,
+     SearchObserver, SearchResult, TieBreak};
+
+/// Entrances at least this wide get a transition at each end rather than a
+/// single one in the middle.
+const WIDE_ENTRANCE: usize = 6;
+
+/// A step in the abstract graph, along with the cells that it covers.
+#[derive(Clone, Debug)]
+struct Edge {
+    to: usize,
+    cost: usize,
+    // Every cell from the start of the edge to its end, inclusive.
+    path: Vec<Position>,
+}
+
+/// A grid divided into clusters, with a precomputed graph of the cheapest
+/// paths between the entrances of each cluster.
+///
+/// Searching the hierarchy is much faster than searching the grid when the
+/// grid is large, but the path found may cost a little more than the
+/// cheapest, since it must pass through the chosen entrances.
+///
+/// # Examples
+///
+/// ```
+/// use bestpath::{AStar, Grid, Hierarchy, MovementModel, Pathfinder,
+///                Position};
+///
+/// let mut grid = Grid::filled(40, 40, ' ');
+/// for y in 0..35 {
+///     grid[Position{x: 20, y}] = '█';
+/// }
+/// let hierarchy = Hierarchy::new(&grid, 8, MovementModel::FourWay);
+/// let start = Position{x: 0, y: 0};
+/// let goal = Position{x: 39, y: 0};
+/// let (path, cost) = hierarchy.search(&grid, start, goal).path.unwrap();
+/// assert_eq!(Some(&goal), path.last());
+/// let (_, optimal) =
+///     AStar.search(&grid, start, goal, MovementModel::FourWay).path.unwrap();
+/// assert!(cost >= optimal);
+/// ```
+#[derive(Clone, Debug)]
+pub struct Hierarchy {
+    cluster_size: usize,
+    movement: MovementModel,
+    width: usize,
+    height: usize,
+    // The cells on either side of each entrance.
+    nodes: Vec<Position>,
+    node_at: HashMap<Position, usize>,
+    edges: Vec<Vec<Edge>>,
+    // The nodes in each cluster, indexed by `cluster_index`.
+    cluster_nodes: Vec<Vec<usize>>,
+}
+
+/// A Dijkstra search from `from` that doesn't leave `cluster`, which is the
+/// part of the full grid whose top left corner is at `origin`, telling
+/// `observer` about every step in the full grid's coordinates.
+fn explore_cluster<O>(cluster: &Grid,
+                      origin: Position,
+                      from: Position,
+                      movement: MovementModel,
+                      observer: &mut O) -> Exploration
+        where O: SearchObserver + ?Sized {
+    let mut part = Part { position: |p| to_global(origin, p), observer };
+    explore(&GridGraph::new(cluster, movement),
+            SearchCells::new(cluster, false), to_local(origin, from),
+            |_| false, |_| 0, TieBreak::default(), &mut part)
+}
+
+#[inline]
+fn to_local(origin: Position, p: Position) -> Position {
+    Position { x: p.x - origin.x, y: p.y - origin.y }
+}
+
+#[inline]
+fn to_global(origin: Position, p: Position) -> Position {
+    Position { x: p.x + origin.x, y: p.y + origin.y }
+}
+
+/// The path to `to` found by `exploration`, in the full grid's coordinates,
+/// or `None` if it wasn't reached.
+fn cluster_path(exploration: &Exploration,
+                origin: Position,
+                to: Position) -> Option<(Vec<Position>, usize)> {
+    let local_to = to_local(origin, to);
+    exploration.state.cost(local_to).map(|cost| {
+        let path = exploration.state.path_to(local_to)
+            .into_iter()
+            .map(|p| to_global(origin, p))
+            .collect();
+        (path, cost)
+    })
+}
+
+/// The abstract graph of a hierarchy joined to the start and goal of a
+/// search, which are numbered after the hierarchy's own nodes.
+struct Abstract<'a> {
+    hierarchy: &'a Hierarchy,
+    // The paths from the start to the nodes of its cluster.
+    start_edges: Vec<Edge>,
+    // The path to the goal from each node of its cluster that reaches it.
+    to_goal: HashMap<usize, Edge>,
+}
+
+impl Abstract<'_> {
+    /// The edges leaving node `i`.
+    fn edges(&self, i: usize) -> Vec<&Edge> {
+        let nodes = self.hierarchy.nodes.len();
+        match i {
+            _ if i < nodes => self.hierarchy.edges[i].iter()
+                .chain(self.to_goal.get(&i))
+                .collect(),
+            _ if i == nodes => self.start_edges.iter().collect(),
+            _ => Vec::new(),
+        }
+    }
+}
+
+impl Graph for Abstract<'_> {
+    type Node = usize;
+
+    fn successors(&self, i: usize) -> Vec<(usize, usize)> {
+        self.edges(i).into_iter().map(|edge| (edge.to, edge.cost)).collect()
+    }
+}
+
+impl Hierarchy {
+    /// Divide `grid` into `cluster_size` x `cluster_size` clusters and find
+    /// the cheapest paths between the entrances of each cluster.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `cluster_size` is less than 2, or is odd for
+    /// `MovementModel::Hex`, since hex rows alternate.
+    pub fn new(grid: &Grid, cluster_size: usize, movement: MovementModel)
+            -> Hierarchy {
+        assert!(cluster_size >= 2, "clusters must be at least 2x2");
+        assert!(movement != MovementModel::Hex ||
+                cluster_size.is_multiple_of(2),
+                "hex clusters must have an even size");
+        let mut hierarchy = Hierarchy {
+            cluster_size,
+            movement,
+            width: grid.width(),
+            height: grid.height(),
+            nodes: Vec::new(),
+            node_at: HashMap::new(),
+            edges: Vec::new(),
+            cluster_nodes: Vec::new(),
+        };
+        let (columns, rows) = hierarchy.clusters();
+        hierarchy.cluster_nodes = vec![Vec::new(); columns * rows];
+        hierarchy.add_entrances(grid);
+        hierarchy.add_cluster_edges(grid);
+        hierarchy
+    }
+
+    /// The number of columns and rows of clusters.
+    fn clusters(&self) -> (usize, usize) {
+        let n = self.cluster_size;
+        (self.width.div_ceil(n), self.height.div_ceil(n))
+    }
+
+    #[inline]
+    fn cluster_index(&self, p: Position) -> usize {
+        let (columns, _) = self.clusters();
+        (p.y / self.cluster_size) * columns + p.x / self.cluster_size
+    }
+
+    /// The top left corner of the cluster containing `p`.
+    #[inline]
+    fn cluster_origin(&self, p: Position) -> Position {
+        Position { x: p.x - p.x % self.cluster_size,
+                   y: p.y - p.y % self.cluster_size }
+    }
+
+    /// The part of `grid` covered by the cluster containing `p`.
+    fn cluster_grid<'c>(&self, grid: &Grid<'c>, p: Position) -> Grid<'c> {
+        grid.crop(self.cluster_origin(p), self.cluster_size, self.cluster_size)
+    }
+
+    fn node(&mut self, p: Position) -> usize {
+        if let Some(&i) = self.node_at.get(&p) {
+            return i;
+        }
+        let i = self.nodes.len();
+        self.nodes.push(p);
+        self.node_at.insert(p, i);
+        self.edges.push(Vec::new());
+        let cluster = self.cluster_index(p);
+        self.cluster_nodes[cluster].push(i);
+        i
+    }
+
+    /// Join the cells `a` and `b`, which are on either side of a cluster
+    /// border, in both directions.
+    fn add_transition(&mut self, grid: &Grid, a: Position, b: Position) {
+        for &(from, to) in [(a, b), (b, a)].iter() {
+            let step = self.movement.neighbours(grid, from)
+                .into_iter()
+                .find(|&(p, _)| p == to);
+            if let Some((_, cost)) = step {
+                let (i, j) = (self.node(from), self.node(to));
+                // Steps across a corner are found from both borders.
+                if self.edges[i].iter().all(|edge| edge.to != j) {
+                    self.edges[i].push(Edge { to: j,
+                                              cost,
+                                              path: vec![from, to] });
+                }
+            }
+        }
+    }
+
+    /// Find the entrances along every border between two clusters: runs of
+    /// cells where both sides of the border are passable.
+    fn add_entrances(&mut self, grid: &Grid) {
+        let n = self.cluster_size;
+        // Each border is given by the first cell on its near side, the step
+        // across it and the step along it.
+        let mut borders = Vec::new();
+        for x in (n..self.width).step_by(n) {
+            for y in (0..self.height).step_by(n) {
+                borders.push((Position { x: x - 1, y }, (1, 0), (0, 1)));
+            }
+        }
+        for y in (n..self.height).step_by(n) {
+            for x in (0..self.width).step_by(n) {
+                borders.push((Position { x, y: y - 1 }, (0, 1), (1, 0)));
+            }
+        }
+
+        for (first, across, along) in borders {
+            let mut run = Vec::new();
+            let mut cell = Some(first);
+            for _ in 0..n {
+                let pair = cell.and_then(|a| {
+                    grid.offset(a, across).map(|b| (a, b))
+                });
+                let open = pair.filter(|&(a, b)| {
+                    grid.is_passable(a) && grid.is_passable(b)
+                });
+                match open {
+                    Some(pair) => run.push(pair),
+                    None => self.add_run(grid, &mut run),
+                }
+                cell = cell.and_then(|a| grid.offset(a, along));
+            }
+            self.add_run(grid, &mut run);
+            self.add_skew_transitions(grid, first, across, along);
+        }
+    }
+
+    /// Add transitions for a run of open pairs across a border and clear it.
+    fn add_run(&mut self, grid: &Grid, run: &mut Vec<(Position, Position)>) {
+        match run.len() {
+            0 => {}
+            n if n < WIDE_ENTRANCE => {
+                let (a, b) = run[n / 2];
+                self.add_transition(grid, a, b);
+            }
+            n => {
+                let (a, b) = run[0];
+                self.add_transition(grid, a, b);
+                let (a, b) = run[n - 1];
+                self.add_transition(grid, a, b);
+            }
+        }
+        run.clear();
+    }
+
+    /// Add a transition for each step from the border starting at `first`
+    /// that crosses it at a slant, such as a diagonal step between clusters
+    /// that only touch at a corner, unless the step can be replaced by two
+    /// straight steps through an open cell, which the entrances already
+    /// cover.
+    fn add_skew_transitions(&mut self,
+                            grid: &Grid,
+                            first: Position,
+                            across: (isize, isize),
+                            along: (isize, isize)) {
+        let open = |p: Option<Position>| {
+            p.is_some_and(|p| grid.is_passable(p))
+        };
+        let mut cell = Some(first);
+        for _ in 0..self.cluster_size {
+            let a = match cell {
+                Some(a) => a,
+                None => break,
+            };
+            for &side in [-1, 1].iter() {
+                let sideways = (side * along.0, side * along.1);
+                let b = grid.offset(a, (across.0 + sideways.0,
+                                        across.1 + sideways.1));
+                if open(Some(a)) && open(b) &&
+                        !open(grid.offset(a, across)) &&
+                        !open(grid.offset(a, sideways)) {
+                    self.add_transition(grid, a, b.unwrap());
+                }
+            }
+            cell = grid.offset(a, along);
+        }
+    }
+
+    /// Find the cheapest path within each cluster between every pair of its
+    /// nodes.
+    fn add_cluster_edges(&mut self, grid: &Grid) {
+        for cluster in 0..self.cluster_nodes.len() {
+            let members = self.cluster_nodes[cluster].clone();
+            let first = match members.first() {
+                Some(&i) => self.nodes[i],
+                None => continue,
+            };
+            let origin = self.cluster_origin(first);
+            let cluster_grid = self.cluster_grid(grid, first);
+            for &i in &members {
+                let from = self.nodes[i];
+                let exploration = explore_cluster(&cluster_grid, origin, from,
+                                                  self.movement, &mut ());
+                for &j in members.iter().filter(|&&j| j != i) {
+                    if let Some((path, cost)) = cluster_path(
+                            &exploration, origin, self.nodes[j]) {
+                        self.edges[i].push(Edge { to: j, cost, path });
+                    }
+                }
+            }
+        }
+    }
+
+    /// The number of entrance cells in the abstract graph.
+    pub fn node_count(&self) -> usize {
+        self.nodes.len()
+    }
+
+    /// Find a path from `start` to `goal` through `grid`, which must be the
+    /// grid that the hierarchy was built from.
+    ///
+    /// The abstract graph is searched first, then the path is refined into
+    /// cells using the paths found when the hierarchy was built. Every step
+    /// between clusters, including diagonal steps across their corners, is
+    /// covered by an entrance, so there is a path in the abstract graph
+    /// whenever there is one in the grid. `expanded` counts the abstract
+    /// nodes and the cells expanded.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `grid` isn't the same size as the grid the hierarchy was
+    /// built from.
+    pub fn search(&self, grid: &Grid, start: Position, goal: Position)
+            -> SearchResult {
+        self.search_observed(grid, start, goal, &mut ())
+    }
+
+    /// Like `search` but telling `observer` about every step of the search.
+    ///
+    /// The searches within the start and goal clusters are reported first,
+    /// with costs measured from where each of them began, and then the
+    /// search of the abstract graph, whose positions are entrance cells and
+    /// whose `CameFrom` events join the ends of whole paths across clusters.
+    /// `ReachedGoal` is reported once, with the cost of the path returned.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bestpath::{Grid, Hierarchy, MovementModel, Position, SearchStats};
+    ///
+    /// let grid = Grid::filled(32, 32, ' ');
+    /// let hierarchy = Hierarchy::new(&grid, 8, MovementModel::EightWay);
+    /// let mut stats = SearchStats::default();
+    /// let result = hierarchy.search_observed(&grid, Position{x: 0, y: 0},
+    ///                                        Position{x: 31, y: 31},
+    ///                                        &mut stats);
+    /// assert_eq!(result.expanded, stats.expanded);
+    /// ```
+    pub fn search_observed<O>(&self,
+                              grid: &Grid,
+                              start: Position,
+                              goal: Position,
+                              observer: &mut O) -> SearchResult
+            where O: SearchObserver + ?Sized {
+        assert!(grid.width() == self.width && grid.height() == self.height,
+                "grid is not the one the hierarchy was built from");
+        if !grid.contains(start) || !grid.contains(goal) {
+            return SearchResult { path: None, expanded: 0 };
+        }
+        let mut expanded = 0;
+
+        // Temporarily join the start and goal to the nodes of their
+        // clusters. They are numbered after the existing nodes.
+        let start_node = self.nodes.len();
+        let goal_node = start_node + 1;
+        let start_origin = self.cluster_origin(start);
+        let start_grid = self.cluster_grid(grid, start);
+        let from_start = explore_cluster(&start_grid, start_origin, start,
+                                         self.movement, observer);
+        expanded += from_start.expanded;
+        let start_edges: Vec<Edge> =
+            self.cluster_nodes[self.cluster_index(start)]
+                .iter()
+                .filter_map(|&i| {
+                    cluster_path(&from_start, start_origin, self.nodes[i])
+                        .map(|(path, cost)| Edge { to: i, cost, path })
+                })
+                .collect();
+
+        let goal_origin = self.cluster_origin(goal);
+        let goal_grid = self.cluster_grid(grid, goal);
+        let mut to_goal: HashMap<usize, Edge> = HashMap::new();
+        for &i in &self.cluster_nodes[self.cluster_index(goal)] {
+            let from = self.nodes[i];
+            let exploration = explore_cluster(&goal_grid, goal_origin, from,
+                                              self.movement, observer);
+            expanded += exploration.expanded;
+            if let Some((path, cost)) =
+                    cluster_path(&exploration, goal_origin, goal) {
+                to_goal.insert(i, Edge { to: goal_node, cost, path });
+            }
+        }
+        let direct = if self.cluster_index(start) == self.cluster_index(goal) {
+            cluster_path(&from_start, start_origin, goal)
+        } else {
+            None
+        };
+
+        // A* over the abstract graph.
+        let position = |i: usize| match i {
+            _ if i == start_node => start,
+            _ if i == goal_node => goal,
+            _ => self.nodes[i],
+        };
+        let graph = Abstract { hierarchy: self, start_edges, to_goal };
+        let result = best_first(&graph, NodeMap::new(), start_node,
+                                |i| i == goal_node,
+                                |i| self.movement.heuristic(position(i), goal),
+                                TieBreak::default(),
+                                &mut Part { position, observer });
+        expanded += result.expanded;
+
+        let abstract_path = result.path.map(|(nodes, cost)| {
+            let mut path = vec![start];
+            for step in nodes.windows(2) {
+                let edge = graph.edges(step[0])
+                    .into_iter()
+                    .filter(|edge| edge.to == step[1])
+                    .min_by_key(|edge| edge.cost)
+                    .unwrap();
+                path.extend_from_slice(&edge.path[1..]);
+            }
+            (path, cost)
+        });
+
+        let path = match (abstract_path, direct) {
+            (Some(a), Some(d)) => Some(if d.1 <= a.1 { d } else { a }),
+            (a, d) => a.or(d),
+        };
+        if let Some((_, cost)) = path {
+            observer.observe(SearchEvent::ReachedGoal { position: goal, cost });
+        }
+        SearchResult { path, expanded }
+    }
+}
+
+#[cfg(test)]
+use mazes::{random_grid, ALL_MODELS};
+#[cfg(test)]
+use search::path_cost;
+#[cfg(test)]
+use {AStar, Pathfinder};
+
+#[test]
+fn finds_valid_near_optimal_paths() {
+    for seed in 0..6 {
+        let grid = random_grid(seed, 40, 30, 5);
+        let hierarchies: Vec<Hierarchy> = ALL_MODELS.iter()
+            .map(|&movement| Hierarchy::new(&grid, 8, movement))
+            .collect();
+        for &(start, goal) in [(Position { x: 0, y: 0 },
+                                Position { x: 39, y: 29 }),
+                               (Position { x: 35, y: 2 },
+                                Position { x: 3, y: 27 }),
+                               (Position { x: 9, y: 9 },
+                                Position { x: 14, y: 12 })].iter() {
+            for hierarchy in &hierarchies {
+                let movement = hierarchy.movement;
+                let expected = AStar.search(&grid, start, goal, movement);
+                let result = hierarchy.search(&grid, start, goal);
+                match (expected.path, result.path) {
+                    (Some((_, optimal)), Some((path, cost))) => {
+                        assert_eq!(Some(&start), path.first());
+                        assert_eq!(Some(&goal), path.last());
+                        assert_eq!(cost, path_cost(&grid, &path, movement));
+                        assert!(cost >= optimal);
+                        assert!(cost * 10 <= optimal * 13,
+                                "seed {} {:?}: {} vs {}",
+                                seed, movement, cost, optimal);
+                    }
+                    (None, None) => {}
+                    (expected, result) =>
+                        panic!("seed {} {:?}: {:?} vs {:?}",
+                               seed, movement, expected, result),
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn expands_fewer_nodes_than_a_star_on_large_grids() {
+    let grid = random_grid(3, 128, 128, 6);
+    let movement = MovementModel::EightWayNoCornerCutting;
+    let hierarchy = Hierarchy::new(&grid, 16, movement);
+    let start = Position { x: 1, y: 1 };
+    let goal = Position { x: 126, y: 126 };
+    let a_star = AStar.search(&grid, start, goal, movement);
+    let result = hierarchy.search(&grid, start, goal);
+    assert!(result.path.is_some());
+    assert!(result.expanded < a_star.expanded,
+            "{} vs {}", result.expanded, a_star.expanded);
+}
+
+#[test]
+fn same_cluster_uses_direct_path() {
+    let grid = Grid::filled(16, 16, ' ');
+    let hierarchy = Hierarchy::new(&grid, 8, MovementModel::FourWay);
+    let start = Position { x: 1, y: 1 };
+    let goal = Position { x: 5, y: 2 };
+    let (path, cost) = hierarchy.search(&grid, start, goal).path.unwrap();
+    assert_eq!(5, cost);
+    assert_eq!(6, path.len());
+}
+
+#[test]
+fn entrances_only_where_both_sides_are_open() {
+    let mut grid = Grid::filled(8, 4, ' ');
+    for y in 0..4 {
+        grid[Position { x: 4, y }] = '█';
+    }
+    let hierarchy = Hierarchy::new(&grid, 4, MovementModel::FourWay);
+    assert_eq!(0, hierarchy.node_count());
+    assert_eq!(None,
+               hierarchy.search(&grid, Position { x: 0, y: 0 },
+                                Position { x: 7, y: 3 }).path);
+
+    grid[Position { x: 4, y: 2 }] = ' ';
+    let hierarchy = Hierarchy::new(&grid, 4, MovementModel::FourWay);
+    assert_eq!(2, hierarchy.node_count());
+    let (_, cost) = hierarchy.search(&grid, Position { x: 0, y: 0 },
+                                     Position { x: 7, y: 3 }).path.unwrap();
+    assert_eq!(10, cost);
+}
+
+#[test]
+fn clusters_that_only_touch_diagonally_are_joined() {
+    let grid = Grid::new(&[vec![' ', ' ', '█', '█'],
+                           vec![' ', ' ', '█', '█'],
+                           vec!['█', '█', ' ', ' '],
+                           vec!['█', '█', ' ', ' ']]).unwrap();
+    let hierarchy = Hierarchy::new(&grid, 2, MovementModel::EightWay);
+    assert_eq!(2, hierarchy.node_count());
+    let (path, _) = hierarchy.search(&grid, Position { x: 0, y: 0 },
+                                     Position { x: 3, y: 3 }).path.unwrap();
+    assert_eq!(4, path.len());
+
+    // Without cutting corners there is no way between them.
+    let hierarchy = Hierarchy::new(&grid, 2,
+                                   MovementModel::EightWayNoCornerCutting);
+    assert_eq!(0, hierarchy.node_count());
+    assert_eq!(None,
+               hierarchy.search(&grid, Position { x: 0, y: 0 },
+                                Position { x: 3, y: 3 }).path);
+}
+
+#[test]
+fn slanted_steps_across_a_border_are_entrances() {
+    // The only ways across the border between the two columns of clusters
+    // are diagonal, away from any corner.
+    let grid = Grid::new(&[vec![' ', ' ', ' ', '█'],
+                           vec![' ', '█', ' ', ' '],
+                           vec!['█', ' ', '█', ' '],
+                           vec![' ', '█', ' ', ' ']]).unwrap();
+    for &movement in [MovementModel::EightWay, MovementModel::Hex].iter() {
+        let hierarchy = Hierarchy::new(&grid, 2, movement);
+        for &goal in [Position { x: 3, y: 3 }, Position { x: 0, y: 3 }]
+                .iter() {
+            let expected = AStar.search(&grid, Position { x: 0, y: 0 }, goal,
+                                        movement);
+            let result = hierarchy.search(&grid, Position { x: 0, y: 0 },
+                                          goal);
+            assert_eq!(expected.path.is_some(), result.path.is_some(),
+                       "{:?} to {:?}", movement, goal);
+        }
+    }
+}
+
+#[test]
+fn finds_a_path_whenever_there_is_one() {
+    for seed in 0..20 {
+        let grid = random_grid(seed, 24, 18, 3);
+        for &movement in ALL_MODELS.iter() {
+            let hierarchy = Hierarchy::new(&grid, 4, movement);
+            for &(start, goal) in [(Position { x: 0, y: 0 },
+                                    Position { x: 23, y: 17 }),
+                                   (Position { x: 22, y: 1 },
+                                    Position { x: 2, y: 16 })].iter() {
+                let expected = AStar.search(&grid, start, goal, movement);
+                let result = hierarchy.search(&grid, start, goal);
+                assert_eq!(expected.path.is_some(), result.path.is_some(),
+                           "seed {} {:?}", seed, movement);
+            }
+        }
+    }
+}
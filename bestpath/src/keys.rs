@@ -0,0 +1,299 @@
+//! Searching mazes with keys and locked doors, where the state of a search
+//! is both where it is and which keys it has collected.
+
+use graph::NodeMap;
+use search::best_first;
+use {Graph, Grid, MovementModel, Position, SearchResult, TieBreak};
+
+/// Returns true if `ch` is a key: the lowercase letters 'a' to 'z'.
+#[inline]
+fn is_key(ch: char) -> bool {
+    ch.is_ascii_lowercase()
+}
+
+/// Returns true if `ch` is a door: the uppercase letters 'A' to 'Z'. Each
+/// door is opened by the key with the same letter in lowercase.
+#[inline]
+fn is_door(ch: char) -> bool {
+    ch.is_ascii_uppercase()
+}
+
+/// The bit for the key or door `ch` in a set of keys.
+#[inline]
+fn key_bit(ch: char) -> u32 {
+    1 << (ch.to_ascii_lowercase() as u32 - 'a' as u32)
+}
+
+/// A grid with keys and doors, seen as a graph whose nodes are a position
+/// and the set of keys collected so far, as a bitmask with bit 0 for 'a'.
+///
+/// Keys and doors cost 1 to enter, every other cell costs what the grid's
+/// cost function says. Stepping onto a key collects it and a door can only
+/// be entered once its key has been collected. With
+/// `MovementModel::EightWayNoCornerCutting`, locked doors can't be cut
+/// past either.
+///
+/// # Examples
+///
+/// ```
+/// use bestpath::{Grid, KeyMaze, MovementModel, Position};
+///
+/// let grid = Grid::new(&[vec![' ', 'A', ' ', 'a'],
+///                        vec![' ', '█', '█', '█']]).unwrap();
+/// let maze = KeyMaze::new(&grid, MovementModel::FourWay);
+/// // The key is behind its own door, so the goal can't be reached.
+/// assert_eq!(None, maze.search(Position{x: 0, y: 1},
+///                              Position{x: 3, y: 0}).path);
+///
+/// let grid = Grid::new(&[vec!['a', ' ', 'A', ' '],
+///                        vec![' ', '█', '█', '█']]).unwrap();
+/// let maze = KeyMaze::new(&grid, MovementModel::FourWay);
+/// let (path, cost) = maze.search(Position{x: 0, y: 1},
+///                                Position{x: 3, y: 0}).path.unwrap();
+/// assert_eq!(4, cost);
+/// assert_eq!(Position{x: 0, y: 0}, path[1]);
+/// ```
+#[derive(Clone, Debug)]
+pub struct KeyMaze<'c> {
+    // The grid with every door open.
+    open: Grid<'c>,
+    movement: MovementModel,
+    all_keys: u32,
+}
+
+impl<'c> KeyMaze<'c> {
+    /// Find the keys and doors in `grid`, where `movement` gives the steps
+    /// between cells.
+    pub fn new(grid: &Grid<'c>, movement: MovementModel) -> KeyMaze<'c> {
+        let cost = grid.cost_function();
+        let open = grid.clone().with_cost(move |ch| {
+            if is_key(ch) || is_door(ch) { Some(1) } else { cost(ch) }
+        });
+        let all_keys = grid.positions()
+            .map(|p| grid[p])
+            .filter(|&ch| is_key(ch))
+            .fold(0, |keys, ch| keys | key_bit(ch));
+        KeyMaze { open, movement, all_keys }
+    }
+
+    /// Every key in the grid, as a bitmask.
+    #[inline]
+    pub fn all_keys(&self) -> u32 {
+        self.all_keys
+    }
+
+    /// The state of a search starting at `start`, which collects the key
+    /// there, if any.
+    fn start_state(&self, start: Position) -> (Position, u32) {
+        match self.open.get(start) {
+            Some(ch) if is_key(ch) => (start, key_bit(ch)),
+            _ => (start, 0),
+        }
+    }
+
+    /// Returns true if `pos` is a door whose key isn't in `keys`.
+    fn is_locked(&self, pos: Position, keys: u32) -> bool {
+        self.open.get(pos)
+            .is_some_and(|ch| is_door(ch) && keys & key_bit(ch) == 0)
+    }
+
+    /// Run A* from `start` until `is_goal` holds, with `heuristic` giving a
+    /// lower bound on the cost from a position, and return the path without
+    /// the keys.
+    fn search_until<F, H>(&self, start: Position, is_goal: F, heuristic: H)
+            -> SearchResult
+            where F: Fn((Position, u32)) -> bool,
+                  H: Fn((Position, u32)) -> usize {
+        let result = best_first(self, NodeMap::new(), self.start_state(start),
+                                is_goal, heuristic, TieBreak::default(),
+                                &mut ());
+        SearchResult {
+            path: result.path.map(|(path, cost)| {
+                (path.into_iter().map(|(p, _)| p).collect(), cost)
+            }),
+            expanded: result.expanded,
+        }
+    }
+
+    /// Find the cheapest path from `start` to `goal`, collecting whatever
+    /// keys are needed on the way.
+    pub fn search(&self, start: Position, goal: Position) -> SearchResult {
+        let movement = self.movement;
+        self.search_until(start,
+                          |(p, _)| p == goal,
+                          |(p, _)| movement.heuristic(p, goal))
+    }
+
+    /// Find the cheapest path from `start` that collects every key in the
+    /// grid, ending wherever the last key is.
+    pub fn collect_all_keys(&self, start: Position) -> SearchResult {
+        let keys: Vec<(Position, u32)> = self.open.positions()
+            .filter(|&p| is_key(self.open[p]))
+            .map(|p| (p, key_bit(self.open[p])))
+            .collect();
+        let movement = self.movement;
+        // The furthest key still to collect has to be reached at least.
+        let heuristic = |(p, held): (Position, u32)| {
+            keys.iter()
+                .filter(|&&(_, bit)| held & bit == 0)
+                .map(|&(k, _)| movement.heuristic(p, k))
+                .max()
+                .unwrap_or(0)
+        };
+        let all_keys = self.all_keys;
+        self.search_until(start, |(_, held)| held == all_keys, heuristic)
+    }
+}
+
+impl<'c> Graph for KeyMaze<'c> {
+    type Node = (Position, u32);
+
+    fn successors(&self, (pos, keys): (Position, u32))
+            -> Vec<((Position, u32), usize)> {
+        self.movement.neighbours(&self.open, pos)
+            .into_iter()
+            .filter(|&(p, _)| !self.is_locked(p, keys))
+            .filter(|&(p, _)| {
+                self.movement != MovementModel::EightWayNoCornerCutting ||
+                    p.x == pos.x || p.y == pos.y ||
+                    !(self.is_locked(Position { x: p.x, y: pos.y }, keys) ||
+                      self.is_locked(Position { x: pos.x, y: p.y }, keys))
+            })
+            .map(|(p, cost)| {
+                let ch = self.open[p];
+                let keys = if is_key(ch) { keys | key_bit(ch) } else { keys };
+                ((p, keys), cost)
+            })
+            .collect()
+    }
+
+    fn heuristic(&self, (pos, _): (Position, u32), (goal, _): (Position, u32))
+            -> usize {
+        self.movement.heuristic(pos, goal)
+    }
+}
+
+#[cfg(test)]
+use mazes::random_grid;
+#[cfg(test)]
+use {find_path_with_keys, terrain_cost, AStar, Pathfinder};
+
+#[cfg(test)]
+fn parse(rows: &[&str]) -> (Grid<'static>, Position) {
+    let rows: Vec<Vec<char>> = rows.iter().map(|r| r.chars().collect())
+        .collect();
+    let mut grid = Grid::new(&rows).unwrap();
+    let start = grid.positions().find(|&p| grid[p] == '@').unwrap();
+    grid[start] = ' ';
+    (grid, start)
+}
+
+#[test]
+fn collects_all_keys_in_small_example() {
+    // The first example from Advent of Code 2019, day 18, with spaces for
+    // open cells.
+    let (grid, start) = parse(&["#########",
+                                "#b A @ a#",
+                                "#########"]);
+    let grid = grid.with_passable(|ch| ch != '#');
+    let maze = KeyMaze::new(&grid, MovementModel::FourWay);
+    assert_eq!(0b11, maze.all_keys());
+    let (path, cost) = maze.collect_all_keys(start).path.unwrap();
+    assert_eq!(8, cost);
+    assert_eq!(Some(&Position { x: 1, y: 1 }), path.last());
+}
+
+#[test]
+fn collects_all_keys_in_larger_examples() {
+    let (grid, start) = parse(&["########################",
+                                "#f D E e C b A @ a B c #",
+                                "###################### #",
+                                "#d                     #",
+                                "########################"]);
+    let grid = grid.with_passable(|ch| ch != '#');
+    let maze = KeyMaze::new(&grid, MovementModel::FourWay);
+    assert_eq!(86, maze.collect_all_keys(start).path.unwrap().1);
+
+    let (grid, start) = parse(&["########################",
+                                "#               b C D f#",
+                                "# ######################",
+                                "#     @ a B c d A e F g#",
+                                "########################"]);
+    let grid = grid.with_passable(|ch| ch != '#');
+    let maze = KeyMaze::new(&grid, MovementModel::FourWay);
+    assert_eq!(132, maze.collect_all_keys(start).path.unwrap().1);
+}
+
+#[test]
+fn doors_need_their_keys() {
+    let (grid, start) = parse(&["@ A ",
+                                "█ ██",
+                                "a   "]);
+    let goal = Position { x: 3, y: 0 };
+    let maze = KeyMaze::new(&grid, MovementModel::FourWay);
+    let (path, cost) = maze.search(start, goal).path.unwrap();
+    assert_eq!(9, cost);
+    let key = path.iter().position(|&p| p == Position { x: 0, y: 2 });
+    let door = path.iter().position(|&p| p == Position { x: 2, y: 0 });
+    assert!(key.unwrap() < door.unwrap());
+
+    let mut keyless = grid.clone();
+    keyless[Position { x: 0, y: 2 }] = ' ';
+    let maze = KeyMaze::new(&keyless, MovementModel::FourWay);
+    assert_eq!(None, maze.search(start, goal).path);
+}
+
+#[test]
+fn matches_grid_search_without_letters() {
+    for seed in 0..6 {
+        let grid = random_grid(seed, 12, 9, 4);
+        for &movement in [MovementModel::FourWay,
+                          MovementModel::EightWayNoCornerCutting].iter() {
+            let maze = KeyMaze::new(&grid, movement);
+            let (start, goal) = (Position { x: 0, y: 2 },
+                                 Position { x: 11, y: 8 });
+            let expected = AStar.search(&grid, start, goal, movement);
+            assert_eq!(expected.path.map(|(_, cost)| cost),
+                       maze.search(start, goal).path.map(|(_, cost)| cost),
+                       "seed {} {:?}", seed, movement);
+        }
+    }
+}
+
+#[test]
+fn terrain_costs_still_apply() {
+    let (grid, start) = parse(&["@9 A ",
+                                " █a██",
+                                "   ██"]);
+    let grid = grid.with_cost(terrain_cost);
+    let maze = KeyMaze::new(&grid, MovementModel::FourWay);
+    // Round the bottom to the key is cheaper than through the 9.
+    let (path, cost) = maze.search(start, Position { x: 4, y: 0 })
+        .path.unwrap();
+    assert_eq!(8, cost);
+    assert!(path.contains(&Position { x: 1, y: 2 }));
+}
+
+#[test]
+fn locked_doors_block_corner_cutting() {
+    let (grid, start) = parse(&["@A",
+                                "B "]);
+    let goal = Position { x: 1, y: 1 };
+    let cutting = KeyMaze::new(&grid, MovementModel::EightWay);
+    assert!(cutting.search(start, goal).path.is_some());
+    let no_cutting = KeyMaze::new(&grid,
+                                  MovementModel::EightWayNoCornerCutting);
+    assert_eq!(None, no_cutting.search(start, goal).path);
+}
+
+#[test]
+fn find_path_with_keys_uses_letters() {
+    let grid = vec![vec![' ', 'a', '█', ' '],
+                    vec![' ', '█', '█', ' '],
+                    vec![' ', ' ', 'A', ' ']];
+    let (path, cost) = find_path_with_keys(&grid, Position { x: 0, y: 2 },
+                                           Position { x: 3, y: 0 })
+        .unwrap();
+    assert_eq!(11, cost);
+    assert!(path.contains(&Position { x: 1, y: 0 }));
+}
@@ -1,8 +1,53 @@
 // Reference: http://www.redblobgames.com/pathfinding/a-star/introduction.html
 
-use std::collections::BinaryHeap;
-use std::collections::HashMap;
-use std::cmp::Ordering;
+
+mod budget;
+mod components;
+mod flow_field;
+mod generate;
+mod graph;
+mod grid;
+mod hierarchy;
+mod keys;
+mod maze_file;
+mod movement;
+mod multi_agent;
+mod multi_goal;
+mod post_process;
+mod render;
+mod replanner;
+mod search;
+mod search_cells;
+mod topology;
+mod trace;
+#[cfg(test)]
+mod mazes;
+
+pub use budget::{AnytimeAStar, BoundedResult, Budget};
+pub use components::{Components, Region};
+pub use flow_field::{flow_field, flow_field_observed, FlowField};
+pub use generate::{MazeGenerator, Prim, RandomObstacles, RecursiveBacktracker,
+                   RoomsAndCorridors, WALL};
+pub use graph::{Graph, GridGraph};
+pub use grid::{Grid, RaggedRowsError};
+pub use hierarchy::Hierarchy;
+pub use keys::KeyMaze;
+pub use maze_file::{MazeFile, ParseMazeError, GOAL_MARKER, START_MARKER};
+pub use movement::{MovementModel, DIAGONAL_STEP, ORTHOGONAL_STEP};
+pub use multi_agent::{first_conflict, Agent, Conflict, ConflictBased,
+                      CooperativeAStar, MultiAgentPlanner};
+pub use multi_goal::{find_nearest, visit_waypoints, Tour,
+                     EXACT_WAYPOINT_LIMIT};
+pub use post_process::{expand_lines, expand_waypoints, from_commands,
+                       line_of_sight, string_pull, to_commands, waypoints,
+                       ParseCommandsError};
+pub use render::Picture;
+pub use replanner::Replanner;
+pub use search::{AStar, BidirectionalAStar, BreadthFirst, Dijkstra, JumpPoint,
+                 Pathfinder, SearchResult, TieBreak};
+pub use topology::{Topology, UnpairedPortalError};
+pub use trace::{SearchEvent, SearchObserver, SearchStats, Trace,
+                EXPLORED_MARKER};
 
 #[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
 pub struct Position {
@@ -10,85 +55,61 @@ pub struct Position {
     pub y: usize
 }
 
-// Used to maintain a priority queue of positions to explore.
-#[derive(Copy, Clone, Eq, PartialEq, Debug)]
-struct PositionPriority {
-    minimum_cost: usize,
-    position: Position,
-}
-
-// The priority queue depends on `Ord`.
-impl Ord for PositionPriority {
-    // Reverse the comparison so the position with the lowest cost is
-    // explored first.
-    fn cmp(&self, other: &PositionPriority) -> Ordering {
-        other.minimum_cost.cmp(&self.minimum_cost)
-    }
-}
-
-impl PartialOrd for PositionPriority {
-    fn partial_cmp(&self, other: &PositionPriority) -> Option<Ordering> {
-        Some(self.cmp(other))
-    }
-}
-
-#[inline]
-fn min_distance_heuristic(p1: Position, p2: Position) -> usize {
-    let x_diff = match p1.x > p2.x {
-        true => p1.x - p2.x,
-        false => p2.x - p1.x,
-    };
-    let y_diff = match p1.y > p2.y {
-        true => p1.y - p2.y,
-        false => p2.y - p1.y,
-    };
-
-    x_diff + y_diff  // Since diagonal moves aren't allowed.
-}
-
-/// Return the navigable neighbouring cells to the given one.
+/// The cost of moving into a cell containing `ch`, or `None` if the cell is an
+/// impassable obstacle. Spaces cost 1 and every other character is an obstacle.
+///
+/// This is the cost function used by `find_path`.
+///
+/// # Examples
+///
+/// ```
+/// use bestpath::uniform_cost;
+///
+/// assert_eq!(Some(1), uniform_cost(' '));
+/// assert_eq!(None, uniform_cost('5'));
+/// assert_eq!(None, uniform_cost('█'));
+/// ```
 #[inline]
-fn neighbours(grid: &[Vec<char>], pos: Position) -> Vec<Position> {
-    let mut v = Vec::new();
-
-    if pos.x > 0 && grid[pos.y][pos.x-1] == ' ' {
-        v.push(Position{x: pos.x-1, y: pos.y});
-    }
-    if pos.x + 1 < grid[pos.y].len() && grid[pos.y][pos.x+1] == ' ' {
-        v.push(Position{x: pos.x+1, y: pos.y});
-    }
-
-    if pos.y > 0 && pos.x < grid[pos.y-1].len() && grid[pos.y-1][pos.x] == ' ' {
-        v.push(Position{x: pos.x, y: pos.y-1});
-    }
-    if pos.y + 1 < grid.len() &&
-       pos.x < grid[pos.y+1].len() &&
-       grid[pos.y+1][pos.x] == ' ' {
-        v.push(Position{x: pos.x, y: pos.y+1});
+pub fn uniform_cost(ch: char) -> Option<usize> {
+    match ch {
+        ' ' => Some(1),
+        _ => None,
     }
-    v
 }
 
-/// Generate the optimal path from start to goal given a mapping from positions
-/// to the position that lead to that position.
+/// The cost of moving into a cell containing `ch`, or `None` if the cell is an
+/// impassable obstacle. Spaces cost 1, the digits '1' to '9' cost their value
+/// (so they can represent rough terrain) and every other character is an
+/// obstacle.
+///
+/// # Examples
+///
+/// ```
+/// use bestpath::terrain_cost;
+///
+/// assert_eq!(Some(1), terrain_cost(' '));
+/// assert_eq!(Some(5), terrain_cost('5'));
+/// assert_eq!(None, terrain_cost('0'));
+/// assert_eq!(None, terrain_cost('█'));
+/// ```
 #[inline]
-fn reconstruct(came_from: &HashMap<Position, Position>,
-               start: Position,
-               goal: Position) -> Vec<Position> {
-    let mut current = goal;
-    let mut path = vec![current];
-    while current != start {
-        current = came_from[&current];
-        path.push(current);
+pub fn terrain_cost(ch: char) -> Option<usize> {
+    match ch {
+        ' ' => Some(1),
+        '1'..='9' => ch.to_digit(10).map(|d| d as usize),
+        _ => None,
     }
-    path.reverse();
-    path
 }
 
 /// Find the optimal path from a starting position to a goal position. Return
 /// None if no path is possible. The input grid is a 2-dimensional grid of
 /// characters. Any character other than a space is considered an impassable
-/// obstacle.
+/// obstacle. Rows may be different lengths.
+///
+/// Finding that there is no path means exploring every cell that can be
+/// reached from the start. To answer many queries on the same grid, label it
+/// once with `Components` and use `find_path_with_components`, which gives
+/// up straight away when the goal can't be reached.
 ///
 /// # Examples
 ///
@@ -111,36 +132,149 @@ fn reconstruct(came_from: &HashMap<Position, Position>,
 #[inline]
 pub fn find_path(grid: &[Vec<char>], start: Position, goal: Position)
         -> Option<Vec<Position>>  {
-    let mut frontier = BinaryHeap::new();
-    frontier.push(PositionPriority { minimum_cost: 0, position: start });
+    find_weighted_path(grid, start, goal, uniform_cost).map(|(path, _)| path)
+}
 
-    let mut came_from = HashMap::new();
-    let mut cost_so_far = HashMap::new();
-    cost_so_far.insert(start, 0);
+/// Find the cheapest path from a starting position to a goal position and
+/// return it along with its total cost. Return None if no path is possible.
+///
+/// `cost` gives the cost of moving into a cell given its character, or `None`
+/// if the cell is an impassable obstacle (see `uniform_cost` and
+/// `terrain_cost`). Costs must be at least 1 or the path found may not be the
+/// cheapest. The starting cell is free.
+///
+/// # Examples
+///
+/// ```
+/// use bestpath::{find_weighted_path, terrain_cost, Position};
+///
+/// let grid = vec![vec![' ', '9', ' '],
+///                 vec![' ', '2', ' '],
+///                 vec![' ', ' ', ' ']];
+/// let (path, cost) = find_weighted_path(&grid,
+///                                       Position{x: 0, y: 0},
+///                                       Position{x: 2, y: 0},
+///                                       terrain_cost).unwrap();
+/// assert_eq!(vec![Position{x: 0, y: 0},
+///                 Position{x: 0, y: 1},
+///                 Position{x: 1, y: 1},
+///                 Position{x: 2, y: 1},
+///                 Position{x: 2, y: 0}],
+///            path);
+/// assert_eq!(5, cost);
+/// ```
+#[inline]
+pub fn find_weighted_path<F>(grid: &[Vec<char>],
+                             start: Position,
+                             goal: Position,
+                             cost: F) -> Option<(Vec<Position>, usize)>
+        where F: Fn(char) -> Option<usize> + Send + Sync {
+    find_path_with_movement(grid, start, goal, cost, MovementModel::FourWay)
+}
 
-    while let Some(PositionPriority { minimum_cost, position }) =
-            frontier.pop() {
-        if position == goal { 
-            return Some(reconstruct(&came_from, start, goal));
-        }
-        let cost = cost_so_far[&position];
-
-        for next_position in neighbours(grid, position) {
-            let new_cost = cost + 1;
-            if let Some(existing_cost) = cost_so_far.get(&next_position) {
-                if new_cost >= *existing_cost {
-                    continue;
-                }
-            }
-            cost_so_far.insert(next_position, new_cost);
-            frontier.push(PositionPriority { 
-                minimum_cost: new_cost +
-                    min_distance_heuristic(goal, next_position),
-                position: next_position });
-            came_from.insert(next_position, position);
-        }
-    }
-    None
+/// Like `find_weighted_path` but moving between cells according to `movement`
+/// rather than only up, down, left and right. See `MovementModel` for how each
+/// model scales costs.
+///
+/// # Examples
+///
+/// ```
+/// use bestpath::{find_path_with_movement, uniform_cost, MovementModel,
+///                Position};
+///
+/// let grid = vec![vec![' ', ' ', ' '],
+///                 vec![' ', ' ', ' '],
+///                 vec![' ', ' ', ' ']];
+/// let (path, cost) = find_path_with_movement(&grid,
+///                                            Position{x: 0, y: 0},
+///                                            Position{x: 2, y: 2},
+///                                            uniform_cost,
+///                                            MovementModel::EightWay).unwrap();
+/// assert_eq!(vec![Position{x: 0, y: 0},
+///                 Position{x: 1, y: 1},
+///                 Position{x: 2, y: 2}],
+///            path);
+/// assert_eq!(28, cost);
+/// ```
+#[inline]
+pub fn find_path_with_movement<F>(grid: &[Vec<char>],
+                                  start: Position,
+                                  goal: Position,
+                                  cost: F,
+                                  movement: MovementModel)
+        -> Option<(Vec<Position>, usize)>
+        where F: Fn(char) -> Option<usize> + Send + Sync {
+    let grid = Grid::from_ragged(grid).with_cost(cost);
+    AStar.search(&grid, start, goal, movement).path
+}
+
+/// Like `find_path` but first checking with `components`, which must have
+/// been labelled from `grid`, that the goal can be reached from the start,
+/// so that no cells are explored when it can't. Paths move according to
+/// `components.movement()`.
+///
+/// # Examples
+///
+/// ```
+/// use bestpath::{find_path_with_components, Components, Grid, MovementModel,
+///                Position};
+///
+/// let grid = vec![vec![' ', ' ', '*', ' '],
+///                 vec!['*', ' ', '*', ' '],
+///                 vec![' ', ' ', '*', ' ']];
+/// let components = Components::new(&Grid::new(&grid).unwrap(),
+///                                  MovementModel::FourWay);
+/// let start = Position{x: 0, y: 0};
+/// assert_eq!(Some(5), find_path_with_components(&grid, start,
+///                                               Position{x: 0, y: 2},
+///                                               &components)
+///                         .map(|path| path.len()));
+/// assert_eq!(None, find_path_with_components(&grid, start,
+///                                            Position{x: 3, y: 2},
+///                                            &components));
+/// ```
+pub fn find_path_with_components(grid: &[Vec<char>],
+                                 start: Position,
+                                 goal: Position,
+                                 components: &Components)
+        -> Option<Vec<Position>> {
+    components.search(&AStar, &Grid::from_ragged(grid), start, goal)
+        .path
+        .map(|(path, _)| path)
+}
+
+/// Like `find_weighted_path` with `uniform_cost`, but in a maze with keys and
+/// doors. Lowercase letters are keys and uppercase letters are doors, which
+/// can only be passed once the key with the same letter has been collected.
+/// See `KeyMaze` for other costs and movement models.
+///
+/// 'X' is a door here, so grids read with `MazeFile` need another goal
+/// marker.
+///
+/// # Examples
+///
+/// ```
+/// use bestpath::{find_path, find_path_with_keys, Position};
+///
+/// let grid = vec![vec!['b', ' ', 'B', ' '],
+///                 vec![' ', '*', '*', '*']];
+/// let (path, cost) = find_path_with_keys(&grid,
+///                                        Position{x: 0, y: 1},
+///                                        Position{x: 3, y: 0}).unwrap();
+/// assert_eq!(vec![Position{x: 0, y: 1},
+///                 Position{x: 0, y: 0},
+///                 Position{x: 1, y: 0},
+///                 Position{x: 2, y: 0},
+///                 Position{x: 3, y: 0}],
+///            path);
+/// assert_eq!(4, cost);
+/// assert_eq!(None,
+///            find_path(&grid, Position{x: 0, y: 1}, Position{x: 3, y: 0}));
+/// ```
+pub fn find_path_with_keys(grid: &[Vec<char>], start: Position, goal: Position)
+        -> Option<(Vec<Position>, usize)> {
+    KeyMaze::new(&Grid::from_ragged(grid), MovementModel::FourWay)
+        .search(start, goal).path
 }
 
 /// Generate a copy of the given grid with the path filled in. The starting
@@ -169,11 +303,11 @@ pub fn find_path(grid: &[Vec<char>], start: Position, goal: Position)
 pub fn format_path_map(grid: &[Vec<char>], path: &[Position])
         -> Vec<Vec<char>> {
     let mut m = grid.to_vec();
-    let last_path = path.len() - 1;
+    let last_path = path.len().saturating_sub(1);
     for (i, p) in path.iter().enumerate() {
         m[p.y][p.x] = match i {
-            0 => '@',
-            _ if i == last_path => 'X',
+            0 => START_MARKER,
+            _ if i == last_path => GOAL_MARKER,
             _ => 'o',
         }
     }
@@ -182,47 +316,29 @@ pub fn format_path_map(grid: &[Vec<char>], path: &[Position])
 
 #[cfg(test)]
 fn print_grid(grid: &[Vec<char>]) {
-    println!("");
+    println!();
     for row in grid {
         for ch in row {
             print!(" {} ", ch);
         }
-        println!("");
+        println!();
     }
 }
 
 #[test]
 fn no_path() {
-    let map =
-        vec![vec![' ', ' ', '█', ' ', ' ', ' ', ' '],
-             vec!['█', '█', ' ', ' ', '█', '█', ' '],
-             vec![' ', '█', ' ', '█', '█', ' ', '█'],
-             vec![' ', ' ', ' ', '█', ' ', '█', ' '],
-             vec!['█', ' ', ' ', ' ', ' ', '█', ' '],
-             vec![' ', '█', '█', '█', '█', ' ', ' '],
-             vec![' ', ' ', ' ', ' ', ' ', ' ', ' ']];
-
-    let path = find_path(&map, Position { x: 0, y: 0}, Position { x: 6, y: 6});
+    let maze = mazes::no_path();
+
+    let path = find_path(&maze.rows, maze.start, maze.goal);
     assert_eq!(None, path);
 }
 
 #[test]
 fn multiple_complex_paths_to_goal() {
-    let map =
-        vec![vec![' ', ' ', ' ', ' ', ' ', ' ', ' '],
-             vec![' ', '█', '█', '█', '█', ' ', ' '],
-             vec![' ', ' ', ' ', '█', '█', ' ', '█'],
-             vec![' ', '█', ' ', '█', ' ', ' ', ' '],
-             vec![' ', '█', ' ', '█', ' ', ' ', '█'],
-             vec![' ', ' ', ' ', '█', ' ', ' ', ' '],
-             vec![' ', '█', ' ', ' ', ' ', '█', ' '],
-             vec![' ', ' ', ' ', ' ', ' ', '█', ' '],
-             vec![' ', ' ', ' ', ' ', '█', ' ', ' ']];
+    let maze = mazes::multiple_complex_paths();
 
-    let path = find_path(&map,
-                         Position { x: 0, y: 0},
-                         Position { x: 6, y: 8}).unwrap();
-    let path_map = format_path_map(&map, &path);
+    let path = find_path(&maze.rows, maze.start, maze.goal).unwrap();
+    let path_map = format_path_map(&maze.rows, &path);
     print_grid(&path_map);
     assert_eq!(
         vec![vec!['@', 'o', 'o', 'o', 'o', 'o', ' '],
@@ -286,38 +402,29 @@ fn start_at_goal() {
 
 #[test]
 fn straight_diagonal_to_goal() {
-    let map =
-        vec![vec![' ', ' ', ' ', ' ', ' ', ' ', ' '],
-             vec![' ', ' ', ' ', ' ', ' ', ' ', ' '],
-             vec![' ', ' ', ' ', ' ', ' ', ' ', ' '],
-             vec![' ', ' ', ' ', ' ', ' ', ' ', ' '],
-             vec![' ', ' ', ' ', ' ', ' ', ' ', ' '],
-             vec![' ', ' ', ' ', ' ', ' ', ' ', ' '],
-             vec![' ', ' ', ' ', ' ', ' ', ' ', ' ']];
+    let maze = mazes::open_field();
 
-    let path = find_path(&map,
-                         Position { x: 0, y: 0},
-                         Position { x: 6, y: 6}).unwrap();
-    let path_map = format_path_map(&map, &path);
+    let path = find_path(&maze.rows, maze.start, maze.goal).unwrap();
+    let path_map = format_path_map(&maze.rows, &path);
     print_grid(&path_map);
-    assert_eq!(13, path.len())  // There are many equivalent paths.
+    // There are many equivalent paths; ties are broken by preferring the
+    // cell furthest from the start.
+    assert_eq!(path_map,
+               vec![vec!['@', ' ', ' ', ' ', ' ', ' ', ' '],
+                    vec!['o', ' ', ' ', ' ', ' ', ' ', ' '],
+                    vec!['o', ' ', ' ', ' ', ' ', ' ', ' '],
+                    vec!['o', ' ', ' ', ' ', ' ', ' ', ' '],
+                    vec!['o', ' ', ' ', ' ', ' ', ' ', ' '],
+                    vec!['o', ' ', ' ', ' ', ' ', ' ', ' '],
+                    vec!['o', 'o', 'o', 'o', 'o', 'o', 'X']]);
 }
 
 #[test]
 fn single_walled_path_to_goal() {
-    let map =
-        vec![vec![' ', ' ', ' ', ' ', ' ', ' ', ' '],
-             vec!['█', '█', '█', '█', '█', '█', ' '],
-             vec![' ', ' ', ' ', ' ', ' ', ' ', ' '],
-             vec![' ', '█', '█', '█', '█', '█', '█'],
-             vec![' ', ' ', ' ', ' ', ' ', ' ', ' '],
-             vec!['█', '█', '█', '█', '█', '█', ' '],
-             vec![' ', ' ', ' ', ' ', ' ', ' ', ' ']];
+    let maze = mazes::single_walled_path();
 
-    let path = find_path(&map,
-                         Position { x: 0, y: 0},
-                         Position { x: 0, y: 6}).unwrap();
-    let path_map = format_path_map(&map, &path);
+    let path = find_path(&maze.rows, maze.start, maze.goal).unwrap();
+    let path_map = format_path_map(&maze.rows, &path);
     print_grid(&path_map);
     assert_eq!(
         vec![vec!['@', 'o', 'o', 'o', 'o', 'o', 'o'],
@@ -332,21 +439,10 @@ fn single_walled_path_to_goal() {
 
 #[test]
 fn spiral_wall_path_to_goal() {
-    let map =
-        vec![vec![' ', ' ', ' ', ' ', ' ', ' ', ' ', ' '],
-             vec![' ', '█', '█', '█', '█', '█', '█', '█'],
-             vec![' ', '█', ' ', ' ', ' ', ' ', ' ', ' '],
-             vec![' ', '█', ' ', '█', '█', '█', '█', ' '],
-             vec![' ', '█', ' ', '█', ' ', ' ', '█', ' '],
-             vec![' ', '█', ' ', '█', '█', ' ', '█', ' '],
-             vec![' ', '█', ' ', ' ', ' ', ' ', '█', ' '],
-             vec![' ', '█', '█', '█', '█', '█', '█', ' '],
-             vec![' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ']];
+    let maze = mazes::spiral_wall();
 
-    let path = find_path(&map,
-                         Position { x: 0, y: 0},
-                         Position { x: 4, y: 4}).unwrap();
-    let path_map = format_path_map(&map, &path);
+    let path = find_path(&maze.rows, maze.start, maze.goal).unwrap();
+    let path_map = format_path_map(&maze.rows, &path);
     print_grid(&path_map);
     assert_eq!(
         vec![vec!['@', ' ', ' ', ' ', ' ', ' ', ' ', ' '],
@@ -360,3 +456,117 @@ fn spiral_wall_path_to_goal() {
              vec!['o', 'o', 'o', 'o', 'o', 'o', 'o', 'o']],
         path_map);
 }
+
+#[test]
+fn weighted_detour_around_expensive_terrain() {
+    let map =
+        vec![vec![' ', ' ', '9', ' ', ' '],
+             vec![' ', '█', '9', '█', ' '],
+             vec![' ', '█', ' ', '█', ' '],
+             vec![' ', ' ', ' ', ' ', ' ']];
+
+    let (path, cost) = find_weighted_path(&map,
+                                          Position { x: 0, y: 0},
+                                          Position { x: 4, y: 0},
+                                          terrain_cost).unwrap();
+    let path_map = format_path_map(&map, &path);
+    print_grid(&path_map);
+    assert_eq!(
+        vec![vec!['@', ' ', '9', ' ', 'X'],
+             vec!['o', '█', '9', '█', 'o'],
+             vec!['o', '█', ' ', '█', 'o'],
+             vec!['o', 'o', 'o', 'o', 'o']],
+        path_map);
+    assert_eq!(10, cost);
+}
+
+#[test]
+fn weighted_cheap_terrain_beats_long_detour() {
+    let map =
+        vec![vec![' ', ' ', '2', ' ', ' '],
+             vec![' ', '█', '█', '█', ' '],
+             vec![' ', '█', ' ', '█', ' '],
+             vec![' ', ' ', ' ', ' ', ' ']];
+
+    let (path, cost) = find_weighted_path(&map,
+                                          Position { x: 0, y: 0},
+                                          Position { x: 4, y: 0},
+                                          terrain_cost).unwrap();
+    let path_map = format_path_map(&map, &path);
+    print_grid(&path_map);
+    assert_eq!(
+        vec![vec!['@', 'o', 'o', 'o', 'X'],
+             vec![' ', '█', '█', '█', ' '],
+             vec![' ', '█', ' ', '█', ' '],
+             vec![' ', ' ', ' ', ' ', ' ']],
+        path_map);
+    assert_eq!(5, cost);
+}
+
+#[test]
+fn uniform_cost_treats_digits_as_walls() {
+    let map =
+        vec![vec![' ', '1', ' ']];
+
+    assert_eq!(None, find_path(&map, Position { x: 0, y: 0},
+                               Position { x: 2, y: 0}));
+    let (_, cost) = find_weighted_path(&map,
+                                       Position { x: 0, y: 0},
+                                       Position { x: 2, y: 0},
+                                       terrain_cost).unwrap();
+    assert_eq!(2, cost);
+}
+
+#[test]
+fn weighted_start_at_goal_is_free() {
+    let map =
+        vec![vec!['9']];
+
+    let (path, cost) = find_weighted_path(&map,
+                                          Position { x: 0, y: 0},
+                                          Position { x: 0, y: 0},
+                                          terrain_cost).unwrap();
+    assert_eq!(vec![Position{x: 0, y: 0 }], path);
+    assert_eq!(0, cost);
+}
+
+#[test]
+fn ragged_rows_are_walled_off() {
+    let map =
+        vec![vec![' ', ' ', ' '],
+             vec![' '],
+             vec![' ', ' ', ' ', ' ']];
+
+    let path = find_path(&map,
+                         Position { x: 2, y: 0},
+                         Position { x: 3, y: 2}).unwrap();
+    assert_eq!(
+        vec![vec!['o', 'o', '@'],
+             vec!['o'],
+             vec!['o', 'o', 'o', 'X']],
+        format_path_map(&map, &path));
+    assert_eq!(None, find_path(&map, Position { x: 0, y: 0},
+                               Position { x: 2, y: 1}));
+    assert_eq!(None, find_weighted_path(&map, Position { x: 0, y: 0},
+                                        Position { x: 2, y: 1},
+                                        |_| Some(1)));
+    assert_eq!(Some(7),
+               find_path_with_keys(&map,
+                                   Position { x: 2, y: 0},
+                                   Position { x: 3, y: 2})
+                   .map(|(_, cost)| cost));
+}
+
+#[test]
+fn components_agree_with_find_path() {
+    for maze in mazes::solvable().into_iter().chain(Some(mazes::no_path())) {
+        let components = Components::new(&maze.grid(),
+                                         MovementModel::FourWay);
+        for &goal in [maze.goal, Position { x: 6, y: 0 },
+                      Position { x: 3, y: 3 }].iter() {
+            assert_eq!(find_path(&maze.rows, maze.start, goal),
+                       find_path_with_components(&maze.rows, maze.start, goal,
+                                                 &components));
+        }
+    }
+}
@@ -0,0 +1,170 @@
+//! Find the best path through a maze read from a file and print it.
+//!
+//! The maze file has one row of the grid per line with the start marked by a
+//! '@' and the goal marked by a 'X'. Spaces are open and every other character
+//! is an obstacle, unless `--terrain` is given in which case the digits '1' to
+//! '9' are open with that cost.
+
+extern crate bestpath;
+
+use bestpath::{terrain_cost, uniform_cost, AStar, MazeFile, MovementModel,
+               Pathfinder, Picture, Trace, GOAL_MARKER, START_MARKER};
+use std::env;
+use std::fs::File;
+use std::io::prelude::*;
+use std::process;
+
+const USAGE: &str = "\
+usage: bestpath [options] MAZE_FILE
+
+options:
+    -m, --movement MODEL  how the path may move: four (the default), eight,
+                          eight-no-corner-cutting or hex
+    -t, --terrain         treat the digits 1-9 as terrain with that cost
+    -f, --format FORMAT   how to print the path: text (the default), ansi
+                          for colours in a terminal, or svg or ppm for an
+                          image that also shows the cells explored
+    -h, --help            print this help";
+
+/// Print `message` and the usage to stderr and exit.
+fn usage_error(message: &str) -> ! {
+    eprintln!("bestpath: {}\n\n{}", message, USAGE);
+    process::exit(2);
+}
+
+/// How the path is printed.
+#[derive(Copy, Clone, PartialEq)]
+enum Format {
+    Text,
+    Ansi,
+    Svg,
+    Ppm,
+}
+
+/// The size of each cell in the pixels of an image.
+const CELL_SIZE: usize = 16;
+
+fn parse_format(name: &str) -> Format {
+    match name {
+        "text" => Format::Text,
+        "ansi" => Format::Ansi,
+        "svg" => Format::Svg,
+        "ppm" => Format::Ppm,
+        _ => usage_error(&format!("unknown format {:?}", name)),
+    }
+}
+
+fn parse_movement(name: &str) -> MovementModel {
+    match name {
+        "four" => MovementModel::FourWay,
+        "eight" => MovementModel::EightWay,
+        "eight-no-corner-cutting" => MovementModel::EightWayNoCornerCutting,
+        "hex" => MovementModel::Hex,
+        _ => usage_error(&format!("unknown movement model {:?}", name)),
+    }
+}
+
+/// Read and parse the maze at `path`, exiting with an error message if it
+/// can't be read or isn't a valid maze.
+fn load_maze(path: &str) -> MazeFile {
+    let mut text = String::new();
+    if let Err(why) = File::open(path).and_then(|mut f| {
+        f.read_to_string(&mut text)
+    }) {
+        eprintln!("bestpath: couldn't read {}: {}", path, why);
+        process::exit(2);
+    }
+    match text.parse() {
+        Ok(maze) => maze,
+        Err(why) => {
+            eprintln!("bestpath: invalid maze {}: {}", path, why);
+            process::exit(2);
+        }
+    }
+}
+
+fn main() {
+    let mut movement = MovementModel::FourWay;
+    let mut terrain = false;
+    let mut format = Format::Text;
+    let mut maze_path = None;
+
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-h" | "--help" => {
+                println!("{}", USAGE);
+                return;
+            }
+            "-t" | "--terrain" => terrain = true,
+            "-m" | "--movement" => match args.next() {
+                Some(name) => movement = parse_movement(&name),
+                None => usage_error("--movement requires a MODEL"),
+            },
+            "-f" | "--format" => match args.next() {
+                Some(name) => format = parse_format(&name),
+                None => usage_error("--format requires a FORMAT"),
+            },
+            _ if arg.starts_with('-') =>
+                usage_error(&format!("unknown option {}", arg)),
+            _ if maze_path.is_some() => usage_error("too many arguments"),
+            _ => maze_path = Some(arg),
+        }
+    }
+    let maze_path = match maze_path {
+        Some(path) => path,
+        None => usage_error("no MAZE_FILE given"),
+    };
+
+    let maze = load_maze(&maze_path);
+    let grid = if terrain {
+        maze.grid.with_cost(terrain_cost)
+    } else {
+        maze.grid.with_cost(uniform_cost)
+    };
+
+    let mut trace = Trace::default();
+    let result = AStar.search_observed(&grid, maze.start, maze.goal, movement,
+                                       &mut trace);
+    // The explored cells are drawn even when there is no path, to show how
+    // far the search got.
+    let explored = trace.expanded();
+    let path: &[_] = match result.path {
+        Some((ref path, _)) => path,
+        None => &[],
+    };
+    let picture = Picture::new(&grid).with_path(path)
+                                     .with_movement(movement)
+                                     .with_explored(&explored);
+    match format {
+        Format::Text if result.path.is_none() => {
+            let mut explored = trace.format(&grid, None);
+            explored[maze.start] = START_MARKER;
+            explored[maze.goal] = GOAL_MARKER;
+            print!("{}", explored)
+        }
+        Format::Text => print!("{}", grid.format_path(path)),
+        Format::Ansi => print!("{}", picture.ansi()),
+        Format::Svg => print!("{}", picture.svg(CELL_SIZE)),
+        Format::Ppm => {
+            let ppm = picture.ppm(CELL_SIZE);
+            if let Err(why) = std::io::stdout().write_all(&ppm) {
+                eprintln!("bestpath: couldn't write image: {}", why);
+                process::exit(2);
+            }
+        }
+    }
+    match result.path {
+        Some((ref path, cost)) => {
+            if format == Format::Text || format == Format::Ansi {
+                println!("length: {}", path.len() - 1);
+                println!("cost: {}", cost);
+            }
+        }
+        None => {
+            eprintln!("bestpath: no path from ({}, {}) to ({}, {})",
+                      maze.start.x, maze.start.y, maze.goal.x, maze.goal.y);
+            process::exit(1);
+        }
+    }
+}
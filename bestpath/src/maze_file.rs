@@ -0,0 +1,190 @@
+//! Reading mazes from text, with the start and goal marked the same way that
+//! `format_path_map` draws them.
+
+use std::error::Error;
+use std::fmt;
+use std::str::FromStr;
+
+use {Grid, Position, RaggedRowsError};
+
+/// The character that marks the starting position in a maze file.
+pub const START_MARKER: char = '@';
+
+/// The character that marks the goal position in a maze file.
+pub const GOAL_MARKER: char = 'X';
+
+/// Returned when a maze file can't be parsed.
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum ParseMazeError {
+    /// The maze has no start marker.
+    MissingStart,
+    /// The maze has no goal marker.
+    MissingGoal,
+    /// The maze has a start marker at both positions.
+    MultipleStarts(Position, Position),
+    /// The maze has a goal marker at both positions.
+    MultipleGoals(Position, Position),
+    /// The rows of the maze are not all the same length.
+    RaggedRows(RaggedRowsError),
+}
+
+impl fmt::Display for ParseMazeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ParseMazeError::MissingStart =>
+                write!(f, "no start marker '{}'", START_MARKER),
+            ParseMazeError::MissingGoal =>
+                write!(f, "no goal marker '{}'", GOAL_MARKER),
+            ParseMazeError::MultipleStarts(p1, p2) =>
+                write!(f, "start marker '{}' at both ({}, {}) and ({}, {})",
+                       START_MARKER, p1.x, p1.y, p2.x, p2.y),
+            ParseMazeError::MultipleGoals(p1, p2) =>
+                write!(f, "goal marker '{}' at both ({}, {}) and ({}, {})",
+                       GOAL_MARKER, p1.x, p1.y, p2.x, p2.y),
+            ParseMazeError::RaggedRows(ref why) => why.fmt(f),
+        }
+    }
+}
+
+impl Error for ParseMazeError {}
+
+impl From<RaggedRowsError> for ParseMazeError {
+    fn from(why: RaggedRowsError) -> ParseMazeError {
+        ParseMazeError::RaggedRows(why)
+    }
+}
+
+/// A maze read from text. Each line of the text is a row of the grid, with a
+/// single `START_MARKER` and a single `GOAL_MARKER`. The markers are replaced
+/// with spaces in `grid`. Trailing blank lines are ignored.
+///
+/// # Examples
+///
+/// ```
+/// use bestpath::{MazeFile, Position};
+///
+/// let maze: MazeFile = "@ █\n  X\n".parse().unwrap();
+/// assert_eq!(Position{x: 0, y: 0}, maze.start);
+/// assert_eq!(Position{x: 2, y: 1}, maze.goal);
+/// assert_eq!(vec![vec![' ', ' ', '█'], vec![' ', ' ', ' ']],
+///            maze.grid.to_rows());
+/// ```
+#[derive(Clone, Debug)]
+pub struct MazeFile {
+    pub grid: Grid<'static>,
+    pub start: Position,
+    pub goal: Position,
+}
+
+/// Record `pos` as the position of a marker, or return an error built by
+/// `multiple` if the marker has already been seen.
+fn set_marker<E>(marker: &mut Option<Position>, pos: Position, multiple: E)
+        -> Result<(), ParseMazeError>
+        where E: Fn(Position, Position) -> ParseMazeError {
+    match *marker {
+        Some(first) => Err(multiple(first, pos)),
+        None => {
+            *marker = Some(pos);
+            Ok(())
+        }
+    }
+}
+
+impl FromStr for MazeFile {
+    type Err = ParseMazeError;
+
+    fn from_str(s: &str) -> Result<MazeFile, ParseMazeError> {
+        let mut rows: Vec<Vec<char>> =
+            s.lines().map(|line| line.chars().collect()).collect();
+        while rows.last().is_some_and(|row| row.is_empty()) {
+            rows.pop();
+        }
+
+        let mut start = None;
+        let mut goal = None;
+        for (y, row) in rows.iter_mut().enumerate() {
+            for (x, ch) in row.iter_mut().enumerate() {
+                let pos = Position { x, y };
+                match *ch {
+                    START_MARKER => set_marker(&mut start, pos,
+                                               ParseMazeError::MultipleStarts)?,
+                    GOAL_MARKER => set_marker(&mut goal, pos,
+                                              ParseMazeError::MultipleGoals)?,
+                    _ => continue,
+                }
+                *ch = ' ';
+            }
+        }
+
+        let grid = Grid::new(&rows)?;
+        Ok(MazeFile {
+            grid,
+            start: start.ok_or(ParseMazeError::MissingStart)?,
+            goal: goal.ok_or(ParseMazeError::MissingGoal)?,
+        })
+    }
+}
+
+#[cfg(test)]
+use {find_path, format_path_map, mazes};
+
+#[test]
+fn parses_markers_and_strips_them() {
+    let maze: MazeFile = "█@ \n X█\n\n\n".parse().unwrap();
+    assert_eq!(Position { x: 1, y: 0 }, maze.start);
+    assert_eq!(Position { x: 1, y: 1 }, maze.goal);
+    assert_eq!(vec![vec!['█', ' ', ' '],
+                    vec![' ', ' ', '█']],
+               maze.grid.to_rows());
+}
+
+#[test]
+fn accepts_windows_line_endings() {
+    let maze: MazeFile = "@ \r\n X\r\n".parse().unwrap();
+    assert_eq!((2, 2), (maze.grid.width(), maze.grid.height()));
+}
+
+#[test]
+fn reports_missing_markers() {
+    assert_eq!(ParseMazeError::MissingStart,
+               " X\n  ".parse::<MazeFile>().unwrap_err());
+    assert_eq!(ParseMazeError::MissingGoal,
+               " @\n  ".parse::<MazeFile>().unwrap_err());
+    assert_eq!(ParseMazeError::MissingStart,
+               "".parse::<MazeFile>().unwrap_err());
+}
+
+#[test]
+fn reports_multiple_markers() {
+    assert_eq!(ParseMazeError::MultipleStarts(Position { x: 0, y: 0 },
+                                              Position { x: 1, y: 1 }),
+               "@X\n @".parse::<MazeFile>().unwrap_err());
+    assert_eq!(ParseMazeError::MultipleGoals(Position { x: 1, y: 0 },
+                                             Position { x: 0, y: 1 }),
+               "@X\nX ".parse::<MazeFile>().unwrap_err());
+}
+
+#[test]
+fn reports_ragged_rows() {
+    let err = "@  \n X\n   ".parse::<MazeFile>().unwrap_err();
+    assert_eq!(ParseMazeError::RaggedRows(RaggedRowsError { row: 1,
+                                                            expected: 3,
+                                                            found: 2 }),
+               err);
+    assert_eq!("row 1 has 2 cells but row 0 has 3", err.to_string());
+}
+
+#[test]
+fn round_trips_format_path_map() {
+    for maze in mazes::solvable() {
+        let path = find_path(&maze.rows, maze.start, maze.goal).unwrap();
+        let text = Grid::new(&format_path_map(&maze.rows, &path))
+            .unwrap()
+            .to_string()
+            .replace('o', " ");
+        let parsed: MazeFile = text.parse().unwrap();
+        assert_eq!(maze.start, parsed.start);
+        assert_eq!(maze.goal, parsed.goal);
+        assert_eq!(maze.rows, parsed.grid.to_rows());
+    }
+}
@@ -0,0 +1,124 @@
+//! Mazes shared by the tests of every module.
+
+use {terrain_cost, Grid, MovementModel, Position};
+
+/// Every movement model, for properties that should hold whichever is used.
+pub const ALL_MODELS: [MovementModel; 4] = [
+    MovementModel::FourWay,
+    MovementModel::EightWay,
+    MovementModel::EightWayNoCornerCutting,
+    MovementModel::Hex,
+];
+
+/// A maze along with the start and goal positions that its tests use.
+pub struct Maze {
+    pub rows: Vec<Vec<char>>,
+    pub start: Position,
+    pub goal: Position,
+}
+
+impl Maze {
+    pub fn grid(&self) -> Grid<'static> {
+        Grid::new(&self.rows).unwrap()
+    }
+}
+
+/// The goal is walled off from the start.
+pub fn no_path() -> Maze {
+    Maze {
+        rows: vec![vec![' ', ' ', '█', ' ', ' ', ' ', ' '],
+                   vec!['█', '█', ' ', ' ', '█', '█', ' '],
+                   vec![' ', '█', ' ', '█', '█', ' ', '█'],
+                   vec![' ', ' ', ' ', '█', ' ', '█', ' '],
+                   vec!['█', ' ', ' ', ' ', ' ', '█', ' '],
+                   vec![' ', '█', '█', '█', '█', ' ', ' '],
+                   vec![' ', ' ', ' ', ' ', ' ', ' ', ' ']],
+        start: Position { x: 0, y: 0 },
+        goal: Position { x: 6, y: 6 },
+    }
+}
+
+pub fn multiple_complex_paths() -> Maze {
+    Maze {
+        rows: vec![vec![' ', ' ', ' ', ' ', ' ', ' ', ' '],
+                   vec![' ', '█', '█', '█', '█', ' ', ' '],
+                   vec![' ', ' ', ' ', '█', '█', ' ', '█'],
+                   vec![' ', '█', ' ', '█', ' ', ' ', ' '],
+                   vec![' ', '█', ' ', '█', ' ', ' ', '█'],
+                   vec![' ', ' ', ' ', '█', ' ', ' ', ' '],
+                   vec![' ', '█', ' ', ' ', ' ', '█', ' '],
+                   vec![' ', ' ', ' ', ' ', ' ', '█', ' '],
+                   vec![' ', ' ', ' ', ' ', '█', ' ', ' ']],
+        start: Position { x: 0, y: 0 },
+        goal: Position { x: 6, y: 8 },
+    }
+}
+
+/// A 7x7 grid without obstacles, crossed corner to corner.
+pub fn open_field() -> Maze {
+    Maze {
+        rows: vec![vec![' '; 7]; 7],
+        start: Position { x: 0, y: 0 },
+        goal: Position { x: 6, y: 6 },
+    }
+}
+
+pub fn single_walled_path() -> Maze {
+    Maze {
+        rows: vec![vec![' ', ' ', ' ', ' ', ' ', ' ', ' '],
+                   vec!['█', '█', '█', '█', '█', '█', ' '],
+                   vec![' ', ' ', ' ', ' ', ' ', ' ', ' '],
+                   vec![' ', '█', '█', '█', '█', '█', '█'],
+                   vec![' ', ' ', ' ', ' ', ' ', ' ', ' '],
+                   vec!['█', '█', '█', '█', '█', '█', ' '],
+                   vec![' ', ' ', ' ', ' ', ' ', ' ', ' ']],
+        start: Position { x: 0, y: 0 },
+        goal: Position { x: 0, y: 6 },
+    }
+}
+
+pub fn spiral_wall() -> Maze {
+    Maze {
+        rows: vec![vec![' ', ' ', ' ', ' ', ' ', ' ', ' ', ' '],
+                   vec![' ', '█', '█', '█', '█', '█', '█', '█'],
+                   vec![' ', '█', ' ', ' ', ' ', ' ', ' ', ' '],
+                   vec![' ', '█', ' ', '█', '█', '█', '█', ' '],
+                   vec![' ', '█', ' ', '█', ' ', ' ', '█', ' '],
+                   vec![' ', '█', ' ', '█', '█', ' ', '█', ' '],
+                   vec![' ', '█', ' ', ' ', ' ', ' ', '█', ' '],
+                   vec![' ', '█', '█', '█', '█', '█', '█', ' '],
+                   vec![' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ']],
+        start: Position { x: 0, y: 0 },
+        goal: Position { x: 4, y: 4 },
+    }
+}
+
+/// Every maze above that has a path from start to goal.
+pub fn solvable() -> Vec<Maze> {
+    vec![multiple_complex_paths(),
+         open_field(),
+         single_walled_path(),
+         spiral_wall()]
+}
+
+/// Generate a `width` x `height` grid where roughly one in `one_in` cells is
+/// an obstacle and some others are '5' terrain, using a simple linear
+/// congruential generator.
+pub fn random_grid(seed: u64, width: usize, height: usize, one_in: u64)
+        -> Grid<'static> {
+    let mut state = seed;
+    let mut next = || {
+        state = state.wrapping_mul(6364136223846793005)
+                     .wrapping_add(1442695040888963407);
+        state >> 33
+    };
+    let mut grid = Grid::filled(width, height, ' ').with_cost(terrain_cost);
+    for p in grid.clone().positions() {
+        grid[p] = match next() % one_in {
+            0 => '█',
+            n if n % 3 == 0 => '5',
+            _ => ' ',
+        };
+    }
+    grid
+}
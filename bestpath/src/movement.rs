@@ -0,0 +1,355 @@
+//! The ways of moving from one cell to the next, each paired with an admissible
+//! heuristic for A*.
+
+use {Grid, Position};
+
+/// The cost of an orthogonal step in the 8-connected movement models, before
+/// being multiplied by the cost of the cell entered. Costs are scaled so that
+/// diagonal steps can cost approximately √2 times as much without floating
+/// point.
+pub const ORTHOGONAL_STEP: usize = 10;
+
+/// The cost of a diagonal step in the 8-connected movement models, before
+/// being multiplied by the cost of the cell entered.
+pub const DIAGONAL_STEP: usize = 14;
+
+/// How a path may move between cells.
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug, Default)]
+pub enum MovementModel {
+    /// Up, down, left and right. Each step costs the cost of the cell entered.
+    #[default]
+    FourWay,
+    /// Orthogonal and diagonal steps. Diagonal steps may squeeze between two
+    /// obstacles. Costs are scaled by `ORTHOGONAL_STEP` and `DIAGONAL_STEP`.
+    EightWay,
+    /// Like `EightWay` but a diagonal step is only allowed when both of the
+    /// orthogonal cells that it passes are passable.
+    EightWayNoCornerCutting,
+    /// A hexagonal grid in "odd-r" layout i.e. odd rows are shifted half a cell
+    /// to the right. Every cell has six neighbours: two in its own row and two
+    /// in each of the rows above and below. Each step costs the cost of the
+    /// cell entered.
+    Hex,
+}
+
+const ORTHOGONAL_OFFSETS: [(isize, isize); 4] =
+    [(-1, 0), (1, 0), (0, -1), (0, 1)];
+const DIAGONAL_OFFSETS: [(isize, isize); 4] =
+    [(-1, -1), (1, -1), (-1, 1), (1, 1)];
+const EVEN_ROW_HEX_OFFSETS: [(isize, isize); 6] =
+    [(-1, 0), (1, 0), (-1, -1), (0, -1), (-1, 1), (0, 1)];
+const ODD_ROW_HEX_OFFSETS: [(isize, isize); 6] =
+    [(-1, 0), (1, 0), (0, -1), (1, -1), (0, 1), (1, 1)];
+
+#[inline]
+fn distances((x1, y1): (isize, isize), (x2, y2): (isize, isize))
+        -> (usize, usize) {
+    (x1.abs_diff(x2), y1.abs_diff(y2))
+}
+
+/// Convert "odd-r" offset coordinates to axial (q, r) coordinates.
+#[inline]
+fn hex_axial((x, y): (isize, isize)) -> (isize, isize) {
+    (x - (y - (y & 1)) / 2, y)
+}
+
+impl MovementModel {
+    /// Return the passable cells reachable from `pos` in one step, along
+    /// with the amount that the cost of entering each cell is multiplied by
+    /// when taking that step.
+    pub(crate) fn moves(self, grid: &Grid, pos: Position)
+            -> Vec<(Position, usize)> {
+        self.moves_with(grid, pos, |p, d| grid.offset(p, d))
+    }
+
+    /// Like `moves` but with `offset` finding the cell a step away from
+    /// another, so that steps may, for example, leave one edge of the grid
+    /// and enter the opposite edge.
+    pub(crate) fn moves_with<F>(self, grid: &Grid, pos: Position, offset: F)
+            -> Vec<(Position, usize)>
+            where F: Fn(Position, (isize, isize)) -> Option<Position> {
+        let mut v = Vec::new();
+        let mut push = |d, multiplier| {
+            if let Some(p) = offset(pos, d) {
+                if grid.is_passable(p) {
+                    v.push((p, multiplier));
+                }
+            }
+        };
+
+        match self {
+            MovementModel::FourWay => {
+                for &d in ORTHOGONAL_OFFSETS.iter() {
+                    push(d, 1);
+                }
+            }
+            MovementModel::EightWay => {
+                for &d in ORTHOGONAL_OFFSETS.iter() {
+                    push(d, ORTHOGONAL_STEP);
+                }
+                for &d in DIAGONAL_OFFSETS.iter() {
+                    push(d, DIAGONAL_STEP);
+                }
+            }
+            MovementModel::EightWayNoCornerCutting => {
+                for &d in ORTHOGONAL_OFFSETS.iter() {
+                    push(d, ORTHOGONAL_STEP);
+                }
+                let open = |d| {
+                    offset(pos, d).is_some_and(|p| grid.is_passable(p))
+                };
+                for &(dx, dy) in DIAGONAL_OFFSETS.iter() {
+                    if open((dx, 0)) && open((0, dy)) {
+                        push((dx, dy), DIAGONAL_STEP);
+                    }
+                }
+            }
+            MovementModel::Hex => {
+                let offsets = match pos.y % 2 {
+                    0 => &EVEN_ROW_HEX_OFFSETS,
+                    _ => &ODD_ROW_HEX_OFFSETS,
+                };
+                for &d in offsets.iter() {
+                    push(d, 1);
+                }
+            }
+        }
+        v
+    }
+
+    /// Return the passable cells reachable from `pos` in one step along with
+    /// the cost of each step.
+    pub(crate) fn neighbours(self, grid: &Grid, pos: Position)
+            -> Vec<(Position, usize)> {
+        self.moves(grid, pos)
+            .into_iter()
+            .filter_map(|(p, multiplier)| {
+                grid.cost(p).map(|c| (p, c * multiplier))
+            })
+            .collect()
+    }
+
+    /// Return the passable cells from which `pos` can be reached in one step
+    /// along with the cost of each step. Every movement model is symmetric so
+    /// these are the cells reachable from `pos`, but the cost is that of
+    /// entering `pos` rather than the other cell.
+    pub(crate) fn reverse_neighbours(self, grid: &Grid, pos: Position)
+            -> Vec<(Position, usize)> {
+        match grid.cost(pos) {
+            Some(c) => self.moves(grid, pos)
+                .into_iter()
+                .map(|(p, multiplier)| (p, c * multiplier))
+                .collect(),
+            None => Vec::new(),
+        }
+    }
+
+    /// A lower bound on the cost of moving from `p1` to `p2`, assuming that
+    /// every cell costs at least 1 to enter.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bestpath::{MovementModel, Position};
+    ///
+    /// let from = Position{x: 0, y: 0};
+    /// let to = Position{x: 3, y: 1};
+    /// assert_eq!(4, MovementModel::FourWay.heuristic(from, to));
+    /// assert_eq!(34, MovementModel::EightWay.heuristic(from, to));
+    /// assert_eq!(4, MovementModel::Hex.heuristic(from, to));
+    /// ```
+    #[inline]
+    pub fn heuristic(self, p1: Position, p2: Position) -> usize {
+        self.heuristic_between((p1.x as isize, p1.y as isize),
+                               (p2.x as isize, p2.y as isize))
+    }
+
+    /// Like `heuristic` but between cells given as (x, y), which may lie
+    /// outside of the grid, such as copies of the goal beyond an edge that
+    /// paths wrap around.
+    #[inline]
+    pub(crate) fn heuristic_between(self, p1: (isize, isize),
+                                    p2: (isize, isize)) -> usize {
+        match self {
+            // Manhattan distance, since diagonal moves aren't allowed.
+            MovementModel::FourWay => {
+                let (dx, dy) = distances(p1, p2);
+                dx + dy
+            }
+            // Octile distance: move diagonally until level with the goal and
+            // then straight.
+            MovementModel::EightWay |
+            MovementModel::EightWayNoCornerCutting => {
+                let (dx, dy) = distances(p1, p2);
+                let (short, long) = if dx < dy { (dx, dy) } else { (dy, dx) };
+                short * DIAGONAL_STEP + (long - short) * ORTHOGONAL_STEP
+            }
+            // The number of hexes between the two cells.
+            MovementModel::Hex => {
+                let (q1, r1) = hex_axial(p1);
+                let (q2, r2) = hex_axial(p2);
+                let (dq, dr) = (q1 - q2, r1 - r2);
+                ((dq.abs() + dr.abs() + (dq + dr).abs()) / 2) as usize
+            }
+        }
+    }
+
+    /// Returns true if a path can move from `p1` to `p2` in a single step,
+    /// ignoring obstacles.
+    pub(crate) fn is_step(self, p1: Position, p2: Position) -> bool {
+        let (dx, dy) = (p1.x.abs_diff(p2.x), p1.y.abs_diff(p2.y));
+        match self {
+            MovementModel::FourWay => dx + dy == 1,
+            MovementModel::EightWay |
+            MovementModel::EightWayNoCornerCutting => dx.max(dy) == 1,
+            MovementModel::Hex => self.heuristic(p1, p2) == 1,
+        }
+    }
+}
+
+#[cfg(test)]
+use {find_path_with_movement, mazes, uniform_cost, Dijkstra, Pathfinder};
+#[cfg(test)]
+use mazes::ALL_MODELS;
+
+/// The cost of the cheapest path found without a heuristic, to check that each
+/// heuristic is admissible.
+#[cfg(test)]
+fn dijkstra_cost(grid: &Grid,
+                 movement: MovementModel,
+                 start: Position,
+                 goal: Position) -> Option<usize> {
+    Dijkstra.search(grid, start, goal, movement).path.map(|(_, cost)| cost)
+}
+
+/// Check that `path` goes from start to goal in legal steps and return its
+/// cost.
+#[cfg(test)]
+fn check_path(grid: &Grid,
+              movement: MovementModel,
+              path: &[Position],
+              start: Position,
+              goal: Position) -> usize {
+    assert_eq!(Some(&start), path.first());
+    assert_eq!(Some(&goal), path.last());
+    let mut cost = 0;
+    for step in path.windows(2) {
+        assert!(movement.is_step(step[0], step[1]),
+                "{:?} -> {:?} is not a step", step[0], step[1]);
+        let &(_, c) = movement.neighbours(grid, step[0])
+            .iter()
+            .find(|&&(p, _)| p == step[1])
+            .expect("path enters an obstacle or cuts a corner");
+        cost += c;
+    }
+    cost
+}
+
+#[test]
+fn every_model_finds_optimal_paths_through_mazes() {
+    for &movement in ALL_MODELS.iter() {
+        for maze in mazes::solvable() {
+            let (path, cost) = find_path_with_movement(
+                &maze.rows, maze.start, maze.goal, uniform_cost, movement)
+                .unwrap();
+            let grid = maze.grid();
+            assert_eq!(cost,
+                       check_path(&grid, movement, &path,
+                                  maze.start, maze.goal));
+            assert_eq!(dijkstra_cost(&grid, movement, maze.start, maze.goal),
+                       Some(cost),
+                       "{:?}", movement);
+        }
+    }
+}
+
+#[test]
+fn every_model_reports_no_path() {
+    let maze = mazes::no_path();
+    for &movement in [MovementModel::FourWay,
+                      MovementModel::EightWayNoCornerCutting].iter() {
+        assert_eq!(None,
+                   find_path_with_movement(&maze.rows, maze.start, maze.goal,
+                                           uniform_cost, movement));
+    }
+}
+
+#[test]
+fn heuristics_never_overestimate() {
+    let maze = mazes::open_field();
+    let grid = maze.grid();
+    for &movement in ALL_MODELS.iter() {
+        for goal in grid.positions() {
+            assert!(movement.heuristic(maze.start, goal) <=
+                    dijkstra_cost(&grid, movement, maze.start, goal).unwrap());
+        }
+    }
+}
+
+#[test]
+fn eight_way_crosses_open_field_diagonally() {
+    let maze = mazes::open_field();
+    let (path, cost) = find_path_with_movement(
+        &maze.rows, maze.start, maze.goal, uniform_cost,
+        MovementModel::EightWay).unwrap();
+    assert_eq!(7, path.len());
+    assert_eq!(6 * DIAGONAL_STEP, cost);
+}
+
+#[test]
+fn eight_way_squeezes_between_obstacles() {
+    let grid =
+        vec![vec![' ', '█'],
+             vec!['█', ' ']];
+    let start = Position { x: 0, y: 0 };
+    let goal = Position { x: 1, y: 1 };
+
+    let (path, _) = find_path_with_movement(
+        &grid, start, goal, uniform_cost, MovementModel::EightWay).unwrap();
+    assert_eq!(vec![start, goal], path);
+    assert_eq!(None,
+               find_path_with_movement(
+                   &grid, start, goal, uniform_cost,
+                   MovementModel::EightWayNoCornerCutting));
+}
+
+#[test]
+fn no_corner_cutting_goes_around_corners() {
+    let grid =
+        vec![vec![' ', '█'],
+             vec![' ', ' ']];
+    let start = Position { x: 0, y: 0 };
+    let goal = Position { x: 1, y: 1 };
+
+    let (path, cost) = find_path_with_movement(
+        &grid, start, goal, uniform_cost,
+        MovementModel::EightWayNoCornerCutting).unwrap();
+    assert_eq!(vec![start, Position { x: 0, y: 1 }, goal], path);
+    assert_eq!(2 * ORTHOGONAL_STEP, cost);
+}
+
+#[test]
+fn hex_neighbours_depend_on_row() {
+    let grid = Grid::filled(3, 3, ' ');
+    let mut even: Vec<Position> = MovementModel::Hex
+        .neighbours(&grid, Position { x: 1, y: 0 })
+        .into_iter().map(|(p, _)| p).collect();
+    even.sort_by_key(|p| (p.y, p.x));
+    assert_eq!(vec![Position { x: 0, y: 0 },
+                    Position { x: 2, y: 0 },
+                    Position { x: 0, y: 1 },
+                    Position { x: 1, y: 1 }],
+               even);
+
+    let mut odd: Vec<Position> = MovementModel::Hex
+        .neighbours(&grid, Position { x: 1, y: 1 })
+        .into_iter().map(|(p, _)| p).collect();
+    odd.sort_by_key(|p| (p.y, p.x));
+    assert_eq!(vec![Position { x: 1, y: 0 },
+                    Position { x: 2, y: 0 },
+                    Position { x: 0, y: 1 },
+                    Position { x: 2, y: 1 },
+                    Position { x: 1, y: 2 },
+                    Position { x: 2, y: 2 }],
+               odd);
+}
@@ -0,0 +1,528 @@
+//! Planning paths for several agents that move at the same time, so that no
+//! two agents are ever in the same cell or swap cells with each other.
+//!
+//! Plans are timed: `path[t]` is where the agent is after `t` steps, and
+//! every step either moves to a neighbouring cell or waits. Once an agent
+//! reaches the end of its path it stays at its goal.
+
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap, HashSet, VecDeque};
+
+use graph::NodeMap;
+use search::{best_first, SearchState};
+use search_cells::SearchCells;
+use {Graph, Grid, MovementModel, Position, TieBreak};
+
+/// Where an agent starts and where it needs to go.
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
+pub struct Agent {
+    pub start: Position,
+    pub goal: Position,
+}
+
+/// Two agents colliding. `agents` holds their indices, smallest first.
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
+pub enum Conflict {
+    /// Both agents are at `position` at `time`.
+    Vertex { agents: (usize, usize), position: Position, time: usize },
+    /// Between `time - 1` and `time` the first agent moves from `from` to
+    /// `to` while the second moves from `to` to `from`.
+    Edge { agents: (usize, usize), from: Position, to: Position, time: usize },
+}
+
+/// Where an agent following `path` is at `time`.
+#[inline]
+fn position_at(path: &[Position], time: usize) -> Position {
+    path[time.min(path.len() - 1)]
+}
+
+/// The earliest collision between agents following `paths`, or `None` if
+/// they never collide. Agents wait at the end of their paths forever.
+///
+/// # Panics
+///
+/// Panics if a path is empty.
+pub fn first_conflict(paths: &[Vec<Position>]) -> Option<Conflict> {
+    let end = paths.iter().map(|path| path.len()).max().unwrap_or(0);
+    for time in 0..end {
+        for a in 0..paths.len() {
+            for b in a + 1..paths.len() {
+                let position = position_at(&paths[a], time);
+                if position == position_at(&paths[b], time) {
+                    return Some(Conflict::Vertex { agents: (a, b),
+                                                   position,
+                                                   time });
+                }
+                if time == 0 {
+                    continue;
+                }
+                let from = position_at(&paths[a], time - 1);
+                if from != position &&
+                        from == position_at(&paths[b], time) &&
+                        position == position_at(&paths[b], time - 1) {
+                    return Some(Conflict::Edge { agents: (a, b),
+                                                 from,
+                                                 to: position,
+                                                 time });
+                }
+            }
+        }
+    }
+    None
+}
+
+/// An algorithm for planning collision-free paths for several agents.
+///
+/// # Examples
+///
+/// ```
+/// use bestpath::{first_conflict, Agent, ConflictBased, CooperativeAStar,
+///                Grid, MovementModel, MultiAgentPlanner, Position};
+///
+/// // Two agents cross in the middle of an open square.
+/// let grid = Grid::filled(3, 3, ' ');
+/// let agents = [Agent{start: Position{x: 0, y: 1},
+///                     goal: Position{x: 2, y: 1}},
+///               Agent{start: Position{x: 1, y: 0},
+///                     goal: Position{x: 1, y: 2}}];
+/// let planners: Vec<Box<dyn MultiAgentPlanner>> =
+///     vec![Box::new(CooperativeAStar), Box::new(ConflictBased::default())];
+/// for planner in planners {
+///     let paths = planner.plan(&grid, &agents, MovementModel::FourWay)
+///         .unwrap();
+///     assert_eq!(None, first_conflict(&paths));
+///     assert_eq!(Some(&Position{x: 2, y: 1}), paths[0].last());
+///     assert_eq!(Some(&Position{x: 1, y: 2}), paths[1].last());
+///     // One of them has to wait or go around.
+///     assert_eq!(7, paths[0].len() + paths[1].len());
+/// }
+/// ```
+pub trait MultiAgentPlanner {
+    /// Plan a timed path for each agent, in the same order as `agents`, such
+    /// that `first_conflict` finds no conflicts. Each path starts at the
+    /// agent's start and ends at its goal. Returns `None` if no plan was
+    /// found.
+    ///
+    /// # Panics
+    ///
+    /// Panics if two agents have the same start or the same goal.
+    fn plan(&self,
+            grid: &Grid,
+            agents: &[Agent],
+            movement: MovementModel) -> Option<Vec<Vec<Position>>>;
+}
+
+/// Plans one agent at a time, in order, treating the cells and moves of the
+/// agents already planned as reserved (Silver's cooperative A*). Fast, but it
+/// can fail to find a plan when one exists, and the plans it finds aren't
+/// always the shortest overall. Putting the agents with the least room to
+/// move first helps.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct CooperativeAStar;
+
+/// Conflict-based search (Sharon et al., 2015): plans each agent on its
+/// own, then repeatedly picks a conflict and tries both ways of resolving it
+/// by forbidding one of the agents from the cell or move involved. Finds the
+/// plan with the smallest total number of steps, but can take exponential
+/// time, so it gives up after trying `max_nodes` sets of constraints.
+#[derive(Copy, Clone, Debug)]
+pub struct ConflictBased {
+    pub max_nodes: usize,
+}
+
+impl Default for ConflictBased {
+    fn default() -> ConflictBased {
+        ConflictBased { max_nodes: 1000 }
+    }
+}
+
+fn check_agents(agents: &[Agent]) {
+    let starts: HashSet<Position> = agents.iter().map(|a| a.start).collect();
+    let goals: HashSet<Position> = agents.iter().map(|a| a.goal).collect();
+    assert!(starts.len() == agents.len(), "two agents share a start");
+    assert!(goals.len() == agents.len(), "two agents share a goal");
+}
+
+/// The number of steps from each cell to `goal`, ignoring other agents.
+fn step_distances(grid: &Grid, goal: Position, movement: MovementModel)
+        -> SearchCells {
+    let mut distances = SearchCells::new(grid, false);
+    if !grid.is_passable(goal) {
+        return distances;
+    }
+    distances.reach(goal, 0, None);
+    let mut queue = VecDeque::new();
+    queue.push_back(goal);
+    while let Some(pos) = queue.pop_front() {
+        let distance = distances.cost(pos).unwrap();
+        // Steps are symmetric, so the cells that can be stepped to from
+        // `pos` are the cells that can step to it.
+        for (next, _) in movement.neighbours(grid, pos) {
+            if distances.cost(next).is_none() {
+                distances.reach(next, distance + 1, Some(pos));
+                queue.push_back(next);
+            }
+        }
+    }
+    distances
+}
+
+/// The cells of a grid at each time up to `horizon`, where every step takes
+/// one unit of time. `blocked(from, to, time)` is true if moving (or waiting
+/// when `from == to`) from `from` to arrive at `to` at `time` isn't allowed.
+struct SpaceTime<'a, B> {
+    grid: &'a Grid<'a>,
+    movement: MovementModel,
+    distances: &'a SearchCells,
+    horizon: usize,
+    blocked: B,
+}
+
+impl<B> Graph for SpaceTime<'_, B>
+        where B: Fn(Position, Position, usize) -> bool {
+    type Node = (Position, usize);
+
+    fn successors(&self, (pos, time): (Position, usize))
+            -> Vec<((Position, usize), usize)> {
+        if time >= self.horizon {
+            return Vec::new();
+        }
+        let moves = self.movement.neighbours(self.grid, pos);
+        Some(pos).into_iter()
+            .chain(moves.into_iter().map(|(p, _)| p))
+            .filter(|&p| self.distances.cost(p).is_some())
+            .filter(|&p| !(self.blocked)(pos, p, time + 1))
+            .map(|p| ((p, time + 1), 1))
+            .collect()
+    }
+}
+
+/// Plan the quickest timed path for `agent` that avoids `blocked` moves and
+/// ends at a time when `can_stop` is true, without going past `horizon`.
+/// `distances` are the agent's `step_distances`.
+fn plan_agent<B, S>(grid: &Grid,
+                    movement: MovementModel,
+                    agent: Agent,
+                    distances: &SearchCells,
+                    horizon: usize,
+                    blocked: B,
+                    can_stop: S) -> Option<Vec<Position>>
+        where B: Fn(Position, Position, usize) -> bool,
+              S: Fn(usize) -> bool {
+    // The goal can't be reached from the start at all.
+    distances.cost(agent.start)?;
+    let graph = SpaceTime { grid, movement, distances, horizon, blocked };
+    best_first(&graph,
+               NodeMap::new(),
+               (agent.start, 0),
+               |(p, time)| p == agent.goal && can_stop(time),
+               |(p, _)| distances.cost(p).unwrap(),
+               TieBreak::default(),
+               &mut ())
+        .path
+        .map(|(path, _)| path.into_iter().map(|(p, _)| p).collect())
+}
+
+impl MultiAgentPlanner for CooperativeAStar {
+    fn plan(&self,
+            grid: &Grid,
+            agents: &[Agent],
+            movement: MovementModel) -> Option<Vec<Vec<Position>>> {
+        check_agents(agents);
+        let cells = grid.width() * grid.height();
+        let mut vertices: HashSet<(Position, usize)> = HashSet::new();
+        let mut edges: HashSet<(Position, Position, usize)> = HashSet::new();
+        // The time from which each finished agent waits at its goal.
+        let mut parked: HashMap<Position, usize> = HashMap::new();
+        let mut last_reserved: HashMap<Position, usize> = HashMap::new();
+        let mut paths: Vec<Vec<Position>> = Vec::new();
+
+        for &agent in agents {
+            let latest = paths.iter().map(|path| path.len()).max()
+                .unwrap_or(0);
+            let blocked = |from: Position, to: Position, time: usize| {
+                vertices.contains(&(to, time)) ||
+                    edges.contains(&(to, from, time)) ||
+                    parked.get(&to).is_some_and(|&t| t <= time)
+            };
+            // Stopping is only safe once no earlier agent needs the goal.
+            let can_stop = |time: usize| {
+                last_reserved.get(&agent.goal).is_none_or(|&t| time > t)
+            };
+            let distances = step_distances(grid, agent.goal, movement);
+            let path = plan_agent(grid, movement, agent, &distances,
+                                  latest + cells, blocked, can_stop)?;
+
+            for (time, &p) in path.iter().enumerate() {
+                vertices.insert((p, time));
+                let last = last_reserved.entry(p).or_insert(time);
+                *last = time.max(*last);
+                if time > 0 {
+                    edges.insert((path[time - 1], p, time));
+                }
+            }
+            parked.insert(agent.goal, path.len() - 1);
+            paths.push(path);
+        }
+        Some(paths)
+    }
+}
+
+/// Forbids an agent from being in a cell, or making a move, at a time.
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+enum Constraint {
+    Vertex(Position, usize),
+    Edge(Position, Position, usize),
+}
+
+impl Constraint {
+    fn time(self) -> usize {
+        match self {
+            Constraint::Vertex(_, time) | Constraint::Edge(_, _, time) => time,
+        }
+    }
+}
+
+/// A node of the constraint tree searched by `ConflictBased`.
+struct ConstraintNode {
+    // Each constraint applies to the agent with the given index.
+    constraints: Vec<(usize, Constraint)>,
+    paths: Vec<Vec<Position>>,
+}
+
+impl ConstraintNode {
+    /// The total number of steps of every path.
+    fn cost(&self) -> usize {
+        self.paths.iter().map(|path| path.len() - 1).sum()
+    }
+}
+
+/// Plan the quickest path for agent `index` that obeys `constraints`, where
+/// `distances` holds each agent's `step_distances`.
+fn plan_constrained(grid: &Grid,
+                    movement: MovementModel,
+                    agents: &[Agent],
+                    distances: &[SearchCells],
+                    index: usize,
+                    constraints: &[(usize, Constraint)])
+        -> Option<Vec<Position>> {
+    let agent = agents[index];
+    let own: Vec<Constraint> = constraints.iter()
+        .filter(|&&(i, _)| i == index)
+        .map(|&(_, c)| c)
+        .collect();
+    let latest = own.iter().map(|c| c.time()).max().unwrap_or(0);
+    let blocked = |from: Position, to: Position, time: usize| {
+        own.contains(&Constraint::Vertex(to, time)) ||
+            own.contains(&Constraint::Edge(from, to, time))
+    };
+    let can_stop = |time: usize| {
+        !own.iter().any(|&c| match c {
+            Constraint::Vertex(p, t) => p == agent.goal && t >= time,
+            Constraint::Edge(..) => false,
+        })
+    };
+    plan_agent(grid, movement, agent, &distances[index],
+               latest + grid.width() * grid.height(), blocked, can_stop)
+}
+
+impl MultiAgentPlanner for ConflictBased {
+    fn plan(&self,
+            grid: &Grid,
+            agents: &[Agent],
+            movement: MovementModel) -> Option<Vec<Vec<Position>>> {
+        check_agents(agents);
+        // The distances don't depend on the constraints, so are only found
+        // once however many times each agent is planned.
+        let distances: Vec<SearchCells> = agents.iter()
+            .map(|agent| step_distances(grid, agent.goal, movement))
+            .collect();
+        let mut paths = Vec::new();
+        for index in 0..agents.len() {
+            paths.push(plan_constrained(grid, movement, agents, &distances,
+                                        index, &[])?);
+        }
+        let root = ConstraintNode { constraints: Vec::new(), paths };
+        // Nodes are explored cheapest first, then in the order they were
+        // made.
+        let mut frontier = BinaryHeap::new();
+        frontier.push(Reverse((root.cost(), 0)));
+        let mut nodes = vec![Some(root)];
+
+        let mut explored = 0;
+        while let Some(Reverse((_, i))) = frontier.pop() {
+            let node = nodes[i].take().unwrap();
+            let (a, constraint_a, b, constraint_b) =
+                match first_conflict(&node.paths) {
+                    None => return Some(node.paths),
+                    Some(Conflict::Vertex { agents: (a, b),
+                                            position,
+                                            time }) =>
+                        (a, Constraint::Vertex(position, time),
+                         b, Constraint::Vertex(position, time)),
+                    Some(Conflict::Edge { agents: (a, b), from, to, time }) =>
+                        (a, Constraint::Edge(from, to, time),
+                         b, Constraint::Edge(to, from, time)),
+                };
+            explored += 1;
+            if explored > self.max_nodes {
+                return None;
+            }
+
+            for &(agent, constraint) in [(a, constraint_a),
+                                         (b, constraint_b)].iter() {
+                let mut constraints = node.constraints.clone();
+                constraints.push((agent, constraint));
+                let path = plan_constrained(grid, movement, agents,
+                                            &distances, agent, &constraints);
+                if let Some(path) = path {
+                    let mut paths = node.paths.clone();
+                    paths[agent] = path;
+                    let child = ConstraintNode { constraints, paths };
+                    frontier.push(Reverse((child.cost(), nodes.len())));
+                    nodes.push(Some(child));
+                }
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+use generate::{MazeGenerator, RandomObstacles};
+#[cfg(test)]
+use mazes::ALL_MODELS;
+#[cfg(test)]
+use {AStar, Pathfinder};
+
+/// Check that each path starts and ends in the right place and only takes
+/// steps allowed by `movement`.
+#[cfg(test)]
+fn assert_valid(grid: &Grid, agents: &[Agent], paths: &[Vec<Position>],
+                movement: MovementModel) {
+    assert_eq!(agents.len(), paths.len());
+    for (agent, path) in agents.iter().zip(paths) {
+        assert_eq!(Some(&agent.start), path.first());
+        assert_eq!(Some(&agent.goal), path.last());
+        for w in path.windows(2) {
+            assert!(w[0] == w[1] ||
+                    movement.neighbours(grid, w[0]).iter()
+                        .any(|&(p, _)| p == w[1]),
+                    "{:?} to {:?}", w[0], w[1]);
+        }
+    }
+    assert_eq!(None, first_conflict(paths));
+}
+
+#[cfg(test)]
+fn planners() -> Vec<Box<dyn MultiAgentPlanner>> {
+    vec![Box::new(CooperativeAStar), Box::new(ConflictBased::default())]
+}
+
+#[test]
+fn finds_vertex_and_edge_conflicts() {
+    let p = |x, y| Position { x, y };
+    assert_eq!(Some(Conflict::Vertex { agents: (0, 1),
+                                       position: p(1, 0),
+                                       time: 1 }),
+               first_conflict(&[vec![p(0, 0), p(1, 0)],
+                                vec![p(2, 0), p(1, 0)]]));
+    assert_eq!(Some(Conflict::Edge { agents: (0, 1),
+                                     from: p(0, 0),
+                                     to: p(1, 0),
+                                     time: 1 }),
+               first_conflict(&[vec![p(0, 0), p(1, 0)],
+                                vec![p(1, 0), p(0, 0)]]));
+    // The first agent waits at its goal for the second to arrive.
+    assert_eq!(Some(Conflict::Vertex { agents: (0, 1),
+                                       position: p(1, 0),
+                                       time: 2 }),
+               first_conflict(&[vec![p(0, 0), p(1, 0)],
+                                vec![p(3, 0), p(2, 0), p(1, 0)]]));
+    assert_eq!(None,
+               first_conflict(&[vec![p(0, 0), p(1, 0)],
+                                vec![p(1, 0), p(2, 0)]]));
+}
+
+#[test]
+fn conflict_based_resolves_swaps() {
+    let grid = Grid::new(&[vec![' ', ' ', ' ', ' ', ' ', ' '],
+                           vec!['█', '█', ' ', '█', '█', '█']]).unwrap();
+    let agents = [Agent { start: Position { x: 0, y: 0 },
+                          goal: Position { x: 5, y: 0 } },
+                  Agent { start: Position { x: 5, y: 0 },
+                          goal: Position { x: 0, y: 0 } }];
+    let movement = MovementModel::FourWay;
+    let independent: Vec<Vec<Position>> = agents.iter()
+        .map(|a| AStar.search(&grid, a.start, a.goal, movement)
+                 .path.unwrap().0)
+        .collect();
+    assert!(first_conflict(&independent).is_some());
+    let paths = ConflictBased::default().plan(&grid, &agents, movement)
+        .unwrap();
+    assert_valid(&grid, &agents, &paths, movement);
+    // The first agent is planned without leaving time for the second to
+    // reach the passing place, so cooperative A* gives up.
+    assert_eq!(None, CooperativeAStar.plan(&grid, &agents, movement));
+}
+
+#[test]
+fn plans_are_valid_on_random_grids() {
+    let p = |x, y| Position { x, y };
+    let agents = [Agent { start: p(0, 0), goal: p(7, 5) },
+                  Agent { start: p(7, 5), goal: p(0, 0) },
+                  Agent { start: p(0, 5), goal: p(7, 0) },
+                  Agent { start: p(3, 2), goal: p(4, 3) }];
+    let mut solved = 0;
+    for seed in 0..6 {
+        let mut grid = RandomObstacles { density: 0.2 }.generate(8, 6, seed);
+        for agent in &agents {
+            grid[agent.start] = ' ';
+            grid[agent.goal] = ' ';
+        }
+        for &movement in ALL_MODELS.iter() {
+            let cooperative = CooperativeAStar.plan(&grid, &agents, movement);
+            let optimal = ConflictBased::default()
+                .plan(&grid, &agents, movement);
+            let steps = |paths: &Vec<Vec<Position>>| -> usize {
+                paths.iter().map(|path| path.len() - 1).sum()
+            };
+            if let Some(ref paths) = cooperative {
+                assert_valid(&grid, &agents, paths, movement);
+            }
+            if let Some(ref paths) = optimal {
+                assert_valid(&grid, &agents, paths, movement);
+                solved += 1;
+            }
+            if let (Some(c), Some(o)) = (cooperative, optimal) {
+                assert!(steps(&o) <= steps(&c));
+            }
+        }
+    }
+    assert!(solved > 12);
+}
+
+#[test]
+fn impossible_swaps_find_no_plan() {
+    // A dead-end corridor that the agents can't pass each other in.
+    let grid = Grid::new(&[vec![' ', ' ', ' ']]).unwrap();
+    let agents = [Agent { start: Position { x: 0, y: 0 },
+                          goal: Position { x: 2, y: 0 } },
+                  Agent { start: Position { x: 2, y: 0 },
+                          goal: Position { x: 0, y: 0 } }];
+    for planner in planners() {
+        assert_eq!(None, planner.plan(&grid, &agents,
+                                      MovementModel::FourWay));
+    }
+}
+
+#[test]
+#[should_panic(expected = "share a goal")]
+fn shared_goals_panic() {
+    let agents = [Agent { start: Position { x: 0, y: 0 },
+                          goal: Position { x: 2, y: 0 } },
+                  Agent { start: Position { x: 1, y: 0 },
+                          goal: Position { x: 2, y: 0 } }];
+    CooperativeAStar.plan(&Grid::filled(3, 1, ' '), &agents,
+                          MovementModel::FourWay);
+}
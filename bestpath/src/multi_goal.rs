@@ -0,0 +1,426 @@
+//! Searches with more than one goal: finding the nearest of several targets
+//! and visiting a set of waypoints in the cheapest order.
+
+use search::{best_first, explore, Exploration, SearchState};
+use search_cells::SearchCells;
+use {Grid, GridGraph, MovementModel, Position, SearchResult, TieBreak};
+
+/// The largest number of waypoints that `visit_waypoints` orders exactly. The
+/// exact solver takes time proportional to 2ⁿn² so larger sets are ordered
+/// with a heuristic instead.
+pub const EXACT_WAYPOINT_LIMIT: usize = 12;
+
+/// A route from a start position through a set of waypoints.
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct Tour {
+    /// The waypoints in the order that they are visited.
+    pub order: Vec<Position>,
+    /// The complete path, starting at the start position and ending at the
+    /// last waypoint.
+    pub path: Vec<Position>,
+    /// The cost of following `path`.
+    pub cost: usize,
+}
+
+/// Find the cheapest path from `start` to whichever of `goals` is cheapest to
+/// reach. The path ends at the goal that was chosen.
+///
+/// This is A* with the heuristic being the smallest of the movement model's
+/// heuristics to each goal, which is admissible because each of them is.
+///
+/// # Examples
+///
+/// ```
+/// use bestpath::{find_nearest, Grid, MovementModel, Position};
+///
+/// let grid = Grid::new(&[vec![' ', ' ', ' ', ' ', ' '],
+///                        vec![' ', '█', '█', '█', ' ']]).unwrap();
+/// let goals = [Position{x: 1, y: 1}, Position{x: 4, y: 1}];
+/// let result = find_nearest(&grid, Position{x: 0, y: 1}, &goals,
+///                           MovementModel::FourWay);
+/// let (path, cost) = result.path.unwrap();
+/// assert_eq!(Some(&goals[1]), path.last());
+/// assert_eq!(6, cost);
+/// ```
+pub fn find_nearest(grid: &Grid,
+                    start: Position,
+                    goals: &[Position],
+                    movement: MovementModel) -> SearchResult {
+    best_first(&GridGraph::new(grid, movement),
+               SearchCells::new(grid, false),
+               start,
+               |p| goals.contains(&p),
+               |p| {
+                   goals.iter()
+                        .map(|&goal| movement.heuristic(p, goal))
+                        .min()
+                        .unwrap_or(0)
+               },
+               TieBreak::default(),
+               &mut ())
+}
+
+/// The cheapest paths between every pair of `points`, found with one
+/// exhaustive Dijkstra search from each point.
+struct Legs {
+    searches: Vec<Exploration>,
+    points: Vec<Position>,
+}
+
+impl Legs {
+    fn new(grid: &Grid, points: Vec<Position>, movement: MovementModel)
+            -> Legs {
+        let searches = points.iter()
+            .map(|&p| {
+                explore(&GridGraph::new(grid, movement),
+                        SearchCells::new(grid, false), p, |_| false, |_| 0,
+                        TieBreak::default(), &mut ())
+            })
+            .collect();
+        Legs { searches, points }
+    }
+
+    /// The cost of the cheapest path from point `from` to point `to`.
+    fn cost(&self, from: usize, to: usize) -> Option<usize> {
+        self.searches[from].state.cost(self.points[to])
+    }
+
+    /// The cheapest path from point `from` to point `to`, which must be
+    /// reachable.
+    fn path(&self, from: usize, to: usize) -> Vec<Position> {
+        self.searches[from].state.path_to(self.points[to])
+    }
+
+    /// The cost of visiting the points in `order` (indices into `points`)
+    /// starting from point 0. Every leg must be reachable.
+    fn order_cost(&self, order: &[usize]) -> usize {
+        let mut previous = 0;
+        let mut cost = 0;
+        for &next in order {
+            cost += self.cost(previous, next).unwrap();
+            previous = next;
+        }
+        cost
+    }
+}
+
+/// The cheapest order to visit points 1..n of `legs` starting from point 0,
+/// found with the Held-Karp dynamic programming algorithm.
+fn exact_order(legs: &Legs, n: usize) -> Vec<usize> {
+    const UNVISITED: usize = usize::MAX;
+    let full = (1 << n) - 1;
+    // best[set][last] is the cheapest way to visit the waypoints in `set`,
+    // ending at `last`. Waypoint `i` is point `i + 1` in `legs`.
+    let mut best = vec![vec![UNVISITED; n]; 1 << n];
+    let mut previous = vec![vec![0; n]; 1 << n];
+    for last in 0..n {
+        best[1 << last][last] = legs.cost(0, last + 1).unwrap();
+    }
+    for set in 1..=full {
+        for last in 0..n {
+            let cost = best[set][last];
+            if cost == UNVISITED {
+                continue;
+            }
+            for next in 0..n {
+                if set & (1 << next) != 0 {
+                    continue;
+                }
+                let next_set = set | (1 << next);
+                let next_cost =
+                    cost + legs.cost(last + 1, next + 1).unwrap();
+                if next_cost < best[next_set][next] {
+                    best[next_set][next] = next_cost;
+                    previous[next_set][next] = last;
+                }
+            }
+        }
+    }
+
+    let mut last = (0..n).min_by_key(|&last| best[full][last]).unwrap();
+    let mut set = full;
+    let mut order = vec![last + 1];
+    while set != 1 << last {
+        let before = previous[set][last];
+        set &= !(1 << last);
+        last = before;
+        order.push(last + 1);
+    }
+    order.reverse();
+    order
+}
+
+/// A good order to visit points 1..n of `legs` starting from point 0: always
+/// go to the nearest unvisited point and then reverse sections of the order
+/// (2-opt) while that makes it cheaper.
+fn heuristic_order(legs: &Legs, n: usize) -> Vec<usize> {
+    let mut order = Vec::with_capacity(n);
+    let mut unvisited: Vec<usize> = (1..=n).collect();
+    let mut current = 0;
+    while !unvisited.is_empty() {
+        let (i, _) = unvisited.iter()
+            .enumerate()
+            .min_by_key(|&(_, &p)| legs.cost(current, p).unwrap())
+            .unwrap();
+        current = unvisited.swap_remove(i);
+        order.push(current);
+    }
+
+    let mut cost = legs.order_cost(&order);
+    let mut improved = true;
+    while improved {
+        improved = false;
+        for i in 0..n {
+            for j in i + 1..n {
+                order[i..=j].reverse();
+                let new_cost = legs.order_cost(&order);
+                if new_cost < cost {
+                    cost = new_cost;
+                    improved = true;
+                } else {
+                    order[i..=j].reverse();
+                }
+            }
+        }
+    }
+    order
+}
+
+/// Find a cheap path from `start` that visits every one of `waypoints`, in
+/// whatever order is cheapest. Returns `None` if any waypoint can't be
+/// reached.
+///
+/// Up to `EXACT_WAYPOINT_LIMIT` waypoints the order is the cheapest possible.
+/// Beyond that it is found with a heuristic and may not be.
+///
+/// # Examples
+///
+/// ```
+/// use bestpath::{visit_waypoints, Grid, MovementModel, Position};
+///
+/// let grid = Grid::filled(5, 1, ' ');
+/// let waypoints = [Position{x: 4, y: 0}, Position{x: 1, y: 0}];
+/// let tour = visit_waypoints(&grid, Position{x: 2, y: 0}, &waypoints,
+///                            MovementModel::FourWay).unwrap();
+/// assert_eq!(vec![Position{x: 1, y: 0}, Position{x: 4, y: 0}], tour.order);
+/// assert_eq!(4, tour.cost);
+/// ```
+pub fn visit_waypoints(grid: &Grid,
+                       start: Position,
+                       waypoints: &[Position],
+                       movement: MovementModel) -> Option<Tour> {
+    let n = waypoints.len();
+    let mut points = vec![start];
+    points.extend_from_slice(waypoints);
+    let legs = Legs::new(grid, points, movement);
+    // The start can be left even if it is impassable, so reaching every
+    // waypoint from it doesn't mean that they can reach each other. The
+    // orderings below rely on every leg having a path.
+    if (0..=n).any(|i| (1..=n).any(|j| legs.cost(i, j).is_none())) {
+        return None;
+    }
+
+    let order = match n {
+        0 => Vec::new(),
+        _ if n <= EXACT_WAYPOINT_LIMIT => exact_order(&legs, n),
+        _ => heuristic_order(&legs, n),
+    };
+
+    let mut path = vec![start];
+    let mut previous = 0;
+    for &next in &order {
+        path.extend(legs.path(previous, next).into_iter().skip(1));
+        previous = next;
+    }
+    Some(Tour {
+        order: order.iter().map(|&i| legs.points[i]).collect(),
+        path,
+        cost: legs.order_cost(&order),
+    })
+}
+
+#[cfg(test)]
+use mazes;
+#[cfg(test)]
+use mazes::random_grid;
+#[cfg(test)]
+use search::path_cost;
+#[cfg(test)]
+use {Dijkstra, Pathfinder};
+
+/// Every ordering of `items`.
+#[cfg(test)]
+fn permutations(items: &[usize]) -> Vec<Vec<usize>> {
+    if items.is_empty() {
+        return vec![Vec::new()];
+    }
+    let mut result = Vec::new();
+    for (i, &first) in items.iter().enumerate() {
+        let mut rest = items.to_vec();
+        rest.remove(i);
+        for mut tail in permutations(&rest) {
+            tail.insert(0, first);
+            result.push(tail);
+        }
+    }
+    result
+}
+
+/// Check that `tour` is a legal path through every waypoint in the order that
+/// it claims.
+#[cfg(test)]
+fn check_tour(grid: &Grid,
+              start: Position,
+              waypoints: &[Position],
+              movement: MovementModel,
+              tour: &Tour) {
+    assert_eq!(Some(&start), tour.path.first());
+    assert_eq!(tour.cost, path_cost(grid, &tour.path, movement));
+    let mut sorted_order = tour.order.clone();
+    let mut sorted_waypoints = waypoints.to_vec();
+    sorted_order.sort_by_key(|p| (p.y, p.x));
+    sorted_waypoints.sort_by_key(|p| (p.y, p.x));
+    assert_eq!(sorted_waypoints, sorted_order);
+
+    let mut rest = &tour.path[..];
+    for waypoint in &tour.order {
+        let i = rest.iter().position(|p| p == waypoint)
+            .expect("tour skips a waypoint");
+        rest = &rest[i..];
+    }
+    assert_eq!(tour.order.last(), tour.path.last());
+}
+
+#[cfg(test)]
+const MOVEMENTS: [MovementModel; 2] = [MovementModel::FourWay,
+                                       MovementModel::EightWay];
+
+#[test]
+fn nearest_matches_cheapest_single_goal_search() {
+    for seed in 0..30 {
+        let grid = random_grid(seed, 12, 9, 4);
+        let start = Position { x: 0, y: 0 };
+        let goals = [Position { x: 11, y: 0 },
+                     Position { x: 6, y: 8 },
+                     Position { x: 10, y: 7 }];
+        for &movement in MOVEMENTS.iter() {
+            let expected = goals.iter()
+                .filter_map(|&goal| {
+                    Dijkstra.search(&grid, start, goal, movement).path
+                })
+                .map(|(_, cost)| cost)
+                .min();
+            let result = find_nearest(&grid, start, &goals, movement);
+            assert_eq!(expected, result.path.as_ref().map(|p| p.1),
+                       "seed {}", seed);
+            if let Some((path, cost)) = result.path {
+                assert!(goals.contains(path.last().unwrap()));
+                assert_eq!(cost, path_cost(&grid, &path, movement));
+            }
+        }
+    }
+}
+
+#[test]
+fn nearest_with_no_goals_finds_nothing() {
+    let grid = Grid::filled(3, 3, ' ');
+    let result = find_nearest(&grid, Position { x: 0, y: 0 }, &[],
+                              MovementModel::FourWay);
+    assert_eq!(None, result.path);
+}
+
+#[test]
+fn nearest_includes_start() {
+    let grid = Grid::filled(3, 3, ' ');
+    let start = Position { x: 1, y: 1 };
+    let result = find_nearest(&grid, start, &[Position { x: 0, y: 0 }, start],
+                              MovementModel::FourWay);
+    assert_eq!(Some((vec![start], 0)), result.path);
+}
+
+#[test]
+fn exact_order_is_cheapest_permutation() {
+    let waypoints = [Position { x: 11, y: 8 },
+                     Position { x: 3, y: 7 },
+                     Position { x: 9, y: 1 },
+                     Position { x: 5, y: 4 },
+                     Position { x: 1, y: 2 }];
+    let start = Position { x: 0, y: 0 };
+    for seed in 0..10 {
+        let mut grid = random_grid(seed, 12, 9, 5);
+        for &p in waypoints.iter().chain(Some(&start)) {
+            grid[p] = ' ';
+        }
+        for &movement in MOVEMENTS.iter() {
+            let mut points = vec![start];
+            points.extend_from_slice(&waypoints);
+            let legs = Legs::new(&grid, points, movement);
+            if (1..=5).any(|i| legs.cost(0, i).is_none()) {
+                assert_eq!(None, visit_waypoints(&grid, start, &waypoints,
+                                                 movement));
+                continue;
+            }
+            let cheapest = permutations(&[1, 2, 3, 4, 5])
+                .iter()
+                .map(|order| legs.order_cost(order))
+                .min();
+            let tour = visit_waypoints(&grid, start, &waypoints, movement)
+                .unwrap();
+            assert_eq!(cheapest, Some(tour.cost), "seed {}", seed);
+            check_tour(&grid, start, &waypoints, movement, &tour);
+        }
+    }
+}
+
+#[test]
+fn heuristic_order_visits_every_waypoint() {
+    let grid = random_grid(7, 20, 20, 6).with_passable(|ch| ch != '█');
+    let start = Position { x: 0, y: 0 };
+    let waypoints: Vec<Position> = grid.positions()
+        .filter(|&p| grid.is_passable(p) && (p.x * 7 + p.y * 3) % 23 == 0)
+        .take(EXACT_WAYPOINT_LIMIT + 4)
+        .collect();
+    assert!(waypoints.len() > EXACT_WAYPOINT_LIMIT);
+    let movement = MovementModel::EightWayNoCornerCutting;
+    let tour = visit_waypoints(&grid, start, &waypoints, movement).unwrap();
+    check_tour(&grid, start, &waypoints, movement, &tour);
+}
+
+#[test]
+fn heuristic_order_is_exact_on_a_line() {
+    let grid = Grid::filled(10, 1, ' ');
+    let points = (0..10).map(|x| Position { x, y: 0 }).collect();
+    let legs = Legs::new(&grid, points, MovementModel::FourWay);
+    let order = heuristic_order(&legs, 9);
+    assert_eq!((1..10).collect::<Vec<_>>(), order);
+    assert_eq!(exact_order(&legs, 9), order);
+}
+
+#[test]
+fn no_waypoints_stays_at_start() {
+    let grid = Grid::filled(2, 2, ' ');
+    let start = Position { x: 1, y: 0 };
+    assert_eq!(Some(Tour { order: vec![], path: vec![start], cost: 0 }),
+               visit_waypoints(&grid, start, &[], MovementModel::FourWay));
+}
+
+#[test]
+fn unreachable_waypoint_has_no_tour() {
+    let maze = mazes::no_path();
+    let grid = maze.grid();
+    assert_eq!(None, visit_waypoints(&grid, maze.start,
+                                     &[Position { x: 0, y: 3 }, maze.goal],
+                                     MovementModel::FourWay));
+}
+
+#[test]
+fn waypoints_separated_by_the_start_have_no_tour() {
+    let grid = Grid::new(&[vec![' ', '█', ' ']]).unwrap();
+    let waypoints = [Position { x: 0, y: 0 }, Position { x: 2, y: 0 }];
+    for &movement in MOVEMENTS.iter() {
+        assert_eq!(None, visit_waypoints(&grid, Position { x: 1, y: 0 },
+                                         &waypoints, movement));
+    }
+    assert!(visit_waypoints(&grid, Position { x: 1, y: 0 }, &waypoints[..1],
+                            MovementModel::FourWay).is_some());
+}
@@ -0,0 +1,477 @@
+//! Turning the cell-by-cell paths found by a search into shorter forms:
+//! the corners of the path, straight lines between cells that can see each
+//! other, or a list of moves. Each can be expanded back into cells.
+
+use std::error::Error;
+use std::fmt;
+
+use {Grid, Position};
+
+/// The offset of the single step from `p1` to `p2`.
+///
+/// # Panics
+///
+/// Panics if `p2` isn't one of the eight cells around `p1`.
+fn step(p1: Position, p2: Position) -> (isize, isize) {
+    let dx = p2.x as isize - p1.x as isize;
+    let dy = p2.y as isize - p1.y as isize;
+    assert!(dx.abs() <= 1 && dy.abs() <= 1 && (dx, dy) != (0, 0),
+            "({}, {}) to ({}, {}) is not a single step",
+            p1.x, p1.y, p2.x, p2.y);
+    (dx, dy)
+}
+
+/// Compress `path` to its first and last positions and every position where
+/// it changes direction.
+///
+/// The result can be expanded back into `path` with `expand_waypoints`. On
+/// hex grids, straight lines other than along a row zig-zag in offset
+/// coordinates, so they keep a waypoint at every cell.
+///
+/// # Panics
+///
+/// Panics if two consecutive positions of `path` aren't next to each other.
+///
+/// # Examples
+///
+/// ```
+/// use bestpath::{waypoints, Position};
+///
+/// let path: Vec<Position> = [(0, 0), (1, 0), (2, 0), (2, 1), (3, 2)]
+///     .iter()
+///     .map(|&(x, y)| Position{x, y})
+///     .collect();
+/// assert_eq!(vec![Position{x: 0, y: 0}, Position{x: 2, y: 0},
+///                 Position{x: 2, y: 1}, Position{x: 3, y: 2}],
+///            waypoints(&path));
+/// ```
+pub fn waypoints(path: &[Position]) -> Vec<Position> {
+    let steps: Vec<(isize, isize)> = path.windows(2)
+        .map(|w| step(w[0], w[1]))
+        .collect();
+    let mut result: Vec<Position> = path.iter().take(1).cloned().collect();
+    for (i, w) in steps.windows(2).enumerate() {
+        if w[0] != w[1] {
+            result.push(path[i + 1]);
+        }
+    }
+    if path.len() > 1 {
+        result.push(path[path.len() - 1]);
+    }
+    result
+}
+
+/// Expand a list of waypoints back into every cell between them, the
+/// reverse of `waypoints`.
+///
+/// Returns `None` if two consecutive waypoints aren't on the same row,
+/// column or 45 degree diagonal.
+///
+/// # Examples
+///
+/// ```
+/// use bestpath::{expand_waypoints, Position};
+///
+/// let path = expand_waypoints(&[Position{x: 0, y: 0},
+///                               Position{x: 2, y: 2},
+///                               Position{x: 2, y: 3}]).unwrap();
+/// assert_eq!(vec![Position{x: 0, y: 0}, Position{x: 1, y: 1},
+///                 Position{x: 2, y: 2}, Position{x: 2, y: 3}],
+///            path);
+/// assert_eq!(None, expand_waypoints(&[Position{x: 0, y: 0},
+///                                     Position{x: 2, y: 1}]));
+/// ```
+pub fn expand_waypoints(waypoints: &[Position]) -> Option<Vec<Position>> {
+    let mut path: Vec<Position> = waypoints.iter().take(1).cloned().collect();
+    for w in waypoints.windows(2) {
+        let dx = w[1].x as isize - w[0].x as isize;
+        let dy = w[1].y as isize - w[0].y as isize;
+        if dx != 0 && dy != 0 && dx.abs() != dy.abs() {
+            return None;
+        }
+        let (sx, sy) = (dx.signum(), dy.signum());
+        for i in 1..=dx.abs().max(dy.abs()) {
+            path.push(Position { x: (w[0].x as isize + sx * i) as usize,
+                                 y: (w[0].y as isize + sy * i) as usize });
+        }
+    }
+    Some(path)
+}
+
+/// Walk the straight line between the centres of `a` and `b`, calling
+/// `visit` with each cell that it passes through, in order. Where the line
+/// passes exactly through the corner of a cell it takes a diagonal step, and
+/// the two cells beside that corner are passed to `corner`.
+fn walk_line<V, C>(a: Position, b: Position, mut visit: V, mut corner: C)
+        where V: FnMut(Position),
+              C: FnMut(Position, Position) {
+    let dx = (b.x as isize - a.x as isize).abs();
+    let dy = (b.y as isize - a.y as isize).abs();
+    let sx = if b.x >= a.x { 1 } else { -1 };
+    let sy = if b.y >= a.y { 1 } else { -1 };
+    let (mut x, mut y) = (a.x as isize, a.y as isize);
+    let at = |x: isize, y: isize| Position { x: x as usize, y: y as usize };
+    let (mut ix, mut iy) = (0, 0);
+    visit(a);
+    while ix < dx || iy < dy {
+        // Compare how far along the line it crosses into the next column
+        // with how far along it crosses into the next row.
+        match ((1 + 2 * ix) * dy).cmp(&((1 + 2 * iy) * dx)) {
+            ::std::cmp::Ordering::Less => {
+                x += sx;
+                ix += 1;
+            }
+            ::std::cmp::Ordering::Greater => {
+                y += sy;
+                iy += 1;
+            }
+            ::std::cmp::Ordering::Equal => {
+                corner(at(x + sx, y), at(x, y + sy));
+                x += sx;
+                y += sy;
+                ix += 1;
+                iy += 1;
+            }
+        }
+        visit(at(x, y));
+    }
+}
+
+/// Returns true if the straight line between the centres of `a` and `b` only
+/// passes through passable cells of `grid`. A line through the corner where
+/// four cells meet needs both of the cells beside it to be passable.
+///
+/// Cells are treated as squares, so this isn't meaningful for hex grids.
+pub fn line_of_sight(grid: &Grid, a: Position, b: Position) -> bool {
+    if !grid.contains(a) || !grid.contains(b) {
+        return false;
+    }
+    let (mut cells_clear, mut corners_clear) = (true, true);
+    walk_line(a, b,
+              |p| cells_clear &= grid.is_passable(p),
+              |p1, p2| corners_clear &= grid.is_passable(p1) &&
+                                        grid.is_passable(p2));
+    cells_clear && corners_clear
+}
+
+/// Shorten `path` for any-angle movement by pulling it tight: each waypoint
+/// is joined to the furthest later cell of the path that is in
+/// `line_of_sight` of it.
+///
+/// The result can be expanded back into cells with `expand_lines`. Steps of
+/// `path` that aren't in line of sight, such as ones that cut the corner of a
+/// wall, are kept as they are.
+///
+/// # Panics
+///
+/// Panics if a position of `path` is outside of `grid`.
+///
+/// # Examples
+///
+/// ```
+/// use bestpath::{find_path, string_pull, Grid, Position};
+///
+/// let rows = vec![vec![' ', ' ', ' ', ' ', ' '],
+///                 vec![' ', ' ', ' ', ' ', ' '],
+///                 vec![' ', ' ', ' ', ' ', ' ']];
+/// let path = find_path(&rows, Position{x: 0, y: 0},
+///                      Position{x: 4, y: 2}).unwrap();
+/// assert_eq!(vec![Position{x: 0, y: 0}, Position{x: 4, y: 2}],
+///            string_pull(&Grid::new(&rows).unwrap(), &path));
+/// ```
+pub fn string_pull(grid: &Grid, path: &[Position]) -> Vec<Position> {
+    let mut result: Vec<Position> = path.iter().take(1).cloned().collect();
+    for i in 1..path.len() {
+        let anchor = result[result.len() - 1];
+        if line_of_sight(grid, anchor, path[i]) {
+            continue;
+        }
+        if path[i - 1] != anchor {
+            result.push(path[i - 1]);
+        }
+        if !line_of_sight(grid, path[i - 1], path[i]) {
+            // The path cuts a corner here, so keep the step.
+            result.push(path[i]);
+        }
+    }
+    if path.len() > 1 && result[result.len() - 1] != path[path.len() - 1] {
+        result.push(path[path.len() - 1]);
+    }
+    result
+}
+
+/// Expand a list of waypoints into every cell that the straight lines
+/// between them pass through. Consecutive cells of the result are next to
+/// each other, diagonally where a line passes through a corner.
+///
+/// String pulling is lossy, so expanding the waypoints from `string_pull`
+/// doesn't give back the original path. It gives another path through the
+/// same waypoints, which is passable wherever the lines between them are in
+/// `line_of_sight`.
+///
+/// # Examples
+///
+/// ```
+/// use bestpath::{expand_lines, Position};
+///
+/// let path = expand_lines(&[Position{x: 0, y: 0}, Position{x: 4, y: 2}]);
+/// assert_eq!(vec![Position{x: 0, y: 0}, Position{x: 1, y: 0},
+///                 Position{x: 1, y: 1}, Position{x: 2, y: 1},
+///                 Position{x: 3, y: 1}, Position{x: 3, y: 2},
+///                 Position{x: 4, y: 2}],
+///            path);
+/// ```
+pub fn expand_lines(waypoints: &[Position]) -> Vec<Position> {
+    let mut path: Vec<Position> = waypoints.iter().take(1).cloned().collect();
+    for w in waypoints.windows(2) {
+        walk_line(w[0], w[1],
+                  |p| if p != w[0] { path.push(p) },
+                  |_, _| {});
+    }
+    path
+}
+
+/// The name of each direction in a command list, by (x, y) offset.
+const DIRECTIONS: [((isize, isize), &str); 8] = [((0, -1), "U"),
+                                                 ((0, 1), "D"),
+                                                 ((-1, 0), "L"),
+                                                 ((1, 0), "R"),
+                                                 ((-1, -1), "UL"),
+                                                 ((1, -1), "UR"),
+                                                 ((-1, 1), "DL"),
+                                                 ((1, 1), "DR")];
+
+/// Describe `path` as a list of moves separated by spaces, such as
+/// "R5 D3 L2". Each move is a direction (U, D, L, R, UL, UR, DL or DR) and the
+/// number of steps to take in it.
+///
+/// The moves can be turned back into `path` with `from_commands`.
+///
+/// # Panics
+///
+/// Panics if two consecutive positions of `path` aren't next to each other.
+///
+/// # Examples
+///
+/// ```
+/// use bestpath::{find_path, to_commands, Position};
+///
+/// let rows = vec![vec![' ', ' ', ' '],
+///                 vec!['█', '█', ' '],
+///                 vec![' ', ' ', ' ']];
+/// let path = find_path(&rows, Position{x: 0, y: 0},
+///                      Position{x: 0, y: 2}).unwrap();
+/// assert_eq!("R2 D2 L2", to_commands(&path));
+/// ```
+pub fn to_commands(path: &[Position]) -> String {
+    let waypoints = waypoints(path);
+    let commands: Vec<String> = waypoints.windows(2)
+        .map(|w| {
+            let dx = w[1].x as isize - w[0].x as isize;
+            let dy = w[1].y as isize - w[0].y as isize;
+            let direction = (dx.signum(), dy.signum());
+            let &(_, name) = DIRECTIONS.iter()
+                .find(|&&(d, _)| d == direction)
+                .unwrap();
+            format!("{}{}", name, dx.abs().max(dy.abs()))
+        })
+        .collect();
+    commands.join(" ")
+}
+
+/// Returned when a list of moves can't be parsed by `from_commands`. Each
+/// variant holds the index of the move that is wrong.
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum ParseCommandsError {
+    /// The move doesn't start with a known direction.
+    UnknownDirection(usize),
+    /// The move's number of steps is missing, zero or not a number.
+    InvalidCount(usize),
+    /// The move would go above the top row or left of the first column.
+    OffGrid(usize),
+}
+
+impl fmt::Display for ParseCommandsError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ParseCommandsError::UnknownDirection(i) =>
+                write!(f, "move {} has an unknown direction", i + 1),
+            ParseCommandsError::InvalidCount(i) =>
+                write!(f, "move {} has an invalid number of steps", i + 1),
+            ParseCommandsError::OffGrid(i) =>
+                write!(f, "move {} goes off the edge of the grid", i + 1),
+        }
+    }
+}
+
+impl Error for ParseCommandsError {}
+
+/// Follow a list of moves in the format written by `to_commands`, starting
+/// at `start`, and return every cell visited.
+///
+/// # Examples
+///
+/// ```
+/// use bestpath::{from_commands, ParseCommandsError, Position};
+///
+/// let path = from_commands(Position{x: 1, y: 1}, "R2 DL1").unwrap();
+/// assert_eq!(vec![Position{x: 1, y: 1}, Position{x: 2, y: 1},
+///                 Position{x: 3, y: 1}, Position{x: 2, y: 2}],
+///            path);
+/// assert_eq!(Err(ParseCommandsError::OffGrid(1)),
+///            from_commands(Position{x: 1, y: 1}, "U1 U1"));
+/// ```
+pub fn from_commands(start: Position, commands: &str)
+        -> Result<Vec<Position>, ParseCommandsError> {
+    let mut path = vec![start];
+    for (i, command) in commands.split_whitespace().enumerate() {
+        let split = command.find(|c: char| c.is_ascii_digit())
+            .unwrap_or(command.len());
+        let (name, count) = command.split_at(split);
+        let &((dx, dy), _) = DIRECTIONS.iter()
+            .find(|&&(_, n)| n == name)
+            .ok_or(ParseCommandsError::UnknownDirection(i))?;
+        let count = match count.parse::<usize>() {
+            Ok(n) if n > 0 => n,
+            _ => return Err(ParseCommandsError::InvalidCount(i)),
+        };
+        for _ in 0..count {
+            let p = path[path.len() - 1];
+            let x = p.x.checked_add_signed(dx);
+            let y = p.y.checked_add_signed(dy);
+            match (x, y) {
+                (Some(x), Some(y)) => path.push(Position { x, y }),
+                _ => return Err(ParseCommandsError::OffGrid(i)),
+            }
+        }
+    }
+    Ok(path)
+}
+
+#[cfg(test)]
+use mazes::{random_grid, ALL_MODELS};
+#[cfg(test)]
+use {mazes, AStar, MovementModel, Pathfinder};
+
+/// Paths through a few random grids with every movement model.
+#[cfg(test)]
+fn sample_paths() -> Vec<(Grid<'static>, Vec<Position>)> {
+    let mut paths = Vec::new();
+    for seed in 0..6 {
+        let (start, goal) = (Position { x: 0, y: 0 }, Position { x: 11, y: 8 });
+        let mut grid = random_grid(seed, 12, 9, 4);
+        grid[start] = ' ';
+        grid[goal] = ' ';
+        for &movement in ALL_MODELS.iter() {
+            let result = AStar.search(&grid, start, goal, movement);
+            if let Some((path, _)) = result.path {
+                paths.push((grid.clone(), path));
+            }
+        }
+    }
+    assert!(paths.len() > 10);
+    paths
+}
+
+#[test]
+fn waypoints_round_trip() {
+    for (_, path) in sample_paths() {
+        let corners = waypoints(&path);
+        assert!(corners.len() <= path.len());
+        assert_eq!(Some(path), expand_waypoints(&corners));
+    }
+    assert_eq!(Vec::<Position>::new(), waypoints(&[]));
+    let single = [Position { x: 3, y: 4 }];
+    assert_eq!(single.to_vec(), waypoints(&single));
+    assert_eq!(Some(single.to_vec()), expand_waypoints(&single));
+}
+
+#[test]
+fn waypoints_keep_only_corners() {
+    let maze = mazes::single_walled_path();
+    let (path, _) = AStar.search(&maze.grid(), maze.start, maze.goal,
+                                 MovementModel::FourWay).path.unwrap();
+    let corners = waypoints(&path);
+    for w in corners.windows(3) {
+        let (d1, d2) = ((w[1].x as isize - w[0].x as isize).signum(),
+                        (w[1].y as isize - w[0].y as isize).signum());
+        let (d3, d4) = ((w[2].x as isize - w[1].x as isize).signum(),
+                        (w[2].y as isize - w[1].y as isize).signum());
+        assert_ne!((d1, d2), (d3, d4));
+    }
+}
+
+#[test]
+#[should_panic(expected = "not a single step")]
+fn waypoints_rejects_gaps() {
+    waypoints(&[Position { x: 0, y: 0 }, Position { x: 2, y: 0 }]);
+}
+
+#[test]
+fn string_pulled_paths_expand_to_valid_paths() {
+    for (grid, path) in sample_paths() {
+        let pulled = string_pull(&grid, &path);
+        assert!(pulled.len() <= path.len());
+        for w in pulled.windows(2) {
+            let cuts_corner = w[0].x.abs_diff(w[1].x) == 1 &&
+                              w[0].y.abs_diff(w[1].y) == 1;
+            assert!(line_of_sight(&grid, w[0], w[1]) || cuts_corner);
+        }
+        let expanded = expand_lines(&pulled);
+        assert_eq!(path.first(), expanded.first());
+        assert_eq!(path.last(), expanded.last());
+        for w in expanded.windows(2) {
+            step(w[0], w[1]);
+        }
+        assert!(expanded.iter().all(|&p| grid.is_passable(p)));
+        let mut rest = expanded.iter();
+        assert!(pulled.iter().all(|w| rest.any(|p| p == w)));
+    }
+}
+
+#[test]
+fn line_of_sight_through_corners_needs_both_sides() {
+    let grid = Grid::new(&[vec![' ', '█', ' '],
+                           vec![' ', ' ', ' '],
+                           vec![' ', ' ', ' ']]).unwrap();
+    assert!(!line_of_sight(&grid, Position { x: 0, y: 0 },
+                           Position { x: 2, y: 0 }));
+    assert!(!line_of_sight(&grid, Position { x: 0, y: 1 },
+                           Position { x: 1, y: 0 }));
+    assert!(!line_of_sight(&grid, Position { x: 0, y: 2 },
+                           Position { x: 2, y: 0 }));
+    assert!(line_of_sight(&grid, Position { x: 0, y: 1 },
+                          Position { x: 2, y: 2 }));
+    assert!(line_of_sight(&grid, Position { x: 0, y: 0 },
+                          Position { x: 1, y: 2 }));
+    assert!(!line_of_sight(&grid, Position { x: 0, y: 0 },
+                           Position { x: 3, y: 0 }));
+}
+
+#[test]
+fn commands_round_trip() {
+    for (_, path) in sample_paths() {
+        let commands = to_commands(&path);
+        assert_eq!(Ok(path.clone()), from_commands(path[0], &commands));
+    }
+    assert_eq!("", to_commands(&[Position { x: 1, y: 1 }]));
+    assert_eq!(Ok(vec![Position { x: 1, y: 1 }]),
+               from_commands(Position { x: 1, y: 1 }, " "));
+}
+
+#[test]
+fn reports_bad_commands() {
+    let start = Position { x: 0, y: 0 };
+    assert_eq!(Err(ParseCommandsError::UnknownDirection(1)),
+               from_commands(start, "R1 X2"));
+    assert_eq!(Err(ParseCommandsError::InvalidCount(0)),
+               from_commands(start, "R"));
+    assert_eq!(Err(ParseCommandsError::InvalidCount(2)),
+               from_commands(start, "R1 D1 L0"));
+    assert_eq!(Err(ParseCommandsError::InvalidCount(0)),
+               from_commands(start, "R2x"));
+    assert_eq!(Err(ParseCommandsError::OffGrid(0)),
+               from_commands(start, "UR1"));
+    assert_eq!("move 2 has an unknown direction",
+               ParseCommandsError::UnknownDirection(1).to_string());
+}
@@ -0,0 +1,350 @@
+//! Drawing grids along with a path and the cells a search explored, as
+//! coloured terminal text, SVG or PPM images.
+
+use std::collections::HashSet;
+use std::fmt::Write;
+
+use {Grid, MovementModel, Position};
+
+/// A colour as red, green and blue components.
+type Rgb = (u8, u8, u8);
+
+const WALL_COLOUR: Rgb = (48, 48, 48);
+const OPEN_COLOUR: Rgb = (255, 255, 255);
+/// The colour of the most expensive terrain in the grid. Cheaper terrain is
+/// shaded between this and `OPEN_COLOUR`.
+const COSTLY_COLOUR: Rgb = (190, 150, 90);
+const EXPLORED_COLOUR: Rgb = (150, 190, 240);
+const PATH_COLOUR: Rgb = (240, 140, 20);
+const START_COLOUR: Rgb = (40, 170, 70);
+const GOAL_COLOUR: Rgb = (210, 40, 40);
+/// The colour of the cells at either end of a jump in the path.
+const JUMP_COLOUR: Rgb = (150, 60, 200);
+
+/// Mix `t` of `b` into `a`.
+fn mix(a: Rgb, b: Rgb, t: f64) -> Rgb {
+    let channel = |a: u8, b: u8| {
+        (a as f64 + (b as f64 - a as f64) * t).round() as u8
+    };
+    (channel(a.0, b.0), channel(a.1, b.1), channel(a.2, b.2))
+}
+
+/// A picture of a grid, with walls, terrain costs, the cells a search
+/// explored and a path each drawn in their own colour.
+///
+/// # Examples
+///
+/// ```
+/// use bestpath::{AStar, Grid, MovementModel, Pathfinder, Picture, Position,
+///                Trace};
+///
+/// let grid = Grid::new(&[vec![' ', ' ', ' '],
+///                        vec![' ', '█', ' ']]).unwrap();
+/// let mut trace = Trace::default();
+/// let (path, _) = AStar.search_observed(&grid,
+///                                       Position{x: 0, y: 1},
+///                                       Position{x: 2, y: 1},
+///                                       MovementModel::FourWay,
+///                                       &mut trace).path.unwrap();
+/// let explored = trace.expanded();
+/// let picture = Picture::new(&grid).with_path(&path)
+///                                  .with_explored(&explored);
+/// assert_eq!(2, picture.ansi().lines().count());
+/// assert!(picture.svg(10).starts_with("<svg"));
+/// assert_eq!(b"P6\n30 20\n255\n", &picture.ppm(10)[..13]);
+/// ```
+#[derive(Copy, Clone, Debug)]
+pub struct Picture<'a> {
+    grid: &'a Grid<'a>,
+    movement: MovementModel,
+    path: &'a [Position],
+    explored: &'a [Position],
+}
+
+impl<'a> Picture<'a> {
+    /// A picture of `grid` on its own, where paths move as with
+    /// `MovementModel::FourWay` until set with `with_movement`.
+    pub fn new(grid: &'a Grid) -> Picture<'a> {
+        Picture { grid,
+                  movement: MovementModel::FourWay,
+                  path: &[],
+                  explored: &[] }
+    }
+
+    /// Draw `path` over the grid, with its first cell as the start and its
+    /// last as the goal. Where the path jumps between cells that aren't a
+    /// single step apart, such as through a portal of a `Topology`, both
+    /// cells are marked.
+    pub fn with_path(self, path: &'a [Position]) -> Picture<'a> {
+        Picture { path, ..self }
+    }
+
+    /// Take the steps of the path to be those of `movement`, so that any
+    /// other move is drawn as a jump.
+    pub fn with_movement(self, movement: MovementModel) -> Picture<'a> {
+        Picture { movement, ..self }
+    }
+
+    /// Returns true if the path moving from `a` to `b` jumps, through a
+    /// portal or across an edge that wraps, rather than taking a step.
+    #[inline]
+    fn is_jump(&self, a: Position, b: Position) -> bool {
+        !self.movement.is_step(a, b)
+    }
+
+    /// Shade the cells in `explored`, such as those from `Trace::expanded`.
+    pub fn with_explored(self, explored: &'a [Position]) -> Picture<'a> {
+        Picture { explored, ..self }
+    }
+
+    /// The colour of every cell, row by row.
+    fn colours(&self) -> Vec<Rgb> {
+        let grid = self.grid;
+        let max_cost = grid.positions().filter_map(|p| grid.cost(p)).max()
+            .unwrap_or(1);
+        let mut colours: Vec<Rgb> = grid.positions()
+            .map(|p| match grid.cost(p) {
+                None => WALL_COLOUR,
+                Some(_) if max_cost <= 1 => OPEN_COLOUR,
+                Some(c) => mix(OPEN_COLOUR, COSTLY_COLOUR,
+                               (c - 1) as f64 / (max_cost - 1) as f64),
+            })
+            .collect();
+        let index = |p: Position| p.y * grid.width() + p.x;
+        for &p in self.explored.iter().filter(|&&p| grid.contains(p)) {
+            // Keep some of the terrain's colour so that costs still show.
+            colours[index(p)] = mix(colours[index(p)], EXPLORED_COLOUR, 0.7);
+        }
+        let last = self.path.len().saturating_sub(1);
+        let jumps = |i: usize| {
+            (i > 0 && self.is_jump(self.path[i - 1], self.path[i])) ||
+                (i < last && self.is_jump(self.path[i], self.path[i + 1]))
+        };
+        for (i, &p) in self.path.iter().enumerate() {
+            if grid.contains(p) {
+                colours[index(p)] = match i {
+                    0 => START_COLOUR,
+                    _ if i == last => GOAL_COLOUR,
+                    _ if jumps(i) => JUMP_COLOUR,
+                    _ => PATH_COLOUR,
+                };
+            }
+        }
+        colours
+    }
+
+    /// Render the picture as text for a terminal that supports 24-bit ANSI
+    /// colours, one row per line. Cells show their characters as drawn by
+    /// `Grid::format_path`, except walls which are left blank.
+    pub fn ansi(&self) -> String {
+        let colours = self.colours();
+        let chars = self.grid.format_path(self.path);
+        let on_path: HashSet<Position> = self.path.iter().cloned().collect();
+        let mut out = String::new();
+        for p in self.grid.positions() {
+            let (r, g, b) = colours[p.y * self.grid.width() + p.x];
+            let ch = if self.grid.is_passable(p) || on_path.contains(&p) {
+                chars[p]
+            } else {
+                ' '
+            };
+            write!(out, "\x1b[30;48;2;{};{};{}m{}", r, g, b, ch).unwrap();
+            if p.x + 1 == self.grid.width() {
+                out.push_str("\x1b[0m\n");
+            }
+        }
+        out
+    }
+
+    /// Render the picture as an SVG image with square cells `cell_size`
+    /// pixels wide. Terrain that costs more than 1 is labelled with its
+    /// cost and the path is also drawn as a line through the cell centres.
+    pub fn svg(&self, cell_size: usize) -> String {
+        let (width, height) = (self.grid.width() * cell_size,
+                               self.grid.height() * cell_size);
+        let mut out = String::new();
+        writeln!(out, "<svg xmlns=\"http://www.w3.org/2000/svg\" \
+                       width=\"{0}\" height=\"{1}\" \
+                       viewBox=\"0 0 {0} {1}\">",
+                 width, height).unwrap();
+        let colours = self.colours();
+        for p in self.grid.positions() {
+            let (r, g, b) = colours[p.y * self.grid.width() + p.x];
+            writeln!(out, "<rect x=\"{x}\" y=\"{y}\" width=\"{size}\" \
+                           height=\"{size}\" \
+                           fill=\"#{r:02x}{g:02x}{b:02x}\"/>",
+                     x = p.x * cell_size, y = p.y * cell_size,
+                     size = cell_size, r = r, g = g, b = b)
+                .unwrap();
+        }
+        let centre = |i: usize| i * cell_size + cell_size / 2;
+        for p in self.grid.positions() {
+            if let Some(cost) = self.grid.cost(p).filter(|&c| c > 1) {
+                writeln!(out, "<text x=\"{}\" y=\"{}\" font-size=\"{}\" \
+                               text-anchor=\"middle\" \
+                               dominant-baseline=\"central\">{}</text>",
+                         centre(p.x), centre(p.y), cell_size * 3 / 5, cost)
+                    .unwrap();
+            }
+        }
+        // The path is drawn as a line through the cell centres, broken
+        // wherever it jumps, with a dashed line across each jump.
+        let (r, g, b) = PATH_COLOUR;
+        let mut runs: Vec<&[Position]> = Vec::new();
+        let mut run_start = 0;
+        for i in 1..self.path.len() {
+            if self.is_jump(self.path[i - 1], self.path[i]) {
+                runs.push(&self.path[run_start..i]);
+                run_start = i;
+            }
+        }
+        runs.push(&self.path[run_start..]);
+        for run in runs.iter().filter(|run| run.len() > 1) {
+            let points: Vec<String> = run.iter()
+                .map(|p| format!("{},{}", centre(p.x), centre(p.y)))
+                .collect();
+            writeln!(out, "<polyline points=\"{}\" fill=\"none\" \
+                           stroke=\"#{:02x}{:02x}{:02x}\" \
+                           stroke-width=\"{}\" stroke-linecap=\"round\" \
+                           stroke-linejoin=\"round\"/>",
+                     points.join(" "), r, g, b, (cell_size / 4).max(1))
+                .unwrap();
+        }
+        let (r, g, b) = JUMP_COLOUR;
+        for pair in runs.windows(2) {
+            let (from, to) = (pair[0][pair[0].len() - 1], pair[1][0]);
+            writeln!(out, "<line x1=\"{}\" y1=\"{}\" x2=\"{}\" y2=\"{}\" \
+                           stroke=\"#{:02x}{:02x}{:02x}\" \
+                           stroke-width=\"{}\" stroke-dasharray=\"{}\"/>",
+                     centre(from.x), centre(from.y), centre(to.x),
+                     centre(to.y), r, g, b, (cell_size / 8).max(1),
+                     (cell_size / 4).max(2))
+                .unwrap();
+        }
+        out.push_str("</svg>\n");
+        out
+    }
+
+    /// Render the picture as a binary PPM (P6) image with square cells
+    /// `cell_size` pixels wide.
+    pub fn ppm(&self, cell_size: usize) -> Vec<u8> {
+        let (width, height) = (self.grid.width() * cell_size,
+                               self.grid.height() * cell_size);
+        let mut out = format!("P6\n{} {}\n255\n", width, height).into_bytes();
+        let colours = self.colours();
+        for y in 0..height {
+            for x in 0..width {
+                let (r, g, b) =
+                    colours[(y / cell_size) * self.grid.width() +
+                            x / cell_size];
+                out.extend_from_slice(&[r, g, b]);
+            }
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+use {mazes, terrain_cost, Topology};
+
+#[test]
+fn colours_cells_by_kind() {
+    let grid = Grid::new(&[vec![' ', '9', '5', '█']]).unwrap()
+        .with_cost(terrain_cost);
+    let path = [Position { x: 0, y: 0 }];
+    let explored = [Position { x: 2, y: 0 }];
+    let colours = Picture::new(&grid).with_path(&path)
+        .with_explored(&explored)
+        .colours();
+    assert_eq!(vec![START_COLOUR,
+                    COSTLY_COLOUR,
+                    mix(mix(OPEN_COLOUR, COSTLY_COLOUR, 0.5),
+                        EXPLORED_COLOUR, 0.7),
+                    WALL_COLOUR],
+               colours);
+}
+
+#[test]
+fn ansi_has_a_line_per_row() {
+    let maze = mazes::single_walled_path();
+    let grid = maze.grid();
+    let path = [maze.start, maze.goal];
+    let text = Picture::new(&grid).with_path(&path).ansi();
+    let lines: Vec<&str> = text.lines().collect();
+    assert_eq!(grid.height(), lines.len());
+    assert!(lines.iter().all(|line| line.ends_with("\x1b[0m")));
+    assert!(text.contains("\x1b[30;48;2;40;170;70m@"));
+    assert!(!text.contains('█'));
+}
+
+#[test]
+fn svg_draws_cells_costs_and_path() {
+    let grid = Grid::new(&[vec![' ', '3'],
+                           vec![' ', ' ']]).unwrap()
+        .with_cost(terrain_cost);
+    let path = [Position { x: 0, y: 0 }, Position { x: 0, y: 1 },
+                Position { x: 1, y: 1 }];
+    let svg = Picture::new(&grid).with_path(&path).svg(20);
+    assert!(svg.starts_with("<svg xmlns=\"http://www.w3.org/2000/svg\" \
+                             width=\"40\" height=\"40\""));
+    assert_eq!(4, svg.matches("<rect ").count());
+    assert!(svg.contains(">3</text>"));
+    assert!(svg.contains("points=\"10,10 10,30 30,30\""));
+    assert!(svg.ends_with("</svg>\n"));
+}
+
+#[test]
+fn ppm_scales_cells() {
+    let grid = Grid::new(&[vec![' ', '█']]).unwrap();
+    let ppm = Picture::new(&grid).ppm(2);
+    let header = b"P6\n4 2\n255\n";
+    assert_eq!(&header[..], &ppm[..header.len()]);
+    let pixels = &ppm[header.len()..];
+    assert_eq!(4 * 2 * 3, pixels.len());
+    let (o, w) = (OPEN_COLOUR, WALL_COLOUR);
+    let row = [o.0, o.1, o.2, o.0, o.1, o.2, w.0, w.1, w.2, w.0, w.1, w.2];
+    assert_eq!(&row[..], &pixels[..12]);
+    assert_eq!(&row[..], &pixels[12..]);
+}
+
+#[test]
+fn jumps_are_marked() {
+    let grid = Grid::new(&[vec![' ', ' ', '█', ' ', ' ']]).unwrap();
+    let topology = Topology::new(&grid, MovementModel::FourWay)
+        .wrapping(true, false);
+    let (path, _) = topology.search(Position { x: 1, y: 0 },
+                                    Position { x: 3, y: 0 })
+        .path.unwrap();
+    let picture = Picture::new(&grid).with_path(&path)
+                                     .with_movement(MovementModel::FourWay);
+    assert_eq!(vec![JUMP_COLOUR, START_COLOUR, WALL_COLOUR, GOAL_COLOUR,
+                    JUMP_COLOUR],
+               picture.colours());
+    let svg = picture.svg(10);
+    assert_eq!(2, svg.matches("<polyline ").count());
+    assert!(svg.contains("points=\"15,5 5,5\""));
+    assert!(svg.contains("points=\"45,5 35,5\""));
+    assert!(svg.contains("<line x1=\"5\" y1=\"5\" x2=\"45\" y2=\"5\""));
+}
+
+#[test]
+fn jumps_to_nearby_cells_are_marked() {
+    // The portals are diagonal neighbours, which isn't a four-way step.
+    let grid = Grid::new(&[vec![' ', 'a', '█'],
+                           vec!['█', '█', 'a'],
+                           vec!['█', '█', ' ']]).unwrap();
+    let topology = Topology::new(&grid, MovementModel::FourWay)
+        .with_portals()
+        .unwrap();
+    let (path, _) = topology.search(Position { x: 0, y: 0 },
+                                    Position { x: 2, y: 2 })
+        .path.unwrap();
+    let picture = Picture::new(topology.grid()).with_path(&path);
+    let colours = picture.colours();
+    assert_eq!((JUMP_COLOUR, JUMP_COLOUR), (colours[1], colours[5]));
+    assert_eq!(1, picture.svg(10).matches("<line ").count());
+
+    let picture = picture.with_movement(MovementModel::EightWay);
+    assert_eq!(PATH_COLOUR, picture.colours()[1]);
+    assert_eq!(0, picture.svg(10).matches("<line ").count());
+}
@@ -0,0 +1,375 @@
+//! Incremental replanning with D* Lite, for grids that change between
+//! searches.
+//!
+//! Reference: Koenig and Likhachev, "D* Lite", AAAI 2002.
+
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, HashMap};
+
+use {Grid, MovementModel, Position, SearchResult};
+
+/// Stands in for an infinite cost.
+const UNREACHABLE: usize = usize::MAX;
+
+/// The priority of a cell in the open list. Smaller keys are expanded first.
+type Key = (usize, usize);
+
+// An entry in the open list, ordered so that `BinaryHeap` pops the smallest
+// key first and ties are broken by position.
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+struct OpenEntry {
+    key: Key,
+    position: Position,
+}
+
+impl Ord for OpenEntry {
+    fn cmp(&self, other: &OpenEntry) -> Ordering {
+        (other.key, other.position.x, other.position.y)
+            .cmp(&(self.key, self.position.x, self.position.y))
+    }
+}
+
+impl PartialOrd for OpenEntry {
+    fn partial_cmp(&self, other: &OpenEntry) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// Keeps the cheapest path from a start position to a goal up to date as the
+/// cells of the grid change, and as the start moves along the path, without
+/// searching from scratch each time.
+///
+/// The search runs backwards from the goal so that the work done for one plan
+/// can be reused for the next: after a change only the cells whose cost to the
+/// goal is affected are searched again.
+///
+/// # Examples
+///
+/// ```
+/// use bestpath::{Grid, MovementModel, Position, Replanner};
+///
+/// let grid = Grid::new(&[vec![' ', ' ', ' '],
+///                        vec![' ', ' ', ' ']]).unwrap();
+/// let mut planner = Replanner::new(grid,
+///                                  Position{x: 0, y: 0},
+///                                  Position{x: 2, y: 0},
+///                                  MovementModel::FourWay);
+/// assert_eq!(Some(2), planner.plan().path.map(|(_, cost)| cost));
+///
+/// // Close a door in the way.
+/// planner.set_cell(Position{x: 1, y: 0}, '█');
+/// assert_eq!(Some(4), planner.plan().path.map(|(_, cost)| cost));
+/// ```
+#[derive(Clone, Debug)]
+pub struct Replanner<'c> {
+    grid: Grid<'c>,
+    movement: MovementModel,
+    start: Position,
+    goal: Position,
+    // The cost to the goal as of the last time each cell was expanded. Cells
+    // that are missing have never been reached.
+    g: HashMap<Position, usize>,
+    // The cost to the goal according to the neighbours' `g`.
+    rhs: HashMap<Position, usize>,
+    open: BinaryHeap<OpenEntry>,
+    // The current key of every cell in `open`; other entries are stale.
+    open_keys: HashMap<Position, Key>,
+    // Added to every key so that existing keys stay valid when the start
+    // moves.
+    key_modifier: usize,
+}
+
+impl<'c> Replanner<'c> {
+    /// Constructs a planner for paths from `start` to `goal` through `grid`.
+    /// No searching is done until `plan` is called.
+    pub fn new(grid: Grid<'c>,
+               start: Position,
+               goal: Position,
+               movement: MovementModel) -> Replanner<'c> {
+        let mut planner = Replanner { grid,
+                                      movement,
+                                      start,
+                                      goal,
+                                      g: HashMap::new(),
+                                      rhs: HashMap::new(),
+                                      open: BinaryHeap::new(),
+                                      open_keys: HashMap::new(),
+                                      key_modifier: 0 };
+        planner.rhs.insert(goal, 0);
+        let key = planner.key(goal);
+        planner.push(goal, key);
+        planner
+    }
+
+    /// The grid as it is now, including every change made with `set_cell`.
+    #[inline]
+    pub fn grid(&self) -> &Grid<'c> {
+        &self.grid
+    }
+
+    /// The position that paths start from.
+    #[inline]
+    pub fn start(&self) -> Position {
+        self.start
+    }
+
+    /// The position that paths lead to.
+    #[inline]
+    pub fn goal(&self) -> Position {
+        self.goal
+    }
+
+    #[inline]
+    fn g(&self, pos: Position) -> usize {
+        self.g.get(&pos).cloned().unwrap_or(UNREACHABLE)
+    }
+
+    #[inline]
+    fn rhs(&self, pos: Position) -> usize {
+        self.rhs.get(&pos).cloned().unwrap_or(UNREACHABLE)
+    }
+
+    fn key(&self, pos: Position) -> Key {
+        let cost = self.g(pos).min(self.rhs(pos));
+        (cost.saturating_add(self.movement.heuristic(self.start, pos))
+             .saturating_add(self.key_modifier),
+         cost)
+    }
+
+    fn push(&mut self, position: Position, key: Key) {
+        self.open_keys.insert(position, key);
+        self.open.push(OpenEntry { key, position });
+    }
+
+    /// The cells that can step directly into `pos`, with the cost of each
+    /// step. Every movement model only steps between cells that touch, so
+    /// only those need to be checked.
+    fn predecessors(&self, pos: Position) -> Vec<(Position, usize)> {
+        let mut v = Vec::new();
+        for dy in -1..=1 {
+            for dx in -1..=1 {
+                if let Some(p) = self.grid.offset(pos, (dx, dy)) {
+                    if let Some(&(_, c)) = self.movement
+                            .neighbours(&self.grid, p)
+                            .iter()
+                            .find(|&&(next, _)| next == pos) {
+                        v.push((p, c));
+                    }
+                }
+            }
+        }
+        v
+    }
+
+    /// Recalculate `rhs` for `pos` from its neighbours and put it in the open
+    /// list if it is now inconsistent.
+    fn update(&mut self, pos: Position) {
+        if pos != self.goal {
+            let rhs = self.movement.neighbours(&self.grid, pos)
+                .into_iter()
+                .map(|(next, c)| self.g(next).saturating_add(c))
+                .min()
+                .unwrap_or(UNREACHABLE);
+            self.rhs.insert(pos, rhs);
+        }
+        self.open_keys.remove(&pos);
+        if self.g(pos) != self.rhs(pos) {
+            let key = self.key(pos);
+            self.push(pos, key);
+        }
+    }
+
+    /// Change the character at `pos`, which may change its cost or whether it
+    /// is passable. The path is repaired by the next call to `plan`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `pos` is outside of the grid.
+    pub fn set_cell(&mut self, pos: Position, ch: char) {
+        if self.grid[pos] == ch {
+            return;
+        }
+        self.grid[pos] = ch;
+        // The steps out of every cell touching `pos` may have changed,
+        // including diagonal steps that pass its corner.
+        for dy in -1..=1 {
+            for dx in -1..=1 {
+                if let Some(p) = self.grid.offset(pos, (dx, dy)) {
+                    self.update(p);
+                }
+            }
+        }
+    }
+
+    /// Move the start, e.g. after an agent has taken some steps along the
+    /// path. The path is repaired by the next call to `plan`.
+    pub fn move_start(&mut self, start: Position) {
+        self.key_modifier += self.movement.heuristic(self.start, start);
+        self.start = start;
+    }
+
+    /// Expand inconsistent cells until the cost from the start is known and
+    /// return the number of cells expanded.
+    fn compute(&mut self) -> usize {
+        let mut expanded = 0;
+        while let Some(&OpenEntry { key, position }) = self.open.peek() {
+            if self.open_keys.get(&position) != Some(&key) {
+                self.open.pop();
+                continue;
+            }
+            if key >= self.key(self.start) &&
+                    self.rhs(self.start) == self.g(self.start) {
+                break;
+            }
+            self.open.pop();
+            self.open_keys.remove(&position);
+
+            let new_key = self.key(position);
+            if key < new_key {
+                self.push(position, new_key);
+                continue;
+            }
+            expanded += 1;
+            let rhs = self.rhs(position);
+            if self.g(position) > rhs {
+                self.g.insert(position, rhs);
+            } else {
+                self.g.remove(&position);
+                self.update(position);
+            }
+            for (p, _) in self.predecessors(position) {
+                self.update(p);
+            }
+        }
+        expanded
+    }
+
+    /// Repair the plan after any changes and return the cheapest path from
+    /// the start to the goal. `expanded` counts only the cells expanded by
+    /// this call.
+    pub fn plan(&mut self) -> SearchResult {
+        let expanded = self.compute();
+        let cost = self.g(self.start);
+        if cost == UNREACHABLE {
+            return SearchResult { path: None, expanded };
+        }
+
+        let mut path = vec![self.start];
+        let mut current = self.start;
+        while current != self.goal {
+            current = self.movement.neighbours(&self.grid, current)
+                .into_iter()
+                .filter(|&(next, c)| {
+                    self.g(next).checked_add(c) == Some(self.g(current))
+                })
+                .map(|(next, _)| next)
+                .next()
+                .expect("cost to the goal is inconsistent");
+            path.push(current);
+        }
+        SearchResult { path: Some((path, cost)), expanded }
+    }
+}
+
+#[cfg(test)]
+use mazes::{random_grid, ALL_MODELS};
+#[cfg(test)]
+use search::path_cost;
+#[cfg(test)]
+use {mazes, AStar, Pathfinder};
+
+/// Check that the planner's path is as cheap as a fresh search of its grid.
+#[cfg(test)]
+fn check_plan(planner: &mut Replanner, context: &str) {
+    let result = planner.plan();
+    let expected = AStar.search(planner.grid(), planner.start(),
+                                planner.goal(), planner.movement);
+    assert_eq!(expected.path.as_ref().map(|p| p.1),
+               result.path.as_ref().map(|p| p.1),
+               "{}", context);
+    if let Some((path, cost)) = result.path {
+        assert_eq!(Some(&planner.start()), path.first());
+        assert_eq!(Some(&planner.goal()), path.last());
+        assert_eq!(cost, path_cost(planner.grid(), &path, planner.movement));
+    }
+}
+
+#[test]
+fn matches_fresh_search_after_every_edit() {
+    for seed in 0..6 {
+        for &movement in ALL_MODELS.iter() {
+            let grid = random_grid(seed, 12, 9, 5);
+            let mut planner = Replanner::new(grid,
+                                             Position { x: 0, y: 0 },
+                                             Position { x: 11, y: 8 },
+                                             movement);
+            check_plan(&mut planner, "initial");
+
+            // Edit cells with a separate generator from the grid's.
+            let mut state = seed.wrapping_add(99);
+            for edit in 0..40 {
+                state = state.wrapping_mul(6364136223846793005)
+                             .wrapping_add(1442695040888963407);
+                let r = (state >> 33) as usize;
+                let pos = Position { x: r % 12, y: (r / 12) % 9 };
+                let ch = ['█', ' ', '5', '█', ' '][(r / 108) % 5];
+                planner.set_cell(pos, ch);
+                check_plan(&mut planner,
+                           &format!("seed {} {:?} edit {} {:?} -> {:?}",
+                                    seed, movement, edit, pos, ch));
+            }
+        }
+    }
+}
+
+#[test]
+fn matches_fresh_search_as_the_start_moves() {
+    for &movement in ALL_MODELS.iter() {
+        let maze = mazes::multiple_complex_paths();
+        let mut planner = Replanner::new(maze.grid(), maze.start, maze.goal,
+                                         movement);
+        let mut toggle = 0;
+        while planner.start() != planner.goal() {
+            check_plan(&mut planner, &format!("{:?}", movement));
+            let (path, _) = planner.plan().path.unwrap();
+            planner.move_start(path[1]);
+            // Block and unblock a cell that some of the paths run through.
+            toggle += 1;
+            let ch = if toggle % 3 == 0 { '█' } else { ' ' };
+            planner.set_cell(Position { x: 4, y: 6 }, ch);
+        }
+        check_plan(&mut planner, "at goal");
+    }
+}
+
+#[test]
+fn reports_no_path_until_doors_open() {
+    let maze = mazes::no_path();
+    let mut planner = Replanner::new(maze.grid(), maze.start, maze.goal,
+                                     MovementModel::FourWay);
+    assert_eq!(None, planner.plan().path);
+    planner.set_cell(Position { x: 0, y: 1 }, ' ');
+    assert_eq!(None, planner.plan().path);
+    planner.set_cell(Position { x: 4, y: 5 }, ' ');
+    check_plan(&mut planner, "doors open");
+    assert!(planner.plan().path.is_some());
+    planner.set_cell(Position { x: 0, y: 1 }, '█');
+    assert_eq!(None, planner.plan().path);
+}
+
+#[test]
+fn distant_edits_need_no_searching() {
+    let grid = Grid::filled(30, 30, ' ');
+    let mut planner = Replanner::new(grid,
+                                     Position { x: 0, y: 0 },
+                                     Position { x: 3, y: 0 },
+                                     MovementModel::FourWay);
+    assert!(planner.plan().expanded > 0);
+    assert_eq!(0, planner.plan().expanded);
+    planner.set_cell(Position { x: 25, y: 25 }, '█');
+    assert_eq!(0, planner.plan().expanded);
+    planner.set_cell(Position { x: 1, y: 0 }, '█');
+    let result = planner.plan();
+    assert!(result.expanded > 0);
+    assert_eq!(Some(5), result.path.map(|(_, cost)| cost));
+}
@@ -0,0 +1,1148 @@
+//! Interchangeable search algorithms that share the grid representation,
+//! movement models and path reconstruction.
+
+use std::collections::{BinaryHeap, VecDeque};
+use std::cmp::Ordering;
+
+use search_cells::SearchCells;
+use {Graph, Grid, GridGraph, MovementModel, Position, SearchEvent,
+     SearchObserver};
+
+/// The outcome of a search by a `Pathfinder`, or of `AStar::search_graph`
+/// for graphs whose nodes are of type `N`.
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct SearchResult<N = Position> {
+    /// The path found and its cost, or `None` if there is no path.
+    pub path: Option<(Vec<N>, usize)>,
+    /// The number of nodes removed from the frontier and expanded. For
+    /// `JumpPoint` this counts jump points rather than cells.
+    pub expanded: usize,
+}
+
+/// An algorithm for finding a path through a grid, where `movement` gives the
+/// steps that a path can take.
+///
+/// # Examples
+///
+/// ```
+/// use bestpath::{AStar, BreadthFirst, Dijkstra, Grid, MovementModel,
+///                Pathfinder, Position};
+///
+/// let grid = Grid::new(&[vec![' ', ' ', ' ', ' '],
+///                        vec![' ', '*', '*', ' '],
+///                        vec![' ', ' ', ' ', ' ']]).unwrap();
+/// let pathfinders: Vec<Box<dyn Pathfinder>> =
+///     vec![Box::new(BreadthFirst), Box::new(Dijkstra), Box::new(AStar)];
+/// for pathfinder in pathfinders {
+///     let result = pathfinder.search(&grid,
+///                                    Position{x: 0, y: 1},
+///                                    Position{x: 3, y: 1},
+///                                    MovementModel::FourWay);
+///     let (path, cost) = result.path.unwrap();
+///     assert_eq!(6, path.len());
+///     assert_eq!(5, cost);
+/// }
+/// ```
+pub trait Pathfinder {
+    fn search(&self,
+              grid: &Grid,
+              start: Position,
+              goal: Position,
+              movement: MovementModel) -> SearchResult;
+
+    /// Like `search` but tells `observer` about every step of the search.
+    /// By default nothing is reported, for pathfinders that can't be
+    /// watched.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bestpath::{AStar, BidirectionalAStar, BreadthFirst, Dijkstra,
+    ///                Grid, JumpPoint, MovementModel, Pathfinder, Position,
+    ///                SearchStats};
+    ///
+    /// let grid = Grid::filled(4, 4, ' ');
+    /// let pathfinders: Vec<Box<dyn Pathfinder>> =
+    ///     vec![Box::new(BreadthFirst), Box::new(Dijkstra), Box::new(AStar),
+    ///          Box::new(BidirectionalAStar), Box::new(JumpPoint)];
+    /// for pathfinder in pathfinders {
+    ///     let mut stats = SearchStats::default();
+    ///     let result = pathfinder.search_observed(
+    ///         &grid, Position{x: 0, y: 0}, Position{x: 3, y: 0},
+    ///         MovementModel::EightWayNoCornerCutting, &mut stats);
+    ///     assert_eq!(result.expanded, stats.expanded);
+    ///     assert!(stats.max_frontier <= stats.pushed);
+    /// }
+    /// ```
+    fn search_observed(&self,
+                       grid: &Grid,
+                       start: Position,
+                       goal: Position,
+                       movement: MovementModel,
+                       _observer: &mut dyn SearchObserver) -> SearchResult {
+        self.search(grid, start, goal, movement)
+    }
+}
+
+/// Explores cells in the order that they were discovered. Finds the path with
+/// the fewest steps, which is only the cheapest path if every step costs the
+/// same.
+///
+/// When observed, the priority of each cell pushed is its number of steps
+/// from the start, and its cost is that of the first path found to it.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct BreadthFirst;
+
+/// Explores the cheapest unexplored cell first. Always finds the cheapest path
+/// but explores in every direction.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct Dijkstra;
+
+/// Like `Dijkstra` but prefers cells closer to the goal according to the
+/// movement model's heuristic. Always finds the cheapest path.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct AStar;
+
+/// Runs A* forwards from the start and backwards from the goal until the two
+/// searches meet. Always finds the cheapest path.
+///
+/// When observed, the events of both searches are reported as they happen.
+/// The backward search measures costs from the goal, its `CameFrom` events
+/// give the cell that the path continues to towards the goal, and frontier
+/// lengths count the entries of both frontiers.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct BidirectionalAStar;
+
+/// Jump Point Search: A* that skips over runs of open cells on uniform-cost,
+/// 8-connected grids, only expanding the cells where a path might turn.
+///
+/// Only `MovementModel::EightWayNoCornerCutting` is supported; for any other
+/// movement model this falls back to `AStar`. Every passable cell is treated
+/// as costing the same so the path found is only the cheapest if that is true,
+/// but the cost reported is always the true cost of the path.
+///
+/// When observed, only the jump points are reported, not the cells jumped
+/// over between them.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct JumpPoint;
+
+/// How a best-first search chooses between frontier entries with the same
+/// estimated total cost. Every choice ends by comparing positions, so the path
+/// found never depends on the order that entries happened to be pushed.
+///
+/// The nodes of a `Graph` searched with `AStar::search_graph_with` have no
+/// positions or directions, so the order that they were first reached takes
+/// the place of their positions and no turns are counted.
+///
+/// # Examples
+///
+/// ```
+/// use bestpath::{AStar, Grid, MovementModel, Position, TieBreak};
+///
+/// let grid = Grid::filled(3, 3, ' ');
+/// let search = |tie_break| {
+///     AStar.search_with(&grid, Position{x: 0, y: 0}, Position{x: 2, y: 2},
+///                       MovementModel::FourWay, tie_break, &mut ())
+///          .path.unwrap().0
+/// };
+/// // The cells with the smallest x are explored first, so the path runs
+/// // down the first column.
+/// assert_eq!(vec![Position{x: 0, y: 0}, Position{x: 0, y: 1},
+///                 Position{x: 0, y: 2}, Position{x: 1, y: 2},
+///                 Position{x: 2, y: 2}],
+///            search(TieBreak::Position));
+/// ```
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug, Default)]
+pub enum TieBreak {
+    /// Prefer the entry with the larger cost so far, i.e. the one closest to
+    /// the goal according to the heuristic, then the smallest position. This
+    /// usually expands the fewest cells.
+    #[default]
+    LargerCost,
+    /// Prefer the smallest position, comparing x and then y.
+    Position,
+    /// Prefer the path with the fewest changes of direction so far, then the
+    /// larger cost so far, then the smallest position. Among paths of equal
+    /// cost a path with fewer turns replaces one found earlier, as long as its
+    /// end hasn't been expanded yet. This usually straightens paths but, since
+    /// turns are only compared between paths to the same cell, it doesn't
+    /// guarantee the fewest turns overall.
+    FewerTurns,
+}
+
+impl TieBreak {
+    /// The key to order entries by after their estimated total cost, smallest
+    /// first, where `[x, y]` identifies the entry's node as given by
+    /// `SearchState::order`.
+    #[inline]
+    fn key(self, cost: usize, turns: usize, [x, y]: [usize; 2])
+            -> [usize; 4] {
+        let larger_cost = usize::MAX - cost;
+        match self {
+            TieBreak::LargerCost => [larger_cost, x, y, 0],
+            TieBreak::Position => [x, y, 0, 0],
+            TieBreak::FewerTurns => [turns, larger_cost, x, y],
+        }
+    }
+}
+
+// Used to maintain a priority queue of positions, or other nodes, to explore.
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub(crate) struct PositionPriority<N = Position> {
+    pub minimum_cost: usize,
+    pub position: N,
+    // Entries with the same `minimum_cost` are explored in order of `tie`.
+    // It must include the position so that distinct entries never compare
+    // equal.
+    tie: [usize; 4],
+}
+
+impl PositionPriority {
+    /// An entry where ties are broken by position.
+    #[inline]
+    pub fn new(minimum_cost: usize, position: Position) -> PositionPriority {
+        PositionPriority { minimum_cost,
+                           position,
+                           tie: [position.x, position.y, 0, 0] }
+    }
+}
+
+// The priority queue depends on `Ord`.
+impl<N: Eq> Ord for PositionPriority<N> {
+    // Reverse the comparison so the position with the lowest cost is
+    // explored first.
+    fn cmp(&self, other: &PositionPriority<N>) -> Ordering {
+        other.minimum_cost.cmp(&self.minimum_cost)
+             .then_with(|| other.tie.cmp(&self.tie))
+    }
+}
+
+impl<N: Eq> PartialOrd for PositionPriority<N> {
+    fn partial_cmp(&self, other: &PositionPriority<N>) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// The total cost of following `path`, which must only take steps allowed by
+/// `movement`.
+pub(crate) fn path_cost(grid: &Grid,
+                         path: &[Position],
+                         movement: MovementModel) -> usize {
+    path.windows(2)
+        .map(|step| {
+            movement.neighbours(grid, step[0])
+                .into_iter()
+                .find(|&(p, _)| p == step[1])
+                .map(|(_, c)| c)
+                .expect("path takes an illegal step")
+        })
+        .sum()
+}
+
+/// What `explore` remembers about the nodes of type `N` that it reaches.
+pub(crate) trait SearchState<N: Copy> {
+    /// The cost of the cheapest path found to `node`, or `None` if it hasn't
+    /// been reached.
+    fn cost(&self, node: N) -> Option<usize>;
+
+    /// The node that the cheapest path found to `node` comes from, or `None`
+    /// if it hasn't been reached or is where the search started.
+    fn came_from(&self, node: N) -> Option<N>;
+
+    /// Records a path to `node` that costs `cost` and comes from `from`, or
+    /// starts at `node` if `from` is `None`, with no turns.
+    fn reach(&mut self, node: N, cost: usize, from: Option<N>);
+
+    /// Marks `node` as expanded, returning false if it already was.
+    fn close(&mut self, node: N) -> bool;
+
+    /// Returns true if `node` has been expanded.
+    fn is_closed(&self, node: N) -> bool;
+
+    /// Two numbers that identify `node`, which must have been reached, for
+    /// breaking ties between frontier entries.
+    fn order(&self, node: N) -> [usize; 2];
+
+    /// The number of turns along the cheapest path found to `node`, if they
+    /// are counted.
+    fn turns(&self, _node: N) -> usize {
+        0
+    }
+
+    /// Sets the number of turns along the path to `node`, if they are
+    /// counted.
+    fn set_turns(&mut self, _node: N, _turns: usize) {}
+
+    /// Returns true if stepping from `from` to `via` and then to `to`
+    /// changes direction at `via`. Only grids have directions, so by default
+    /// it never does.
+    fn is_turn(&self, _from: N, _via: N, _to: N) -> bool {
+        false
+    }
+
+    /// The cheapest path found from where the search started to `node`,
+    /// which must have been reached.
+    fn path_to(&self, node: N) -> Vec<N> {
+        let mut path = vec![node];
+        let mut current = node;
+        while let Some(previous) = self.came_from(current) {
+            path.push(previous);
+            current = previous;
+        }
+        path.reverse();
+        path
+    }
+}
+
+/// The state left behind by `explore`.
+pub(crate) struct Exploration<N = Position, S = SearchCells> {
+    /// The goal that the search stopped at, if any.
+    pub reached: Option<N>,
+    pub state: S,
+    pub expanded: usize,
+}
+
+/// Best-first search through `graph` ordered by cost so far plus
+/// `heuristic`, stopping at the first node for which `is_goal` returns true.
+/// With a zero heuristic this is Dijkstra's algorithm, otherwise it is A*. If
+/// `is_goal` never returns true then every reachable node is explored.
+/// `state` holds what is learned about each node, and starts empty.
+/// `observer` is told about every step of the search.
+pub(crate) fn explore<G, S, F, H, O>(graph: &G,
+                                     mut state: S,
+                                     start: G::Node,
+                                     is_goal: F,
+                                     heuristic: H,
+                                     tie_break: TieBreak,
+                                     observer: &mut O)
+        -> Exploration<G::Node, S>
+        where G: Graph + ?Sized,
+              S: SearchState<G::Node>,
+              F: Fn(G::Node) -> bool,
+              H: Fn(G::Node) -> usize,
+              O: SearchObserver<G::Node> + ?Sized {
+    let priority = |state: &S, cost: usize, turns: usize, node: G::Node| {
+        PositionPriority { minimum_cost: cost + heuristic(node),
+                           position: node,
+                           tie: tie_break.key(cost, turns, state.order(node)) }
+    };
+    state.reach(start, 0, None);
+    let mut frontier = BinaryHeap::new();
+    frontier.push(priority(&state, 0, 0, start));
+    observer.observe(SearchEvent::Pushed { position: start,
+                                           priority: heuristic(start),
+                                           frontier_len: 1 });
+    let mut expanded = 0;
+
+    while let Some(PositionPriority { minimum_cost, position, .. }) =
+            frontier.pop() {
+        let current_cost = state.cost(position).unwrap();
+        if minimum_cost > current_cost + heuristic(position) ||
+                !state.close(position) {
+            // A cheaper way to this node was found after it was queued, or it
+            // was queued again by a path with fewer turns.
+            observer.observe(SearchEvent::Superseded {
+                position,
+                cost: minimum_cost - heuristic(position) });
+            continue;
+        }
+        if is_goal(position) {
+            observer.observe(SearchEvent::ReachedGoal { position,
+                                                        cost: current_cost });
+            return Exploration { reached: Some(position), state, expanded };
+        }
+        observer.observe(SearchEvent::Expanded { position,
+                                                 cost: current_cost });
+        expanded += 1;
+
+        let current_turns = state.turns(position);
+        let previous = state.came_from(position);
+        for (next, step_cost) in graph.successors(position) {
+            let new_cost = current_cost + step_cost;
+            let new_turns = match tie_break {
+                TieBreak::FewerTurns if previous.is_some_and(|previous| {
+                    state.is_turn(previous, position, next)
+                }) => current_turns + 1,
+                _ => current_turns,
+            };
+            if let Some(existing_cost) = state.cost(next) {
+                let fewer_turns = tie_break == TieBreak::FewerTurns &&
+                    new_cost == existing_cost &&
+                    new_turns < state.turns(next) &&
+                    !state.is_closed(next);
+                if new_cost > existing_cost ||
+                        (new_cost == existing_cost && !fewer_turns) {
+                    continue;
+                }
+            }
+            state.reach(next, new_cost, Some(position));
+            state.set_turns(next, new_turns);
+            observer.observe(SearchEvent::CameFrom { position: next,
+                                                     from: position,
+                                                     cost: new_cost });
+            let entry = priority(&state, new_cost, new_turns, next);
+            frontier.push(entry);
+            observer.observe(SearchEvent::Pushed {
+                position: next,
+                priority: entry.minimum_cost,
+                frontier_len: frontier.len() });
+        }
+    }
+    Exploration { reached: None, state, expanded }
+}
+
+/// Run `explore` and turn the result into a `SearchResult`.
+pub(crate) fn best_first<G, S, F, H, O>(graph: &G,
+                                        state: S,
+                                        start: G::Node,
+                                        is_goal: F,
+                                        heuristic: H,
+                                        tie_break: TieBreak,
+                                        observer: &mut O)
+        -> SearchResult<G::Node>
+        where G: Graph + ?Sized,
+              S: SearchState<G::Node>,
+              F: Fn(G::Node) -> bool,
+              H: Fn(G::Node) -> usize,
+              O: SearchObserver<G::Node> + ?Sized {
+    let Exploration { reached, state, expanded } =
+        explore(graph, state, start, is_goal, heuristic, tie_break,
+                observer);
+    SearchResult {
+        path: reached.map(|goal| {
+            (state.path_to(goal), state.cost(goal).unwrap())
+        }),
+        expanded,
+    }
+}
+
+/// Breadth-first search from `start` to `goal`, telling `observer` about
+/// every step.
+fn breadth_first<O>(grid: &Grid,
+                    start: Position,
+                    goal: Position,
+                    movement: MovementModel,
+                    observer: &mut O) -> SearchResult
+        where O: SearchObserver + ?Sized {
+    // Each entry holds the number of steps to its cell.
+    let mut frontier = VecDeque::new();
+    frontier.push_back((start, 0));
+    observer.observe(SearchEvent::Pushed { position: start,
+                                           priority: 0,
+                                           frontier_len: 1 });
+
+    let mut cells = SearchCells::new(grid, false);
+    cells.reach(start, 0, None);
+    let mut expanded = 0;
+
+    while let Some((position, steps)) = frontier.pop_front() {
+        let current_cost = cells.cost(position).unwrap();
+        if position == goal {
+            observer.observe(SearchEvent::ReachedGoal { position,
+                                                        cost: current_cost });
+            return SearchResult { path: Some((cells.path_to(goal),
+                                              current_cost)),
+                                  expanded };
+        }
+        observer.observe(SearchEvent::Expanded { position,
+                                                 cost: current_cost });
+        expanded += 1;
+
+        for (next_position, step_cost) in
+                movement.neighbours(grid, position) {
+            if cells.cost(next_position).is_none() {
+                let new_cost = current_cost + step_cost;
+                cells.reach(next_position, new_cost, Some(position));
+                observer.observe(SearchEvent::CameFrom {
+                    position: next_position,
+                    from: position,
+                    cost: new_cost });
+                frontier.push_back((next_position, steps + 1));
+                observer.observe(SearchEvent::Pushed {
+                    position: next_position,
+                    priority: steps + 1,
+                    frontier_len: frontier.len() });
+            }
+        }
+    }
+    SearchResult { path: None, expanded }
+}
+
+impl Pathfinder for BreadthFirst {
+    fn search(&self,
+              grid: &Grid,
+              start: Position,
+              goal: Position,
+              movement: MovementModel) -> SearchResult {
+        breadth_first(grid, start, goal, movement, &mut ())
+    }
+
+    fn search_observed(&self,
+                       grid: &Grid,
+                       start: Position,
+                       goal: Position,
+                       movement: MovementModel,
+                       observer: &mut dyn SearchObserver) -> SearchResult {
+        breadth_first(grid, start, goal, movement, observer)
+    }
+}
+
+impl Dijkstra {
+    /// Like `search_observed` but breaking ties between equally promising
+    /// cells with `tie_break`.
+    pub fn search_with<O>(&self,
+                          grid: &Grid,
+                          start: Position,
+                          goal: Position,
+                          movement: MovementModel,
+                          tie_break: TieBreak,
+                          observer: &mut O) -> SearchResult
+            where O: SearchObserver + ?Sized {
+        best_first(&GridGraph::new(grid, movement),
+                   SearchCells::new(grid, tie_break == TieBreak::FewerTurns),
+                   start, |p| p == goal, |_| 0, tie_break, observer)
+    }
+}
+
+impl Pathfinder for Dijkstra {
+    fn search(&self,
+              grid: &Grid,
+              start: Position,
+              goal: Position,
+              movement: MovementModel) -> SearchResult {
+        self.search_with(grid, start, goal, movement, TieBreak::default(),
+                         &mut ())
+    }
+
+    fn search_observed(&self,
+                       grid: &Grid,
+                       start: Position,
+                       goal: Position,
+                       movement: MovementModel,
+                       observer: &mut dyn SearchObserver) -> SearchResult {
+        self.search_with(grid, start, goal, movement, TieBreak::default(),
+                         observer)
+    }
+}
+
+impl AStar {
+    /// Like `search_observed` but breaking ties between equally promising
+    /// cells with `tie_break`.
+    pub fn search_with<O>(&self,
+                          grid: &Grid,
+                          start: Position,
+                          goal: Position,
+                          movement: MovementModel,
+                          tie_break: TieBreak,
+                          observer: &mut O) -> SearchResult
+            where O: SearchObserver + ?Sized {
+        best_first(&GridGraph::new(grid, movement),
+                   SearchCells::new(grid, tie_break == TieBreak::FewerTurns),
+                   start, |p| p == goal, |p| movement.heuristic(p, goal),
+                   tie_break, observer)
+    }
+}
+
+impl Pathfinder for AStar {
+    fn search(&self,
+              grid: &Grid,
+              start: Position,
+              goal: Position,
+              movement: MovementModel) -> SearchResult {
+        self.search_with(grid, start, goal, movement, TieBreak::default(),
+                         &mut ())
+    }
+
+    fn search_observed(&self,
+                       grid: &Grid,
+                       start: Position,
+                       goal: Position,
+                       movement: MovementModel,
+                       observer: &mut dyn SearchObserver) -> SearchResult {
+        self.search_with(grid, start, goal, movement, TieBreak::default(),
+                         observer)
+    }
+}
+
+/// Bidirectional A* from `start` to `goal`, telling `observer` about every
+/// step.
+fn bidirectional<O>(grid: &Grid,
+                    start: Position,
+                    goal: Position,
+                    movement: MovementModel,
+                    observer: &mut O) -> SearchResult
+        where O: SearchObserver + ?Sized {
+    if start == goal {
+        observer.observe(SearchEvent::ReachedGoal { position: goal, cost: 0 });
+        return SearchResult { path: Some((vec![start], 0)), expanded: 0 };
+    }
+    // Index 0 searches forwards from the start and index 1 searches
+    // backwards from the goal, following steps in reverse.
+    let origins = [start, goal];
+    let heuristic = |side: usize, p: Position| {
+        movement.heuristic(p, origins[1 - side])
+    };
+    let mut frontiers = [BinaryHeap::new(), BinaryHeap::new()];
+    let mut cells = [SearchCells::new(grid, false),
+                     SearchCells::new(grid, false)];
+    for side in 0..2 {
+        let priority = heuristic(side, origins[side]);
+        frontiers[side].push(PositionPriority::new(priority, origins[side]));
+        cells[side].reach(origins[side], 0, None);
+        observer.observe(SearchEvent::Pushed { position: origins[side],
+                                               priority,
+                                               frontier_len: side + 1 });
+    }
+
+    // The cheapest complete path seen so far and where the searches met.
+    let mut best: Option<(usize, Position)> = None;
+    let mut expanded = 0;
+
+    while let (Some(forward), Some(backward)) =
+            (frontiers[0].peek(), frontiers[1].peek()) {
+        let (forward_min, backward_min) =
+            (forward.minimum_cost, backward.minimum_cost);
+        // Every path not yet found costs at least as much as the cheapest
+        // position on either frontier.
+        if let Some((best_cost, _)) = best {
+            if forward_min >= best_cost || backward_min >= best_cost {
+                break;
+            }
+        }
+        let side = if frontiers[0].len() <= frontiers[1].len() { 0 }
+                   else { 1 };
+
+        let PositionPriority { minimum_cost, position, .. } =
+            frontiers[side].pop().unwrap();
+        let current_cost = cells[side].cost(position).unwrap();
+        if minimum_cost > current_cost + heuristic(side, position) {
+            observer.observe(SearchEvent::Superseded {
+                position,
+                cost: minimum_cost - heuristic(side, position) });
+            continue;
+        }
+        observer.observe(SearchEvent::Expanded { position,
+                                                 cost: current_cost });
+        expanded += 1;
+
+        let steps = match side {
+            0 => movement.neighbours(grid, position),
+            _ => movement.reverse_neighbours(grid, position),
+        };
+        for (next_position, step_cost) in steps {
+            let new_cost = current_cost + step_cost;
+            if cells[side].cost(next_position)
+                    .is_some_and(|existing| new_cost >= existing) {
+                continue;
+            }
+            cells[side].reach(next_position, new_cost, Some(position));
+            observer.observe(SearchEvent::CameFrom { position: next_position,
+                                                     from: position,
+                                                     cost: new_cost });
+            let priority = new_cost + heuristic(side, next_position);
+            frontiers[side].push(PositionPriority::new(priority,
+                                                       next_position));
+            observer.observe(SearchEvent::Pushed {
+                position: next_position,
+                priority,
+                frontier_len: frontiers[0].len() + frontiers[1].len() });
+
+            if let Some(other_cost) = cells[1 - side].cost(next_position) {
+                let total = new_cost + other_cost;
+                if best.is_none_or(|(best_cost, _)| total < best_cost) {
+                    best = Some((total, next_position));
+                }
+            }
+        }
+    }
+
+    let path = best.map(|(total, meeting)| {
+        observer.observe(SearchEvent::ReachedGoal { position: goal,
+                                                    cost: total });
+        let mut path = cells[0].path_to(meeting);
+        let mut to_goal = cells[1].path_to(meeting);
+        to_goal.reverse();
+        path.extend(to_goal.into_iter().skip(1));
+        (path, total)
+    });
+    SearchResult { path, expanded }
+}
+
+impl Pathfinder for BidirectionalAStar {
+    fn search(&self,
+              grid: &Grid,
+              start: Position,
+              goal: Position,
+              movement: MovementModel) -> SearchResult {
+        bidirectional(grid, start, goal, movement, &mut ())
+    }
+
+    fn search_observed(&self,
+                       grid: &Grid,
+                       start: Position,
+                       goal: Position,
+                       movement: MovementModel,
+                       observer: &mut dyn SearchObserver) -> SearchResult {
+        bidirectional(grid, start, goal, movement, observer)
+    }
+}
+
+/// The direction, with each component in -1..=1, from `from` towards `to`.
+#[inline]
+fn direction(from: Position, to: Position) -> (isize, isize) {
+    let sign = |a: usize, b: usize| match b.cmp(&a) {
+        Ordering::Less => -1,
+        Ordering::Equal => 0,
+        Ordering::Greater => 1,
+    };
+    (sign(from.x, to.x), sign(from.y, to.y))
+}
+
+/// The state needed to find jump points in a particular grid.
+struct Jumper<'a> {
+    grid: &'a Grid<'a>,
+    goal: Position,
+}
+
+impl<'a> Jumper<'a> {
+    #[inline]
+    fn walkable(&self, x: isize, y: isize) -> bool {
+        if x < 0 || y < 0 {
+            return false;
+        }
+        self.grid.is_passable(Position { x: x as usize, y: y as usize })
+    }
+
+    /// Move from `from` in direction (dx, dy) until reaching a cell where the
+    /// path might need to turn (or the goal) and return it, or `None` if an
+    /// obstacle is reached first.
+    fn jump(&self, from: Position, (dx, dy): (isize, isize))
+            -> Option<Position> {
+        let (mut x, mut y) = (from.x as isize + dx, from.y as isize + dy);
+        loop {
+            if !self.walkable(x, y) {
+                return None;
+            }
+            let position = Position { x: x as usize, y: y as usize };
+            if position == self.goal {
+                return Some(position);
+            }
+            if dx != 0 && dy != 0 {
+                if self.jump(position, (dx, 0)).is_some() ||
+                   self.jump(position, (0, dy)).is_some() {
+                    return Some(position);
+                }
+            } else if dx != 0 {
+                // A forced neighbour: an obstacle behind and to the side has
+                // just ended so a path may need to turn here.
+                if (self.walkable(x, y - 1) && !self.walkable(x - dx, y - 1)) ||
+                   (self.walkable(x, y + 1) && !self.walkable(x - dx, y + 1)) {
+                    return Some(position);
+                }
+            } else if (self.walkable(x - 1, y) &&
+                       !self.walkable(x - 1, y - dy)) ||
+                      (self.walkable(x + 1, y) &&
+                       !self.walkable(x + 1, y - dy)) {
+                return Some(position);
+            }
+
+            // Diagonal moves may not cut corners.
+            if !self.walkable(x + dx, y) || !self.walkable(x, y + dy) {
+                return None;
+            }
+            x += dx;
+            y += dy;
+        }
+    }
+
+    /// The directions worth exploring from `position` given that it was
+    /// reached from `parent`.
+    fn pruned_directions(&self, position: Position, parent: Option<Position>)
+            -> Vec<(isize, isize)> {
+        let (x, y) = (position.x as isize, position.y as isize);
+        let (dx, dy) = match parent {
+            Some(parent) => direction(parent, position),
+            None => {
+                return MovementModel::EightWayNoCornerCutting
+                    .moves(self.grid, position)
+                    .into_iter()
+                    .map(|(p, _)| direction(position, p))
+                    .collect();
+            }
+        };
+
+        let mut directions = Vec::new();
+        if dx != 0 && dy != 0 {
+            let vertical = self.walkable(x, y + dy);
+            let horizontal = self.walkable(x + dx, y);
+            if vertical {
+                directions.push((0, dy));
+            }
+            if horizontal {
+                directions.push((dx, 0));
+            }
+            if vertical && horizontal {
+                directions.push((dx, dy));
+            }
+        } else if dx != 0 {
+            let ahead = self.walkable(x + dx, y);
+            for &side in [-1, 1].iter() {
+                if self.walkable(x, y + side) {
+                    directions.push((0, side));
+                    if ahead {
+                        directions.push((dx, side));
+                    }
+                }
+            }
+            if ahead {
+                directions.push((dx, 0));
+            }
+        } else {
+            let ahead = self.walkable(x, y + dy);
+            for &side in [-1, 1].iter() {
+                if self.walkable(x + side, y) {
+                    directions.push((side, 0));
+                    if ahead {
+                        directions.push((side, dy));
+                    }
+                }
+            }
+            if ahead {
+                directions.push((0, dy));
+            }
+        }
+        directions
+    }
+}
+
+/// Fill in the cells between consecutive jump points, which always lie on a
+/// straight or diagonal line.
+fn interpolate(jump_points: &[Position]) -> Vec<Position> {
+    let mut path = vec![jump_points[0]];
+    for pair in jump_points.windows(2) {
+        let (dx, dy) = direction(pair[0], pair[1]);
+        let mut current = pair[0];
+        while current != pair[1] {
+            current = Position { x: (current.x as isize + dx) as usize,
+                                 y: (current.y as isize + dy) as usize };
+            path.push(current);
+        }
+    }
+    path
+}
+
+/// Jump point search from `start` to `goal`, telling `observer` about every
+/// jump point.
+fn jump_point<O>(grid: &Grid,
+                 start: Position,
+                 goal: Position,
+                 observer: &mut O) -> SearchResult
+        where O: SearchObserver + ?Sized {
+    let movement = MovementModel::EightWayNoCornerCutting;
+    let jumper = Jumper { grid, goal };
+
+    let mut frontier = BinaryHeap::new();
+    let priority = movement.heuristic(start, goal);
+    frontier.push(PositionPriority::new(priority, start));
+    observer.observe(SearchEvent::Pushed { position: start,
+                                           priority,
+                                           frontier_len: 1 });
+
+    let mut cells = SearchCells::new(grid, false);
+    cells.reach(start, 0, None);
+    let mut expanded = 0;
+
+    while let Some(PositionPriority { minimum_cost, position, .. }) =
+            frontier.pop() {
+        let current_cost = cells.cost(position).unwrap();
+        let heuristic = movement.heuristic(position, goal);
+        if minimum_cost > current_cost + heuristic {
+            observer.observe(SearchEvent::Superseded {
+                position,
+                cost: minimum_cost - heuristic });
+            continue;
+        }
+        if position == goal {
+            let path = interpolate(&cells.path_to(goal));
+            let path_cost = path_cost(grid, &path, movement);
+            observer.observe(SearchEvent::ReachedGoal { position,
+                                                        cost: path_cost });
+            return SearchResult { path: Some((path, path_cost)), expanded };
+        }
+        observer.observe(SearchEvent::Expanded { position,
+                                                 cost: current_cost });
+        expanded += 1;
+
+        let parent = cells.came_from(position);
+        for d in jumper.pruned_directions(position, parent) {
+            let next_position = match jumper.jump(position, d) {
+                Some(p) => p,
+                None => continue,
+            };
+            // Jump points lie on straight or diagonal lines so the
+            // uniform-cost heuristic is exact between them.
+            let new_cost = current_cost +
+                movement.heuristic(position, next_position);
+            if cells.cost(next_position)
+                    .is_some_and(|existing| new_cost >= existing) {
+                continue;
+            }
+            cells.reach(next_position, new_cost, Some(position));
+            observer.observe(SearchEvent::CameFrom { position: next_position,
+                                                     from: position,
+                                                     cost: new_cost });
+            let priority = new_cost + movement.heuristic(next_position, goal);
+            frontier.push(PositionPriority::new(priority, next_position));
+            observer.observe(SearchEvent::Pushed {
+                position: next_position,
+                priority,
+                frontier_len: frontier.len() });
+        }
+    }
+    SearchResult { path: None, expanded }
+}
+
+impl Pathfinder for JumpPoint {
+    fn search(&self,
+              grid: &Grid,
+              start: Position,
+              goal: Position,
+              movement: MovementModel) -> SearchResult {
+        self.search_observed(grid, start, goal, movement, &mut ())
+    }
+
+    fn search_observed(&self,
+                       grid: &Grid,
+                       start: Position,
+                       goal: Position,
+                       movement: MovementModel,
+                       observer: &mut dyn SearchObserver) -> SearchResult {
+        if movement != MovementModel::EightWayNoCornerCutting {
+            return AStar.search_observed(grid, start, goal, movement,
+                                         observer);
+        }
+        jump_point(grid, start, goal, observer)
+    }
+}
+
+#[cfg(test)]
+use {terrain_cost, mazes};
+#[cfg(test)]
+use mazes::{random_grid, ALL_MODELS};
+
+#[test]
+fn optimal_pathfinders_agree_on_mazes() {
+    let pathfinders: Vec<Box<dyn Pathfinder>> =
+        vec![Box::new(Dijkstra), Box::new(AStar), Box::new(BidirectionalAStar)];
+    for &movement in ALL_MODELS.iter() {
+        for maze in mazes::solvable() {
+            let grid = maze.grid();
+            let expected = Dijkstra.search(&grid, maze.start, maze.goal,
+                                           movement);
+            let (_, expected_cost) = expected.path.unwrap();
+            for pathfinder in &pathfinders {
+                let (path, cost) = pathfinder.search(
+                    &grid, maze.start, maze.goal, movement).path.unwrap();
+                assert_eq!(expected_cost, cost);
+                assert_eq!(cost, path_cost(&grid, &path, movement));
+            }
+        }
+    }
+}
+
+#[test]
+fn every_tie_break_finds_the_cheapest_path() {
+    for seed in 0..20 {
+        let grid = random_grid(seed, 10, 8, 4);
+        let start = Position { x: 0, y: 0 };
+        let goal = Position { x: 9, y: 7 };
+        for &movement in ALL_MODELS.iter() {
+            let expected = Dijkstra.search(&grid, start, goal, movement);
+            for &tie_break in [TieBreak::LargerCost,
+                               TieBreak::Position,
+                               TieBreak::FewerTurns].iter() {
+                for &heuristic in [false, true].iter() {
+                    let result = match heuristic {
+                        true => AStar.search_with(&grid, start, goal,
+                                                  movement, tie_break,
+                                                  &mut ()),
+                        false => Dijkstra.search_with(&grid, start, goal,
+                                                      movement, tie_break,
+                                                      &mut ()),
+                    };
+                    assert_eq!(expected.path.as_ref().map(|p| p.1),
+                               result.path.as_ref().map(|p| p.1),
+                               "seed {} {:?} {:?}", seed, movement,
+                               tie_break);
+                    if let Some((path, cost)) = result.path {
+                        assert_eq!(cost, path_cost(&grid, &path, movement));
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn fewer_turns_straightens_paths() {
+    let grid = Grid::new(&[
+        vec![' ', ' ', ' ', ' ', '█', ' ', ' ', '█', ' '],
+        vec![' ', ' ', ' ', ' ', '█', '█', ' ', '█', ' '],
+        vec![' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' '],
+        vec![' ', ' ', ' ', ' ', ' ', '█', ' ', ' ', ' '],
+        vec![' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' '],
+        vec![' ', ' ', ' ', ' ', ' ', ' ', ' ', '█', ' '],
+        vec!['█', '█', '█', ' ', ' ', ' ', ' ', ' ', ' ']]).unwrap();
+    let start = Position { x: 0, y: 0 };
+    let goal = Position { x: 8, y: 6 };
+    let format = |tie_break| {
+        let (path, _) = AStar.search_with(&grid, start, goal,
+                                          MovementModel::FourWay, tie_break,
+                                          &mut ()).path.unwrap();
+        grid.format_path(&path).to_rows()
+            .into_iter()
+            .map(|row| row.into_iter().collect::<String>())
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(vec!["@   █  █ ",
+                    "o   ██ █ ",
+                    "o        ",
+                    "o    █   ",
+                    "o        ",
+                    "oooo   █ ",
+                    "███oooooX"],
+               format(TieBreak::LargerCost));
+    assert_eq!(vec!["@   █  █ ",
+                    "o   ██ █ ",
+                    "o        ",
+                    "o    █   ",
+                    "ooooooooo",
+                    "       █o",
+                    "███     X"],
+               format(TieBreak::FewerTurns));
+}
+
+#[test]
+fn optimal_pathfinders_agree_on_weighted_random_grids() {
+    let pathfinders: Vec<Box<dyn Pathfinder>> =
+        vec![Box::new(AStar), Box::new(BidirectionalAStar)];
+    for seed in 0..40 {
+        let grid = random_grid(seed, 12, 9, 4);
+        let start = Position { x: 0, y: 0 };
+        let goal = Position { x: 11, y: 8 };
+        for &movement in ALL_MODELS.iter() {
+            let expected = Dijkstra.search(&grid, start, goal, movement);
+            for pathfinder in &pathfinders {
+                let result = pathfinder.search(&grid, start, goal, movement);
+                assert_eq!(expected.path.as_ref().map(|p| p.1),
+                           result.path.as_ref().map(|p| p.1),
+                           "seed {} {:?}", seed, movement);
+            }
+        }
+    }
+}
+
+#[test]
+fn jump_point_matches_dijkstra_on_random_grids() {
+    let movement = MovementModel::EightWayNoCornerCutting;
+    for seed in 0..60 {
+        let grid = random_grid(seed, 15, 11, 3).with_passable(|ch| ch != '█');
+        let start = Position { x: 0, y: 0 };
+        let goal = Position { x: 14, y: 10 };
+        let expected = Dijkstra.search(&grid, start, goal, movement);
+        let result = JumpPoint.search(&grid, start, goal, movement);
+        assert_eq!(expected.path.as_ref().map(|p| p.1),
+                   result.path.as_ref().map(|p| p.1),
+                   "seed {}", seed);
+        if let Some((path, cost)) = result.path {
+            assert_eq!(cost, path_cost(&grid, &path, movement));
+        }
+    }
+}
+
+#[test]
+fn jump_point_matches_dijkstra_on_mazes() {
+    let movement = MovementModel::EightWayNoCornerCutting;
+    for maze in mazes::solvable() {
+        let grid = maze.grid();
+        let expected = Dijkstra.search(&grid, maze.start, maze.goal, movement);
+        let result = JumpPoint.search(&grid, maze.start, maze.goal, movement);
+        assert_eq!(expected.path.unwrap().1, result.path.unwrap().1);
+    }
+}
+
+#[test]
+fn breadth_first_finds_fewest_steps() {
+    for &movement in ALL_MODELS.iter() {
+        for maze in mazes::solvable() {
+            let grid = maze.grid();
+            let steps = grid.clone().with_passable(|ch| ch == ' ');
+            let (expected, _) = Dijkstra.search(
+                &steps, maze.start, maze.goal, movement).path.unwrap();
+            let (path, _) = BreadthFirst.search(
+                &grid, maze.start, maze.goal, movement).path.unwrap();
+            assert_eq!(expected.len(), path.len());
+        }
+    }
+}
+
+#[test]
+fn breadth_first_ignores_costs() {
+    let grid = Grid::new(&[vec![' ', '9', ' '],
+                           vec![' ', ' ', ' ']]).unwrap()
+        .with_cost(terrain_cost);
+    let start = Position { x: 0, y: 0 };
+    let goal = Position { x: 2, y: 0 };
+
+    let (path, cost) = BreadthFirst.search(
+        &grid, start, goal, MovementModel::FourWay).path.unwrap();
+    assert_eq!(3, path.len());
+    assert_eq!(10, cost);
+    let (_, cost) = AStar.search(
+        &grid, start, goal, MovementModel::FourWay).path.unwrap();
+    assert_eq!(4, cost);
+}
+
+#[test]
+fn every_pathfinder_reports_no_path() {
+    let maze = mazes::no_path();
+    let grid = maze.grid();
+    let pathfinders: Vec<Box<dyn Pathfinder>> =
+        vec![Box::new(BreadthFirst), Box::new(Dijkstra), Box::new(AStar),
+             Box::new(BidirectionalAStar), Box::new(JumpPoint)];
+    for pathfinder in pathfinders {
+        let result = pathfinder.search(&grid, maze.start, maze.goal,
+                                       MovementModel::EightWayNoCornerCutting);
+        assert_eq!(None, result.path);
+        assert!(result.expanded > 0);
+    }
+}
+
+#[test]
+fn every_pathfinder_handles_start_at_goal() {
+    let grid = Grid::filled(2, 1, ' ');
+    let start = Position { x: 1, y: 0 };
+    let pathfinders: Vec<Box<dyn Pathfinder>> =
+        vec![Box::new(BreadthFirst), Box::new(Dijkstra), Box::new(AStar),
+             Box::new(BidirectionalAStar), Box::new(JumpPoint)];
+    for pathfinder in pathfinders {
+        let result = pathfinder.search(&grid, start, start,
+                                       MovementModel::EightWayNoCornerCutting);
+        assert_eq!(Some((vec![start], 0)), result.path);
+    }
+}
+
+#[test]
+fn heuristics_reduce_expansions() {
+    let maze = mazes::open_field();
+    let grid = maze.grid();
+    let movement = MovementModel::EightWayNoCornerCutting;
+    let expansions = |pathfinder: &dyn Pathfinder| {
+        pathfinder.search(&grid, maze.start, maze.goal, movement).expanded
+    };
+    let dijkstra = expansions(&Dijkstra);
+    let a_star = expansions(&AStar);
+    let jump_point = expansions(&JumpPoint);
+    assert!(a_star < dijkstra);
+    assert!(jump_point < a_star);
+}
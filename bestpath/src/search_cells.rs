@@ -0,0 +1,244 @@
+//! Per-cell search state that is kept from one search to the next.
+//!
+//! A search only touches the cells that it reaches, but a map with a slot for
+//! every cell of the grid has to be allocated and cleared before it starts,
+//! which on a large grid costs far more than a short search. Instead each
+//! thread keeps the arrays of its finished searches, and every slot written
+//! is stamped with the generation of the search that wrote it, so slots left
+//! behind by earlier searches read as empty without being cleared.
+
+use std::cell::RefCell;
+use std::mem;
+
+use search::SearchState;
+use {Grid, Position};
+
+/// The most sets of arrays that a thread keeps for later searches.
+const POOL_SIZE: usize = 4;
+
+/// Stands for no cell in `came_from`.
+const NONE: usize = usize::MAX;
+
+#[derive(Default)]
+struct Arrays {
+    generation: u32,
+    // The generation of the search that last reached, and last closed, each
+    // cell. The other arrays only hold a value for a cell if it was reached
+    // by the current generation.
+    reached: Vec<u32>,
+    closed: Vec<u32>,
+    cost: Vec<usize>,
+    came_from: Vec<usize>,
+    // Left empty unless some search asks for it.
+    turns: Vec<usize>,
+}
+
+thread_local! {
+    static POOL: RefCell<Vec<Arrays>> = const { RefCell::new(Vec::new()) };
+}
+
+/// The cost of the cheapest path found to each cell of a `width` x `height`
+/// grid, the cell it was reached from and whether it has been expanded, and
+/// optionally the number of turns along that path.
+pub(crate) struct SearchCells {
+    width: usize,
+    height: usize,
+    track_turns: bool,
+    arrays: Arrays,
+}
+
+impl SearchCells {
+    /// State for a search of `grid` with no cells reached, which counts
+    /// turns if `track_turns` is true.
+    pub fn new(grid: &Grid, track_turns: bool) -> SearchCells {
+        SearchCells::with_size(grid.width(), grid.height(), track_turns)
+    }
+
+    /// State for a search of a `width` x `height` grid with no cells
+    /// reached, which counts turns if `track_turns` is true.
+    pub fn with_size(width: usize, height: usize, track_turns: bool)
+            -> SearchCells {
+        let len = width * height;
+        let mut arrays = POOL.try_with(|pool| {
+            let mut pool = pool.borrow_mut();
+            match pool.iter().position(|a| a.reached.len() >= len) {
+                Some(i) => pool.swap_remove(i),
+                None => pool.pop().unwrap_or_default(),
+            }
+        }).unwrap_or_default();
+        if arrays.reached.len() < len {
+            // Zeroed memory is only paged in once it is written, so a short
+            // search of a large grid stays cheap.
+            arrays = Arrays { generation: 0,
+                              reached: vec![0; len],
+                              closed: vec![0; len],
+                              cost: vec![0; len],
+                              came_from: vec![0; len],
+                              turns: Vec::new() };
+        }
+        if track_turns && arrays.turns.len() < len {
+            arrays.turns = vec![0; len];
+        }
+        arrays.generation = arrays.generation.wrapping_add(1);
+        if arrays.generation == 0 {
+            for stamp in arrays.reached.iter_mut()
+                    .chain(arrays.closed.iter_mut()) {
+                *stamp = 0;
+            }
+            arrays.generation = 1;
+        }
+        SearchCells { width, height, track_turns, arrays }
+    }
+
+    #[inline]
+    fn index_of(&self, pos: Position) -> Option<usize> {
+        if pos.x < self.width && pos.y < self.height {
+            Some(pos.y * self.width + pos.x)
+        } else {
+            None
+        }
+    }
+
+    /// The index of `pos` if this search has reached it.
+    #[inline]
+    fn reached_index(&self, pos: Position) -> Option<usize> {
+        self.index_of(pos)
+            .filter(|&i| self.arrays.reached[i] == self.arrays.generation)
+    }
+}
+
+impl SearchState<Position> for SearchCells {
+    #[inline]
+    fn cost(&self, pos: Position) -> Option<usize> {
+        self.reached_index(pos).map(|i| self.arrays.cost[i])
+    }
+
+    #[inline]
+    fn came_from(&self, pos: Position) -> Option<Position> {
+        self.reached_index(pos)
+            .map(|i| self.arrays.came_from[i])
+            .filter(|&from| from != NONE)
+            .map(|from| Position { x: from % self.width,
+                                   y: from / self.width })
+    }
+
+    /// # Panics
+    ///
+    /// Panics if `pos` is outside of the grid.
+    #[inline]
+    fn reach(&mut self, pos: Position, cost: usize, from: Option<Position>) {
+        let i = self.index_of(pos)
+            .unwrap_or_else(|| panic!("{:?} is outside of the grid", pos));
+        self.arrays.reached[i] = self.arrays.generation;
+        self.arrays.cost[i] = cost;
+        self.arrays.came_from[i] = from.map_or(NONE, |from| {
+            from.y * self.width + from.x
+        });
+        if self.track_turns {
+            self.arrays.turns[i] = 0;
+        }
+    }
+
+    /// # Panics
+    ///
+    /// Panics if `pos` is outside of the grid.
+    #[inline]
+    fn close(&mut self, pos: Position) -> bool {
+        let i = self.index_of(pos)
+            .unwrap_or_else(|| panic!("{:?} is outside of the grid", pos));
+        let newly = self.arrays.closed[i] != self.arrays.generation;
+        self.arrays.closed[i] = self.arrays.generation;
+        newly
+    }
+
+    #[inline]
+    fn is_closed(&self, pos: Position) -> bool {
+        self.index_of(pos)
+            .is_some_and(|i| self.arrays.closed[i] == self.arrays.generation)
+    }
+
+    /// Cells are identified by their position, comparing x and then y.
+    #[inline]
+    fn order(&self, pos: Position) -> [usize; 2] {
+        [pos.x, pos.y]
+    }
+
+    #[inline]
+    fn turns(&self, pos: Position) -> usize {
+        match self.reached_index(pos) {
+            Some(i) if self.track_turns => self.arrays.turns[i],
+            _ => 0,
+        }
+    }
+
+    #[inline]
+    fn set_turns(&mut self, pos: Position, turns: usize) {
+        if let Some(i) = self.reached_index(pos) {
+            if self.track_turns {
+                self.arrays.turns[i] = turns;
+            }
+        }
+    }
+
+    #[inline]
+    fn is_turn(&self, from: Position, via: Position, to: Position) -> bool {
+        let step = |a: Position, b: Position| {
+            (b.x as isize - a.x as isize, b.y as isize - a.y as isize)
+        };
+        step(from, via) != step(via, to)
+    }
+}
+
+impl Drop for SearchCells {
+    fn drop(&mut self) {
+        let arrays = mem::take(&mut self.arrays);
+        // The pool is gone if the thread is exiting, and then there is
+        // nothing to keep the arrays for.
+        let _ = POOL.try_with(|pool| {
+            let mut pool = pool.borrow_mut();
+            if pool.len() < POOL_SIZE {
+                pool.push(arrays);
+            }
+        });
+    }
+}
+
+#[test]
+fn reach_and_close() {
+    let mut cells = SearchCells::with_size(3, 2, false);
+    let (a, b) = (Position { x: 2, y: 1 }, Position { x: 0, y: 1 });
+    assert_eq!(None, cells.cost(a));
+    cells.reach(a, 4, None);
+    cells.reach(b, 5, Some(a));
+    assert_eq!(Some(5), cells.cost(b));
+    assert_eq!(Some(a), cells.came_from(b));
+    assert_eq!(None, cells.came_from(a));
+    assert_eq!(vec![a, b], cells.path_to(b));
+    assert!(cells.close(a));
+    assert!(!cells.close(a));
+    assert!(cells.is_closed(a) && !cells.is_closed(b));
+    assert_eq!(None, cells.cost(Position { x: 3, y: 0 }));
+}
+
+#[test]
+fn later_searches_start_empty() {
+    let p = Position { x: 1, y: 1 };
+    {
+        let mut cells = SearchCells::with_size(2, 2, true);
+        cells.reach(p, 3, None);
+        cells.set_turns(p, 2);
+        assert_eq!(2, cells.turns(p));
+        cells.close(p);
+    }
+    let cells = SearchCells::with_size(2, 2, true);
+    assert_eq!(None, cells.cost(p));
+    assert_eq!(0, cells.turns(p));
+    assert!(!cells.is_closed(p));
+}
+
+#[test]
+#[should_panic(expected = "outside of the grid")]
+fn reach_outside_panics() {
+    let mut cells = SearchCells::with_size(3, 2, false);
+    cells.reach(Position { x: 0, y: 2 }, 0, None);
+}
@@ -0,0 +1,413 @@
+//! Grids whose edges wrap around to the opposite edge and whose cells may be
+//! joined in pairs by portals.
+
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+
+use graph::NodeMap;
+use search::best_first;
+use {Graph, Grid, MovementModel, Position, SearchResult, TieBreak};
+
+/// Returned by `Topology::with_portals` when a letter doesn't appear exactly
+/// twice in the grid.
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub struct UnpairedPortalError {
+    /// The letter of the portal.
+    pub letter: char,
+    /// The number of cells with that letter.
+    pub count: usize,
+}
+
+impl fmt::Display for UnpairedPortalError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.count {
+            1 => write!(f, "portal '{}' has no partner", self.letter),
+            n => write!(f, "portal '{}' appears {} times, not twice",
+                        self.letter, n),
+        }
+    }
+}
+
+impl Error for UnpairedPortalError {}
+
+/// A grid seen as a graph in which paths may leave one edge and enter the
+/// opposite edge, and may step from a portal to its partner. Nothing wraps
+/// and there are no portals until added with `wrapping` and `with_portals`.
+///
+/// The heuristic allows for both, so A* still finds the cheapest path:
+/// it is the movement model's heuristic to the nearest copy of the goal
+/// when the grid is repeated in the directions that wrap, or, if that is
+/// further, to the nearest portal plus from the portal nearest the goal.
+///
+/// # Examples
+///
+/// ```
+/// use bestpath::{Grid, MovementModel, Position, Topology};
+///
+/// let grid = Grid::new(&[vec![' ', ' ', '█', ' ', ' ']]).unwrap();
+/// let topology = Topology::new(&grid, MovementModel::FourWay);
+/// let (start, goal) = (Position{x: 1, y: 0}, Position{x: 3, y: 0});
+/// assert_eq!(None, topology.search(start, goal).path);
+///
+/// let topology = topology.wrapping(true, false);
+/// let (path, cost) = topology.search(start, goal).path.unwrap();
+/// assert_eq!(vec![Position{x: 1, y: 0}, Position{x: 0, y: 0},
+///                 Position{x: 4, y: 0}, Position{x: 3, y: 0}],
+///            path);
+/// assert_eq!(3, cost);
+/// ```
+#[derive(Clone, Debug)]
+pub struct Topology<'c> {
+    grid: Grid<'c>,
+    movement: MovementModel,
+    wrap_x: bool,
+    wrap_y: bool,
+    // Each portal cell and the cell it leads to.
+    portals: HashMap<Position, Position>,
+    // The distance from each cell to the nearest portal, row by row, or
+    // nothing if there are no portals.
+    to_portal: Vec<usize>,
+}
+
+impl<'c> Topology<'c> {
+    /// Treat `grid` as a graph where `movement` gives the steps between
+    /// cells.
+    pub fn new(grid: &Grid<'c>, movement: MovementModel) -> Topology<'c> {
+        Topology { grid: grid.clone(),
+                   movement,
+                   wrap_x: false,
+                   wrap_y: false,
+                   portals: HashMap::new(),
+                   to_portal: Vec::new() }
+    }
+
+    /// Let paths step off the left and right edges onto the opposite edge if
+    /// `x`, and off the top and bottom edges if `y`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if wrapping `MovementModel::Hex` rows when the height of the
+    /// grid is odd, since the first and last rows would both be shifted the
+    /// same way.
+    pub fn wrapping(self, x: bool, y: bool) -> Topology<'c> {
+        assert!(!(y && self.movement == MovementModel::Hex &&
+                  self.grid.height() % 2 == 1),
+                "wrapping hex rows needs an even number of rows");
+        Topology { wrap_x: x, wrap_y: y, ..self }.measure_portals()
+    }
+
+    /// Join the two cells marked with each ASCII letter as a pair of
+    /// portals. Letters cost 1 to enter, and a path on a portal may step to
+    /// its partner for free.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bestpath::{Grid, MovementModel, Position, Topology};
+    ///
+    /// let grid = Grid::new(&[vec![' ', 'a', '█', ' ', ' '],
+    ///                        vec![' ', ' ', '█', 'a', ' ']]).unwrap();
+    /// let topology = Topology::new(&grid, MovementModel::FourWay)
+    ///     .with_portals()
+    ///     .unwrap();
+    /// assert_eq!(Some(Position{x: 3, y: 1}),
+    ///            topology.portal(Position{x: 1, y: 0}));
+    /// let (path, cost) = topology.search(Position{x: 0, y: 0},
+    ///                                    Position{x: 4, y: 0})
+    ///     .path.unwrap();
+    /// assert_eq!(5, path.len());
+    /// assert_eq!(3, cost);
+    /// ```
+    pub fn with_portals(self)
+            -> Result<Topology<'c>, UnpairedPortalError> {
+        let mut cells: Vec<(char, Vec<Position>)> = Vec::new();
+        for p in self.grid.positions() {
+            let ch = self.grid[p];
+            if !ch.is_ascii_alphabetic() {
+                continue;
+            }
+            match cells.iter_mut().find(|&&mut (letter, _)| letter == ch) {
+                Some(&mut (_, ref mut positions)) => positions.push(p),
+                None => cells.push((ch, vec![p])),
+            }
+        }
+        let mut portals = self.portals;
+        for &(letter, ref positions) in cells.iter() {
+            match positions[..] {
+                [a, b] => {
+                    portals.insert(a, b);
+                    portals.insert(b, a);
+                }
+                _ => return Err(UnpairedPortalError {
+                    letter,
+                    count: positions.len(),
+                }),
+            }
+        }
+        let cost = self.grid.cost_function();
+        let grid = self.grid.with_cost(move |ch| {
+            if ch.is_ascii_alphabetic() { Some(1) } else { cost(ch) }
+        });
+        Ok(Topology { grid, portals, ..self }.measure_portals())
+    }
+
+    /// Find the distance from each cell to the nearest portal, which depends
+    /// on the directions that wrap, so that `estimate` doesn't look through
+    /// every portal each time.
+    fn measure_portals(self) -> Topology<'c> {
+        let to_portal = self.grid.positions()
+            .filter_map(|pos| {
+                self.portals.keys().map(|&p| self.distance(pos, p)).min()
+            })
+            .collect();
+        Topology { to_portal, ..self }
+    }
+
+    /// The grid, with portals passable if there are any.
+    #[inline]
+    pub fn grid(&self) -> &Grid<'c> {
+        &self.grid
+    }
+
+    /// The cell that the portal at `pos` leads to, if there is one.
+    #[inline]
+    pub fn portal(&self, pos: Position) -> Option<Position> {
+        self.portals.get(&pos).cloned()
+    }
+
+    /// Find the cheapest path from `start` to `goal`. Steps across an edge
+    /// or through a portal join cells that aren't next to each other in the
+    /// grid; `Picture` shows where these jumps are.
+    pub fn search(&self, start: Position, goal: Position) -> SearchResult {
+        // The distance from the portals to the goal is the same for every
+        // cell, so is only found once.
+        let from_portal = self.portal_to_goal(goal);
+        best_first(self, NodeMap::new(), start, |p| p == goal,
+                   |p| self.estimate(p, goal, from_portal),
+                   TieBreak::default(), &mut ())
+    }
+
+    /// The cell a step of `(dx, dy)` from `pos` lands on, wrapping around
+    /// the edges that wrap.
+    fn offset(&self, pos: Position, (dx, dy): (isize, isize))
+            -> Option<Position> {
+        let (width, height) = (self.grid.width() as isize,
+                               self.grid.height() as isize);
+        let (mut x, mut y) = (pos.x as isize + dx, pos.y as isize + dy);
+        if self.wrap_x {
+            x = x.rem_euclid(width);
+        }
+        if self.wrap_y {
+            y = y.rem_euclid(height);
+        }
+        if x < 0 || y < 0 || x >= width || y >= height {
+            return None;
+        }
+        Some(Position { x: x as usize, y: y as usize })
+    }
+
+    /// The movement model's heuristic from `from` to the nearest copy of
+    /// `to` when the grid is repeated in the directions that wrap.
+    fn distance(&self, from: Position, to: Position) -> usize {
+        let (width, height) = (self.grid.width() as isize,
+                               self.grid.height() as isize);
+        let shifts = |wraps: bool, size: isize| {
+            if wraps { vec![-size, 0, size] } else { vec![0] }
+        };
+        let from = (from.x as isize, from.y as isize);
+        let mut nearest = usize::MAX;
+        for &sx in shifts(self.wrap_x, width).iter() {
+            for &sy in shifts(self.wrap_y, height).iter() {
+                let to = (to.x as isize + sx, to.y as isize + sy);
+                nearest = nearest.min(self.movement
+                                          .heuristic_between(from, to));
+            }
+        }
+        nearest
+    }
+
+    /// The distance from the portal nearest `goal` to it, if there are any
+    /// portals.
+    fn portal_to_goal(&self, goal: Position) -> Option<usize> {
+        self.portals.keys().map(|&p| self.distance(p, goal)).min()
+    }
+
+    /// The heuristic from `pos` to `goal`, given its `portal_to_goal`.
+    fn estimate(&self, pos: Position, goal: Position,
+                from_portal: Option<usize>) -> usize {
+        let direct = self.distance(pos, goal);
+        // Any path through portals walks to the first portal it enters and
+        // from the last one it leaves.
+        let to_portal = self.to_portal.get(pos.y * self.grid.width() + pos.x);
+        match (to_portal, from_portal) {
+            (Some(&to), Some(from)) => direct.min(to + from),
+            _ => direct,
+        }
+    }
+}
+
+impl<'c> Graph for Topology<'c> {
+    type Node = Position;
+
+    fn successors(&self, pos: Position) -> Vec<(Position, usize)> {
+        let mut next: Vec<(Position, usize)> = self.movement
+            .moves_with(&self.grid, pos, |p, d| self.offset(p, d))
+            .into_iter()
+            .filter_map(|(p, multiplier)| {
+                self.grid.cost(p).map(|c| (p, c * multiplier))
+            })
+            .collect();
+        if let Some(partner) = self.portal(pos) {
+            next.push((partner, 0));
+        }
+        next
+    }
+
+    fn heuristic(&self, pos: Position, goal: Position) -> usize {
+        self.estimate(pos, goal, self.portal_to_goal(goal))
+    }
+}
+
+#[cfg(test)]
+use mazes::{random_grid, ALL_MODELS};
+#[cfg(test)]
+use {AStar, Pathfinder};
+
+/// A random grid with two pairs of portals.
+#[cfg(test)]
+fn random_portals(seed: u64) -> Grid<'static> {
+    let mut grid = random_grid(seed, 12, 10, 3);
+    grid[Position { x: 1, y: 8 }] = 'a';
+    grid[Position { x: 10, y: 1 }] = 'a';
+    grid[Position { x: 6, y: 0 }] = 'B';
+    grid[Position { x: 5, y: 9 }] = 'B';
+    grid
+}
+
+#[test]
+fn without_wrapping_or_portals_matches_grid_search() {
+    for seed in 0..4 {
+        let grid = random_grid(seed, 12, 10, 3);
+        for &movement in ALL_MODELS.iter() {
+            let topology = Topology::new(&grid, movement);
+            let (start, goal) = (Position { x: 0, y: 0 },
+                                 Position { x: 11, y: 9 });
+            let expected = AStar.search(&grid, start, goal, movement);
+            assert_eq!(expected.path.map(|(_, cost)| cost),
+                       topology.search(start, goal).path.map(|(_, c)| c));
+        }
+    }
+}
+
+#[test]
+fn heuristic_never_overestimates() {
+    for seed in 0..2 {
+        let grid = random_portals(seed);
+        for &movement in ALL_MODELS.iter() {
+            for &(x, y) in [(false, false), (true, false), (false, true),
+                            (true, true)].iter() {
+                let topology = Topology::new(&grid, movement)
+                    .wrapping(x, y)
+                    .with_portals()
+                    .unwrap();
+                let goal = Position { x: 7, y: 4 };
+                for start in grid.positions() {
+                    let dijkstra = best_first(&topology, NodeMap::new(),
+                                              start, |p| p == goal, |_| 0,
+                                              TieBreak::default(), &mut ());
+                    let found = topology.search(start, goal);
+                    let context = format!("seed {} {:?} wrap {:?} {:?}",
+                                          seed, movement, (x, y), start);
+                    assert_eq!(dijkstra.path.as_ref().map(|&(_, c)| c),
+                               found.path.map(|(_, c)| c),
+                               "{}", context);
+                    if let Some((_, cost)) = dijkstra.path {
+                        assert!(topology.heuristic(start, goal) <= cost,
+                                "{}", context);
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn wrapping_steps_across_edges() {
+    let grid = Grid::new(&[vec![' ', '█', ' '],
+                           vec!['█', '█', '█'],
+                           vec![' ', '█', ' ']]).unwrap();
+    let corners = [Position { x: 0, y: 0 }, Position { x: 2, y: 0 },
+                   Position { x: 0, y: 2 }, Position { x: 2, y: 2 }];
+    let topology = Topology::new(&grid, MovementModel::FourWay)
+        .wrapping(true, true);
+    for &a in corners.iter() {
+        for &b in corners.iter() {
+            let cost = topology.search(a, b).path.unwrap().1;
+            assert_eq!(a.x.abs_diff(b.x) / 2 + a.y.abs_diff(b.y) / 2, cost);
+        }
+    }
+    let eight_way = Topology::new(&grid, MovementModel::EightWay)
+        .wrapping(true, true);
+    assert_eq!(Some((vec![corners[0], corners[3]], 14)),
+               eight_way.search(corners[0], corners[3]).path);
+    let rows_only = Topology::new(&grid, MovementModel::FourWay)
+        .wrapping(false, true);
+    assert_eq!(None, rows_only.search(corners[0], corners[1]).path);
+}
+
+#[test]
+fn no_corner_cutting_checks_wrapped_corners() {
+    let mut grid = Grid::filled(3, 2, ' ');
+    let (start, goal) = (Position { x: 0, y: 0 }, Position { x: 2, y: 1 });
+    let search = |grid: &Grid, movement| {
+        Topology::new(grid, movement).wrapping(true, false)
+            .search(start, goal).path.unwrap().1
+    };
+    assert_eq!(14, search(&grid, MovementModel::EightWayNoCornerCutting));
+    grid[Position { x: 2, y: 0 }] = '█';
+    assert_eq!(14, search(&grid, MovementModel::EightWay));
+    assert_eq!(20, search(&grid, MovementModel::EightWayNoCornerCutting));
+}
+
+#[test]
+fn portals_are_found_in_pairs() {
+    let grid = Grid::new(&[vec!['a', ' ', 'b', 'a', 'b', 'c']]).unwrap();
+    let error = Topology::new(&grid, MovementModel::FourWay)
+        .with_portals()
+        .unwrap_err();
+    assert_eq!(UnpairedPortalError { letter: 'c', count: 1 }, error);
+    assert_eq!("portal 'c' has no partner", error.to_string());
+
+    let grid = Grid::new(&[vec!['a', 'a', 'a']]).unwrap();
+    assert_eq!("portal 'a' appears 3 times, not twice",
+               Topology::new(&grid, MovementModel::FourWay)
+                   .with_portals()
+                   .unwrap_err()
+                   .to_string());
+}
+
+#[test]
+fn portals_jump_between_regions() {
+    let grid = Grid::new(&[vec![' ', ' ', '█', ' ', ' '],
+                           vec!['Z', ' ', '█', ' ', ' '],
+                           vec![' ', ' ', '█', ' ', 'Z']]).unwrap();
+    let topology = Topology::new(&grid, MovementModel::FourWay)
+        .with_portals()
+        .unwrap();
+    let (path, cost) = topology.search(Position { x: 1, y: 0 },
+                                       Position { x: 3, y: 0 })
+        .path.unwrap();
+    assert_eq!(5, cost);
+    let jump = path.windows(2)
+        .position(|w| w[0] == Position { x: 0, y: 1 })
+        .unwrap();
+    assert_eq!(Position { x: 4, y: 2 }, path[jump + 1]);
+}
+
+#[test]
+#[should_panic(expected = "even number of rows")]
+fn hex_rows_only_wrap_in_pairs() {
+    let grid = Grid::filled(4, 3, ' ');
+    Topology::new(&grid, MovementModel::Hex).wrapping(false, true);
+}
@@ -0,0 +1,345 @@
+//! Watching a search as it runs, to count the work that it does or to see
+//! which cells it explored.
+
+use {Grid, Position};
+
+/// The character used by `Trace::format` for cells that the search expanded
+/// but which aren't on the path.
+pub const EXPLORED_MARKER: char = '░';
+
+/// A single step of a best-first search through a grid, or through a graph
+/// whose nodes are of type `N`.
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum SearchEvent<N = Position> {
+    /// `position` was added to the frontier with the given priority (the cost
+    /// so far plus the heuristic), leaving `frontier_len` entries in it.
+    Pushed { position: N, priority: usize, frontier_len: usize },
+    /// `position` was removed from the frontier and its neighbours are about
+    /// to be examined. `cost` is the cost of the cheapest path to it.
+    Expanded { position: N, cost: usize },
+    /// `position` was removed from the frontier and is a goal, so the search
+    /// is over.
+    ReachedGoal { position: N, cost: usize },
+    /// A cheaper path to `position` was found, going through `from` and
+    /// costing `cost`.
+    CameFrom { position: N, from: N, cost: usize },
+    /// An entry for `position`, pushed when the path to it cost `cost`, was
+    /// removed from the frontier and skipped, because a cheaper path to it
+    /// was found after it was pushed or it had already been expanded.
+    Superseded { position: N, cost: usize },
+}
+
+impl<N> SearchEvent<N> {
+    /// The same event with every node replaced by `f` of it.
+    pub(crate) fn map<M, F>(self, f: F) -> SearchEvent<M>
+            where F: Fn(N) -> M {
+        match self {
+            SearchEvent::Pushed { position, priority, frontier_len } =>
+                SearchEvent::Pushed { position: f(position),
+                                      priority,
+                                      frontier_len },
+            SearchEvent::Expanded { position, cost } =>
+                SearchEvent::Expanded { position: f(position), cost },
+            SearchEvent::ReachedGoal { position, cost } =>
+                SearchEvent::ReachedGoal { position: f(position), cost },
+            SearchEvent::CameFrom { position, from, cost } =>
+                SearchEvent::CameFrom { position: f(position),
+                                        from: f(from),
+                                        cost },
+            SearchEvent::Superseded { position, cost } =>
+                SearchEvent::Superseded { position: f(position), cost },
+        }
+    }
+}
+
+/// Something that is told about each step of a search, as it happens.
+///
+/// Every pathfinder can be observed with `Pathfinder::search_observed`, and
+/// so can `Hierarchy::search_observed`, `AStar::search_bounded_observed`,
+/// `AStar::search_graph_with` and `flow_field_observed`. The other searches,
+/// such as those of `Topology`, `KeyMaze` and `Replanner`, can't be
+/// observed.
+pub trait SearchObserver<N = Position> {
+    fn observe(&mut self, event: SearchEvent<N>);
+}
+
+/// Passes on the events of a search that is part of a larger one, with its
+/// nodes turned into positions by `position`. Its `ReachedGoal` events are
+/// dropped, since only the larger search knows when its goal is reached.
+pub(crate) struct Part<'o, F, O: ?Sized + 'o> {
+    pub position: F,
+    pub observer: &'o mut O,
+}
+
+impl<N, F, O> SearchObserver<N> for Part<'_, F, O>
+        where F: Fn(N) -> Position,
+              O: SearchObserver + ?Sized {
+    fn observe(&mut self, event: SearchEvent<N>) {
+        if let SearchEvent::ReachedGoal { .. } = event {
+            return;
+        }
+        self.observer.observe(event.map(&self.position));
+    }
+}
+
+/// Ignores every event.
+impl<N> SearchObserver<N> for () {
+    #[inline]
+    fn observe(&mut self, _event: SearchEvent<N>) {}
+}
+
+/// Records every event in order.
+impl<N> SearchObserver<N> for Vec<SearchEvent<N>> {
+    fn observe(&mut self, event: SearchEvent<N>) {
+        self.push(event);
+    }
+}
+
+/// Counts of the work done by a search.
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug, Default)]
+pub struct SearchStats {
+    /// The number of positions removed from the frontier and expanded. The
+    /// same as `SearchResult::expanded`.
+    pub expanded: usize,
+    /// The number of entries added to the frontier.
+    pub pushed: usize,
+    /// The largest number of entries in the frontier at one time.
+    pub max_frontier: usize,
+    /// The number of entries removed from the frontier and skipped because
+    /// they had been superseded.
+    pub superseded: usize,
+}
+
+impl<N> SearchObserver<N> for SearchStats {
+    fn observe(&mut self, event: SearchEvent<N>) {
+        match event {
+            SearchEvent::Pushed { frontier_len, .. } => {
+                self.pushed += 1;
+                self.max_frontier = self.max_frontier.max(frontier_len);
+            }
+            SearchEvent::Expanded { .. } => self.expanded += 1,
+            SearchEvent::Superseded { .. } => self.superseded += 1,
+            SearchEvent::ReachedGoal { .. } |
+            SearchEvent::CameFrom { .. } => {}
+        }
+    }
+}
+
+/// Records the events of a search along with its `SearchStats`.
+///
+/// # Examples
+///
+/// ```
+/// use bestpath::{AStar, Grid, MovementModel, Pathfinder, Position, Trace};
+///
+/// let grid = Grid::new(&[vec![' ', ' ', ' ', ' ', ' '],
+///                        vec![' ', '█', '█', '█', ' '],
+///                        vec![' ', ' ', ' ', '█', ' '],
+///                        vec![' ', ' ', ' ', '█', ' ']]).unwrap();
+/// let mut trace = Trace::default();
+/// let result = AStar.search_observed(&grid,
+///                                    Position{x: 2, y: 2},
+///                                    Position{x: 4, y: 2},
+///                                    MovementModel::FourWay,
+///                                    &mut trace);
+/// let (path, _) = result.path.unwrap();
+/// assert_eq!("ooooo\n\
+///             o███o\n\
+///             oo@█X\n\
+///             ░░░█ \n",
+///            trace.format(&grid, Some(&path)).to_string());
+/// ```
+#[derive(Clone, Eq, PartialEq, Debug, Default)]
+pub struct Trace {
+    pub events: Vec<SearchEvent>,
+    pub stats: SearchStats,
+}
+
+impl SearchObserver for Trace {
+    fn observe(&mut self, event: SearchEvent) {
+        self.stats.observe(event);
+        self.events.push(event);
+    }
+}
+
+impl Trace {
+    /// The positions that were expanded, in the order that they were
+    /// expanded.
+    pub fn expanded(&self) -> Vec<Position> {
+        self.events
+            .iter()
+            .filter_map(|event| match *event {
+                SearchEvent::Expanded { position, .. } => Some(position),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Generate a copy of `grid` with every expanded cell shaded with
+    /// `EXPLORED_MARKER` and then `path`, if any, drawn over it as by
+    /// `Grid::format_path`.
+    pub fn format<'c>(&self, grid: &Grid<'c>, path: Option<&[Position]>)
+            -> Grid<'c> {
+        let mut m = grid.clone();
+        for p in self.expanded() {
+            m[p] = EXPLORED_MARKER;
+        }
+        match path {
+            Some(path) => m.format_path(path),
+            None => m,
+        }
+    }
+}
+
+#[cfg(test)]
+use mazes::{random_grid, ALL_MODELS};
+#[cfg(test)]
+use {mazes, AStar, BidirectionalAStar, BreadthFirst, Budget, Dijkstra,
+     Hierarchy, JumpPoint, MovementModel, Pathfinder};
+
+#[test]
+fn stats_match_search_result() {
+    for maze in mazes::solvable().into_iter().chain(Some(mazes::no_path())) {
+        let grid = maze.grid();
+        let mut stats = SearchStats::default();
+        let result = AStar.search_observed(&grid, maze.start, maze.goal,
+                                           MovementModel::FourWay,
+                                           &mut stats);
+        assert_eq!(result.expanded, stats.expanded);
+        assert!(stats.pushed > stats.expanded ||
+                result.path.is_none());
+        assert!(stats.max_frontier >= 1);
+        assert!(stats.max_frontier <= stats.pushed);
+    }
+}
+
+#[test]
+fn every_search_is_observed() {
+    let pathfinders: Vec<Box<dyn Pathfinder>> =
+        vec![Box::new(BreadthFirst), Box::new(Dijkstra), Box::new(AStar),
+             Box::new(BidirectionalAStar), Box::new(JumpPoint)];
+    for maze in mazes::solvable().into_iter().chain(Some(mazes::no_path())) {
+        let grid = maze.grid();
+        for &movement in ALL_MODELS.iter() {
+            for pathfinder in pathfinders.iter() {
+                let mut stats = SearchStats::default();
+                let result = pathfinder.search_observed(
+                    &grid, maze.start, maze.goal, movement, &mut stats);
+                assert_eq!(pathfinder.search(&grid, maze.start, maze.goal,
+                                             movement),
+                           result);
+                assert_eq!(result.expanded, stats.expanded);
+            }
+
+            let hierarchy = Hierarchy::new(&grid, 4, movement);
+            let mut events: Vec<SearchEvent> = Vec::new();
+            let result = hierarchy.search_observed(&grid, maze.start,
+                                                   maze.goal, &mut events);
+            assert_eq!(hierarchy.search(&grid, maze.start, maze.goal),
+                       result);
+            let expanded = events.iter()
+                .filter(|e| matches!(e, SearchEvent::Expanded { .. }))
+                .count();
+            assert_eq!(result.expanded, expanded);
+            let reached: Vec<&SearchEvent> = events.iter()
+                .filter(|e| matches!(e, SearchEvent::ReachedGoal { .. }))
+                .collect();
+            let expected = result.path.map(|(_, cost)| {
+                SearchEvent::ReachedGoal { position: maze.goal, cost }
+            });
+            assert_eq!(expected.iter().collect::<Vec<_>>(), reached);
+
+            for &max in [0, 5, 1000].iter() {
+                let budget = Budget::default().with_max_expansions(max);
+                let mut stats = SearchStats::default();
+                let result = AStar.search_bounded_observed(
+                    &grid, maze.start, maze.goal, movement, budget,
+                    &mut stats);
+                assert_eq!(result.expanded, stats.expanded);
+            }
+        }
+    }
+}
+
+#[test]
+fn every_frontier_entry_is_expanded_or_superseded() {
+    let mut superseded = 0;
+    for seed in 0..4 {
+        let mut grid = random_grid(seed, 20, 15, 5);
+        let goal = Position { x: 19, y: 14 };
+        // Walling off the goal makes the search empty its frontier.
+        grid[goal] = '█';
+        let start = Position { x: 0, y: 0 };
+        grid[start] = ' ';
+        for &movement in ALL_MODELS.iter() {
+            let mut stats = SearchStats::default();
+            let result = Dijkstra.search_observed(&grid, start, goal,
+                                                  movement, &mut stats);
+            assert_eq!(None, result.path);
+            assert_eq!(stats.pushed, stats.expanded + stats.superseded);
+            superseded += stats.superseded;
+        }
+    }
+    assert!(superseded > 0);
+}
+
+#[test]
+fn came_from_events_rebuild_the_path() {
+    for maze in mazes::solvable() {
+        let grid = maze.grid();
+        let mut events: Vec<SearchEvent> = Vec::new();
+        let (path, cost) = Dijkstra.search_observed(
+            &grid, maze.start, maze.goal, MovementModel::EightWay,
+            &mut events).path.unwrap();
+
+        let mut came_from = ::std::collections::HashMap::new();
+        for event in &events {
+            if let SearchEvent::CameFrom { position, from, .. } = *event {
+                came_from.insert(position, from);
+            }
+        }
+        let mut rebuilt = vec![maze.goal];
+        while *rebuilt.last().unwrap() != maze.start {
+            rebuilt.push(came_from[rebuilt.last().unwrap()]);
+        }
+        rebuilt.reverse();
+        assert_eq!(path, rebuilt);
+        assert_eq!(Some(&SearchEvent::ReachedGoal { position: maze.goal,
+                                                    cost }),
+                   events.last());
+    }
+}
+
+#[test]
+fn expansions_are_in_cost_order_for_dijkstra() {
+    let maze = mazes::multiple_complex_paths();
+    let mut events: Vec<SearchEvent> = Vec::new();
+    Dijkstra.search_observed(&maze.grid(), maze.start, maze.goal,
+                             MovementModel::FourWay, &mut events);
+    let costs: Vec<usize> = events.iter()
+        .filter_map(|event| match *event {
+            SearchEvent::Expanded { cost, .. } => Some(cost),
+            _ => None,
+        })
+        .collect();
+    assert!(costs.windows(2).all(|w| w[0] <= w[1]));
+}
+
+#[test]
+fn format_shades_explored_cells() {
+    let maze = mazes::no_path();
+    let grid = maze.grid();
+    let mut trace = Trace::default();
+    AStar.search_observed(&grid, maze.start, maze.goal,
+                          MovementModel::FourWay, &mut trace);
+    assert_eq!(trace.expanded().len(), trace.stats.expanded);
+    assert_eq!(vec!["░░█    ",
+                    "██  ██ ",
+                    " █ ██ █",
+                    "   █ █ ",
+                    "█    █ ",
+                    " ████  ",
+                    "       "],
+               trace.format(&grid, None).to_string().lines()
+                   .collect::<Vec<_>>());
+}
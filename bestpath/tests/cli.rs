@@ -0,0 +1,35 @@
+//! Runs the `bestpath` command line tool on small maze files.
+
+use std::env;
+use std::fs;
+use std::process::{Command, Output};
+
+/// Runs `bestpath` on a maze file containing `maze`.
+fn run(name: &str, maze: &str) -> Output {
+    let path = env::temp_dir().join(format!("bestpath-{}-{}.txt", name,
+                                            std::process::id()));
+    fs::write(&path, maze).unwrap();
+    let output = Command::new(env!("CARGO_BIN_EXE_bestpath"))
+        .arg(&path)
+        .output()
+        .unwrap();
+    fs::remove_file(&path).unwrap();
+    output
+}
+
+#[test]
+fn prints_the_path() {
+    let output = run("path", "@ X\n");
+    assert!(output.status.success());
+    assert_eq!("@oX\nlength: 2\ncost: 2\n",
+               String::from_utf8(output.stdout).unwrap());
+}
+
+#[test]
+fn no_path_keeps_the_start_and_goal() {
+    let output = run("no-path", "@ █X\n");
+    assert_eq!(Some(1), output.status.code());
+    assert_eq!("@░█X\n", String::from_utf8(output.stdout).unwrap());
+    assert!(String::from_utf8(output.stderr).unwrap()
+                .contains("no path from (0, 0) to (3, 0)"));
+}
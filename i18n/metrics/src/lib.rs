@@ -0,0 +1,372 @@
+//! Counters and timing histograms shared by the i18n matchers.
+//!
+//! Each matcher ("naive", "sets", "prefix") reports through a `Recorder` under
+//! its own backend name, so a single `Metrics` can hold a per-backend
+//! breakdown of how long loading the dictionary, indexing it, parsing
+//! patterns, looking up words and formatting results took.
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::time::{Duration, Instant};
+
+/// The stages of answering a query that are timed separately.
+#[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug)]
+pub enum Phase {
+    /// Reading the dictionary.
+    Load,
+    /// Building any lookup structures, such as tries, from the dictionary.
+    Index,
+    /// Turning a pattern like "i18n" into the matcher's representation.
+    Parse,
+    /// Finding the words that match a parsed pattern.
+    Lookup,
+    /// Sorting and printing the matching words.
+    Format,
+}
+
+impl Phase {
+    /// Returns the lowercase name of the phase e.g. "lookup".
+    pub fn name(&self) -> &'static str {
+        match *self {
+            Phase::Load => "load",
+            Phase::Index => "index",
+            Phase::Parse => "parse",
+            Phase::Lookup => "lookup",
+            Phase::Format => "format",
+        }
+    }
+}
+
+/// Somewhere to send counts and timings. Implement this to route measurements
+/// elsewhere (or nowhere).
+pub trait Recorder {
+    /// Add `n` to the named counter.
+    fn increment(&mut self, counter: &str, n: u64);
+
+    /// Record that a single run of `phase` took `elapsed`.
+    fn record(&mut self, phase: Phase, elapsed: Duration);
+}
+
+/// Run `f`, record how long it took under `phase` and return its result.
+///
+/// # Examples
+///
+/// ```
+/// use metrics::{time, Metrics, Phase};
+///
+/// let mut metrics = Metrics::new();
+/// let n = time(metrics.backend("naive"), Phase::Parse, || 2 + 2);
+/// assert_eq!(4, n);
+/// assert_eq!(1, metrics.backend("naive").histogram(Phase::Parse).count());
+/// ```
+#[inline]
+pub fn time<R, T, F>(recorder: &mut R, phase: Phase, f: F) -> T
+        where R: Recorder + ?Sized, F: FnOnce() -> T {
+    let start = Instant::now();
+    let result = f();
+    recorder.record(phase, start.elapsed());
+    result
+}
+
+// Bucket `i` holds durations in [2^(i-1), 2^i) microseconds, with bucket 0
+// holding everything under a microsecond. The last bucket is unbounded.
+const NUM_BUCKETS: usize = 32;
+
+/// A log-scaled histogram of durations.
+#[derive(Clone, Debug)]
+pub struct Histogram {
+    buckets: [u64; NUM_BUCKETS],
+    count: u64,
+    total: Duration,
+    min: Duration,
+    max: Duration,
+}
+
+impl Default for Histogram {
+    fn default() -> Histogram {
+        Histogram::new()
+    }
+}
+
+impl Histogram {
+    /// Constructs a new, empty `Histogram`.
+    #[inline]
+    pub fn new() -> Histogram {
+        Histogram {
+            buckets: [0; NUM_BUCKETS],
+            count: 0,
+            total: Duration::new(0, 0),
+            min: Duration::new(0, 0),
+            max: Duration::new(0, 0),
+        }
+    }
+
+    /// Add a single duration to the histogram.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use metrics::Histogram;
+    /// use std::time::Duration;
+    ///
+    /// let mut h = Histogram::new();
+    /// h.record(Duration::from_micros(3));
+    /// h.record(Duration::from_micros(5));
+    /// assert_eq!(2, h.count());
+    /// assert_eq!(Some(Duration::from_micros(4)), h.mean());
+    /// ```
+    #[inline]
+    pub fn record(&mut self, elapsed: Duration) {
+        let micros = elapsed.as_micros();
+        let bucket = if micros == 0 {
+            0
+        } else {
+            // The number of bits needed to represent `micros`.
+            (128 - micros.leading_zeros()) as usize
+        };
+        self.buckets[bucket.min(NUM_BUCKETS - 1)] += 1;
+
+        if self.count == 0 || elapsed < self.min {
+            self.min = elapsed;
+        }
+        if elapsed > self.max {
+            self.max = elapsed;
+        }
+        self.count += 1;
+        self.total += elapsed;
+    }
+
+    /// Returns the number of durations recorded.
+    #[inline]
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+
+    /// Returns the sum of all durations recorded.
+    #[inline]
+    pub fn total(&self) -> Duration {
+        self.total
+    }
+
+    /// Returns the shortest duration recorded, or `None` if it is empty.
+    #[inline]
+    pub fn min(&self) -> Option<Duration> {
+        if self.count == 0 { None } else { Some(self.min) }
+    }
+
+    /// Returns the longest duration recorded, or `None` if it is empty.
+    #[inline]
+    pub fn max(&self) -> Option<Duration> {
+        if self.count == 0 { None } else { Some(self.max) }
+    }
+
+    /// Returns the average duration recorded, or `None` if it is empty.
+    #[inline]
+    pub fn mean(&self) -> Option<Duration> {
+        if self.count == 0 {
+            return None;
+        }
+        let nanos = self.total.as_nanos() / u128::from(self.count);
+        Some(Duration::from_nanos(nanos as u64))
+    }
+
+    /// Returns an upper bound on the given percentile (0 to 100) of the
+    /// recorded durations, or `None` if it is empty. Since durations are
+    /// bucketed by powers of two, the bound is within a factor of two (and
+    /// never more than the maximum).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use metrics::Histogram;
+    /// use std::time::Duration;
+    ///
+    /// let mut h = Histogram::new();
+    /// for micros in 1..101 {
+    ///     h.record(Duration::from_micros(micros));
+    /// }
+    /// assert_eq!(Some(Duration::from_micros(63)), h.percentile(50.0));
+    /// assert_eq!(Some(Duration::from_micros(100)), h.percentile(100.0));
+    /// ```
+    pub fn percentile(&self, percent: f64) -> Option<Duration> {
+        if self.count == 0 {
+            return None;
+        }
+        let rank = ((percent / 100.0) * self.count as f64).ceil().max(1.0);
+        let mut seen = 0;
+        for (bucket, &n) in self.buckets.iter().enumerate() {
+            seen += n;
+            if seen as f64 >= rank {
+                // The largest whole number of microseconds in the bucket.
+                let upper = Duration::from_micros((1u64 << bucket) - 1);
+                return Some(upper.max(self.min).min(self.max));
+            }
+        }
+        Some(self.max)
+    }
+}
+
+/// The counters and per-phase histograms for a single backend.
+#[derive(Clone, Debug, Default)]
+pub struct BackendMetrics {
+    counters: BTreeMap<String, u64>,
+    phases: BTreeMap<Phase, Histogram>,
+}
+
+impl BackendMetrics {
+    /// Returns the value of the named counter (0 if it was never incremented).
+    #[inline]
+    pub fn counter(&self, counter: &str) -> u64 {
+        self.counters.get(counter).cloned().unwrap_or(0)
+    }
+
+    /// Returns the histogram of timings recorded for `phase`.
+    #[inline]
+    pub fn histogram(&self, phase: Phase) -> Histogram {
+        self.phases.get(&phase).cloned().unwrap_or_default()
+    }
+}
+
+impl Recorder for BackendMetrics {
+    #[inline]
+    fn increment(&mut self, counter: &str, n: u64) {
+        *self.counters.entry(counter.to_string()).or_insert(0) += n;
+    }
+
+    #[inline]
+    fn record(&mut self, phase: Phase, elapsed: Duration) {
+        self.phases.entry(phase).or_default().record(elapsed);
+    }
+}
+
+/// Metrics for any number of backends, keyed by backend name. The `Display`
+/// implementation renders a summary table suitable for printing at exit.
+#[derive(Clone, Debug, Default)]
+pub struct Metrics {
+    backends: BTreeMap<String, BackendMetrics>,
+}
+
+impl Metrics {
+    /// Constructs a new, empty `Metrics`.
+    #[inline]
+    pub fn new() -> Metrics {
+        Metrics { backends: BTreeMap::new() }
+    }
+
+    /// Returns the recorder for the named backend, creating it if necessary.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use metrics::{Metrics, Recorder};
+    ///
+    /// let mut metrics = Metrics::new();
+    /// metrics.backend("sets").increment("patterns", 1);
+    /// metrics.backend("sets").increment("patterns", 1);
+    /// assert_eq!(2, metrics.backend("sets").counter("patterns"));
+    /// assert_eq!(0, metrics.backend("prefix").counter("patterns"));
+    /// ```
+    #[inline]
+    pub fn backend(&mut self, name: &str) -> &mut BackendMetrics {
+        self.backends.entry(name.to_string()).or_default()
+    }
+}
+
+fn format_duration(d: Option<Duration>) -> String {
+    match d {
+        Some(d) => format!("{}μs", d.as_micros()),
+        None => "-".to_string(),
+    }
+}
+
+impl fmt::Display for Metrics {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (name, backend) in &self.backends {
+            writeln!(f, "{}:", name)?;
+            for (counter, n) in &backend.counters {
+                writeln!(f, "\t{:<8} {:>10}", counter, n)?;
+            }
+            if backend.phases.is_empty() {
+                continue;
+            }
+            writeln!(f,
+                     "\t{:<8} {:>10} {:>12} {:>10} {:>10} {:>10} {:>10}",
+                     "phase", "count", "total", "mean", "p50", "p99", "max")?;
+            for (phase, h) in &backend.phases {
+                writeln!(f,
+                         "\t{:<8} {:>10} {:>12} {:>10} {:>10} {:>10} {:>10}",
+                         phase.name(),
+                         h.count(),
+                         format_duration(Some(h.total())),
+                         format_duration(h.mean()),
+                         format_duration(h.percentile(50.0)),
+                         format_duration(h.percentile(99.0)),
+                         format_duration(h.max()))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[test]
+fn empty_histogram() {
+    let h = Histogram::new();
+    assert_eq!(0, h.count());
+    assert_eq!(None, h.min());
+    assert_eq!(None, h.max());
+    assert_eq!(None, h.mean());
+    assert_eq!(None, h.percentile(50.0));
+}
+
+#[test]
+fn histogram_min_max() {
+    let mut h = Histogram::new();
+    h.record(Duration::from_micros(40));
+    h.record(Duration::from_micros(2));
+    h.record(Duration::from_millis(7));
+    assert_eq!(Some(Duration::from_micros(2)), h.min());
+    assert_eq!(Some(Duration::from_millis(7)), h.max());
+    assert_eq!(Duration::from_micros(7042), h.total());
+}
+
+#[test]
+fn histogram_percentile_within_factor_of_two() {
+    let mut h = Histogram::new();
+    for _ in 0..99 {
+        h.record(Duration::from_micros(10));
+    }
+    h.record(Duration::from_micros(1000));
+    let p50 = h.percentile(50.0).unwrap();
+    assert!(p50 >= Duration::from_micros(10));
+    assert!(p50 < Duration::from_micros(20));
+    assert_eq!(Some(Duration::from_micros(1000)), h.percentile(100.0));
+}
+
+#[test]
+fn histogram_sub_microsecond() {
+    let mut h = Histogram::new();
+    h.record(Duration::from_nanos(10));
+    assert_eq!(Some(Duration::from_nanos(10)), h.percentile(50.0));
+}
+
+#[test]
+fn backends_are_separate() {
+    let mut metrics = Metrics::new();
+    metrics.backend("naive").record(Phase::Lookup, Duration::from_micros(5));
+    metrics.backend("prefix").record(Phase::Parse, Duration::from_micros(5));
+    assert_eq!(1, metrics.backend("naive").histogram(Phase::Lookup).count());
+    assert_eq!(0, metrics.backend("naive").histogram(Phase::Parse).count());
+    assert_eq!(1, metrics.backend("prefix").histogram(Phase::Parse).count());
+}
+
+#[test]
+fn summary_lists_backends_and_phases() {
+    let mut metrics = Metrics::new();
+    metrics.backend("sets").increment("patterns", 3);
+    metrics.backend("sets").record(Phase::Format, Duration::from_micros(12));
+    let summary = metrics.to_string();
+    assert!(summary.starts_with("sets:\n"));
+    assert!(summary.contains("patterns"));
+    assert!(summary.contains("format"));
+    assert!(summary.contains("12μs"));
+}
@@ -1,26 +1,27 @@
 extern crate getopts;
+extern crate metrics;
 extern crate regex;
-extern crate time;
 
 use getopts::Options;
+use metrics::{time, Metrics, Phase, Recorder};
 use std::env;
-use std::error::Error;
 use std::fs::File;
 use std::io;
 use std::io::BufReader;
 use std::io::prelude::*;
 use std::path::Path;
+use std::time::{Duration, Instant};
 use regex::Regex;
-use time::{Duration, PreciseTime};
+
+/// The name that this matcher reports its metrics under.
+const BACKEND : &str = "naive";
 
 /// Load a dictionary containing one word per line (e.g. from /usr/share/dict/).
 fn load_dictionary(dictionary_path: &Path) -> Vec<String> {
     let display = dictionary_path.display();
 
     let file = match File::open(dictionary_path) {
-        Err(why) => panic!("couldn't open {}: {}",
-                           display,
-                           Error::description(&why)),
+        Err(why) => panic!("couldn't open {}: {}", display, why),
         Ok(file) => file,
     };
     let reader = BufReader::new(file);
@@ -28,15 +29,13 @@ fn load_dictionary(dictionary_path: &Path) -> Vec<String> {
 
     for readline in reader.lines() {
         let line = match readline {
-            Err(why) => panic!("couldn't read from {}: {}",
-                                display,
-                                Error::description(&why)),
+            Err(why) => panic!("couldn't read from {}: {}", display, why),
             Ok(readline) => readline,
         };
 
         words.push(line.trim().to_string());
     }
-    return words;
+    words
 }
 
 /// Match a pattern against a given list of words and return the subset that
@@ -59,40 +58,63 @@ fn load_dictionary(dictionary_path: &Path) -> Vec<String> {
 /// matches.sort();
 /// assert_eq!(matches, ["intercrystallization", "internationalization"]);
 /// ```
+fn match_pattern<'a>(pattern : &str, words : &'a [String]) -> Vec<&'a str> {
+    find_matches(&compile_pattern(pattern), words)
+}
+
+/// Convert the input pattern into a regular expression e.g. "i18n" becomes
+/// "(?i)^i.{18}n$".
 #[inline]
-fn match_pattern<'a>(pattern : &str, words : &'a Vec<String>)
-        -> Vec<&'a str> {
+fn compile_pattern(pattern : &str) -> Regex {
     let pattern_parser = Regex::new(
         r"(?P<number>\d+)|(?P<letter>[A-Za-z])").unwrap();
 
-    // Convert the input pattern into a regular expression.
     let mut regex_string = "(?i)^".to_string();
-    for number_or_letter in pattern_parser.captures_iter(&pattern) {
-        if number_or_letter.name("number").is_some() {
-            let number_as_string = number_or_letter.name("number").unwrap();
+    for number_or_letter in pattern_parser.captures_iter(pattern) {
+        if let Some(number) = number_or_letter.name("number") {
             regex_string.push_str(".{");
-            regex_string.push_str(number_as_string);
+            regex_string.push_str(number.as_str());
             regex_string.push('}');
         } else {
-            let match_word = number_or_letter.name("letter").unwrap();
-            let ch = match_word.char_indices().next().unwrap().1;
+            let letter = number_or_letter.name("letter").unwrap();
+            let ch = letter.as_str().chars().next().unwrap();
             regex_string.push(ch);
         }
     }
 
     regex_string.push('$');
-    let pattern_matcher = Regex::new(&regex_string).unwrap();
+    Regex::new(&regex_string).unwrap()
+}
+
+/// Try to match the compiled regular expression against every word.
+#[inline]
+fn find_matches<'a>(pattern_matcher : &Regex, words : &'a [String])
+        -> Vec<&'a str> {
     let mut matching_words : Vec<&str> = Vec::new();
 
-    // Try to match the constructed regular expression against every word.
     for word in words {
         if pattern_matcher.is_match(word) {
-           matching_words.push(word) 
+           matching_words.push(word)
         }
     }
     matching_words
 }
 
+/// Like `match_pattern` but records the time spent parsing the pattern and
+/// looking up words, and counts patterns and matches.
+fn match_pattern_recorded<'a, R: Recorder>(pattern : &str,
+                                           words : &'a [String],
+                                           recorder : &mut R)
+        -> Vec<&'a str> {
+    let pattern_matcher = time(
+        recorder, Phase::Parse, || compile_pattern(pattern));
+    let matches = time(
+        recorder, Phase::Lookup, || find_matches(&pattern_matcher, words));
+    recorder.increment("patterns", 1);
+    recorder.increment("matches", matches.len() as u64);
+    matches
+}
+
 #[test]
 fn match_number_only() {
     let words = vec![
@@ -187,7 +209,7 @@ fn match_ignores_case() {
     assert_eq!(matches, ["Cat", "cat", "cot"]);
 }
 
-fn print_matches(words : Vec<&str>, num_runs : u32, duration : &Duration) {
+fn print_matches(words : Vec<&str>, num_runs : u32, duration : Duration) {
     if words.is_empty() {
         println!("\t<No Results>");
     } else {
@@ -201,10 +223,10 @@ fn print_matches(words : Vec<&str>, num_runs : u32, duration : &Duration) {
         match num_runs {
             1 => println!("\t => {} results in {}μs",
                           len,
-                          duration.num_microseconds().unwrap()),
+                          duration.as_micros()),
             _ => println!("\t => {} results in {}μs ({} runs)",
                           len,
-                          duration.num_microseconds().unwrap(),
+                          duration.as_micros(),
                           num_runs)
         }
     }
@@ -220,24 +242,37 @@ fn main() {
         "num_runs",
         "the number of times to run the match per line",
         "COUNT");
+    opts.optflag(
+        "s",
+        "summary",
+        "print a summary of counts and phase timings at exit");
     let matches = opts.parse(&args[1..]).unwrap();
     let num_runs : u32 = match matches.opt_str("n") {
         Some(n) => n.parse().unwrap(),
         None => 1
     };
-
+    let mut metrics = Metrics::new();
 
     let dictionary_path = Path::new("/usr/share/dict/words");
-    let words = load_dictionary(&dictionary_path);
+    let words = time(metrics.backend(BACKEND), Phase::Load,
+                     || load_dictionary(dictionary_path));
 
     let stdin = io::stdin();
     for line in stdin.lock().lines() {
-        let start = PreciseTime::now();
+        let start = Instant::now();
         let word = line.unwrap();
+        let recorder = metrics.backend(BACKEND);
+        // Only the reported run is recorded, so each pattern counts once.
         for _ in 0..num_runs {
             match_pattern(&word, &words);
         }
-        let matches = match_pattern(&word, &words);
-        print_matches(matches, num_runs, &start.to(PreciseTime::now()));
+        let matches = match_pattern_recorded(&word, &words, recorder);
+        let elapsed = start.elapsed();
+        time(recorder, Phase::Format,
+             || print_matches(matches, num_runs, elapsed));
+    }
+
+    if matches.opt_present("s") {
+        print!("{}", metrics);
     }
 }
@@ -1,12 +1,11 @@
 extern crate getopts;
+extern crate metrics;
 extern crate regex;
-extern crate time;
 
 use getopts::Options;
+use metrics::{time, Metrics, Phase, Recorder};
 use std::env;
 use std::mem;
-use std::ascii::AsciiExt;
-use std::error::Error;
 use std::fs::File;
 use std::io;
 use std::io::BufReader;
@@ -14,8 +13,11 @@ use std::io::prelude::*;
 use std::path::Path;
 use std::str::Chars;
 use std::collections::HashMap;
+use std::time::{Duration, Instant};
 use regex::Regex;
-use time::{Duration, PreciseTime};
+
+/// The name that this matcher reports its metrics under.
+const BACKEND : &str = "prefix";
 
 /// A node in a prefix-tree (aka trie).
 // TODO(brian@sweetapp.com): Since each trie only stores words of the same
@@ -30,9 +32,7 @@ fn load_dictionary(dictionary_path: &Path) -> Vec<String> {
     let display = dictionary_path.display();
 
     let file = match File::open(dictionary_path) {
-        Err(why) => panic!("couldn't open {}: {}",
-                           display,
-                           Error::description(&why)),
+        Err(why) => panic!("couldn't open {}: {}", display, why),
         Ok(file) => file,
     };
     let reader = BufReader::new(file);
@@ -40,15 +40,13 @@ fn load_dictionary(dictionary_path: &Path) -> Vec<String> {
 
     for readline in reader.lines() {
         let line = match readline {
-            Err(why) => panic!("couldn't read from {}: {}",
-                                display,
-                                Error::description(&why)),
+            Err(why) => panic!("couldn't read from {}: {}", display, why),
             Ok(readline) => readline,
         };
 
         words.push(line.trim().to_string());
     }
-    return words;
+    words
 }
 
 fn insert_in_trie_internal(word : &str,
@@ -78,47 +76,62 @@ fn build_length_to_trie_map(words: &[String]) -> HashMap<usize, Node> {
     let mut length_to_trie = HashMap::new();
 
     for word in words {
-        let mut trie = length_to_trie.entry(word.len()).or_insert_with(
+        let trie = length_to_trie.entry(word.len()).or_insert_with(
             || Node {words: Vec::new(), children: HashMap::new()});
-        insert_in_trie(word, &mut trie);
+        insert_in_trie(word, trie);
     }
     length_to_trie
 }
 
 fn match_pattern<'a>(pattern : &str,
          build_length_to_trie_map : &'a HashMap<usize, Node>) -> Vec<&'a str> {
+    let (tokens, pattern_length) = parse_pattern(pattern);
+    find_matches(&tokens, pattern_length, build_length_to_trie_map)
+}
+
+// To make it easier to traverse the trie, the input pattern is converted into a
+// list of tokens. For example, "c2t" => vec![Token::Character('c'),
+//                                            Token::Any,
+//                                            Token::Any,
+//                                            Token::Character('t')]
+enum Token {
+    Character(char),
+    Any
+}
+
+/// Convert the input pattern into a list of tokens and the length of the words
+/// that it can match.
+fn parse_pattern(pattern : &str) -> (Vec<Token>, usize) {
     let pattern_parser = Regex::new(
         r"(?P<number>\d+)|(?P<letter>[A-Za-z])").unwrap();
 
-    // To make it easier to traverse the trie, convert the input pattern into a
-    // list of tokens. For example, "c2t" => vec![Token::Character('c'),
-    //                                            Token::Any,
-    //                                            Token::Any,
-    //                                            Token::Character('t')]
-    enum Token {
-        Character(char),
-        Any
-    }
     let mut tokens = Vec::new();
     let mut pattern_length: usize = 0;
 
     for number_or_letter in pattern_parser.captures_iter(
             &pattern.to_ascii_lowercase()) {
-        if number_or_letter.name("number").is_some() {
-            let number_as_string = number_or_letter.name("number").unwrap();
-            let number: usize = number_as_string.parse().unwrap();
+        if let Some(number_as_string) = number_or_letter.name("number") {
+            let number: usize = number_as_string.as_str().parse().unwrap();
             for _ in 0..number {
                 tokens.push(Token::Any);
             }
             pattern_length += number;
         } else {
             let match_word = number_or_letter.name("letter").unwrap();
-            let ch = match_word.char_indices().next().unwrap().1;
+            let ch = match_word.as_str().chars().next().unwrap();
             tokens.push(Token::Character(ch));
             pattern_length += 1;
         }
     }
+    (tokens, pattern_length)
+}
 
+/// Traverse the trie of words of length `pattern_length` following `tokens`
+/// and return the words found at the end.
+fn find_matches<'a>(tokens : &[Token],
+                    pattern_length : usize,
+                    build_length_to_trie_map : &'a HashMap<usize, Node>)
+        -> Vec<&'a str> {
     let trie = match build_length_to_trie_map.get(&pattern_length) {
         Some(node) => node,
         None => return Vec::new()
@@ -150,7 +163,7 @@ fn match_pattern<'a>(pattern : &str,
                 }
             }
         }
-        if next_nodes.len() == 0 {
+        if next_nodes.is_empty() {
             return Vec::new();
         }
         assert_eq!(nodes.len(), 0);
@@ -161,12 +174,28 @@ fn match_pattern<'a>(pattern : &str,
     let mut words : Vec<&str> = Vec::new();
     for node in nodes {
         for word in node.words.iter() {
-            words.push(&word);
+            words.push(word);
         }
     }
     words
 }
 
+/// Like `match_pattern` but records the time spent parsing the pattern and
+/// looking up words, and counts patterns and matches.
+fn match_pattern_recorded<'a, R: Recorder>(
+        pattern : &str,
+        build_length_to_trie_map : &'a HashMap<usize, Node>,
+        recorder : &mut R) -> Vec<&'a str> {
+    let (tokens, pattern_length) = time(
+        recorder, Phase::Parse, || parse_pattern(pattern));
+    let matches = time(
+        recorder, Phase::Lookup,
+        || find_matches(&tokens, pattern_length, build_length_to_trie_map));
+    recorder.increment("patterns", 1);
+    recorder.increment("matches", matches.len() as u64);
+    matches
+}
+
 #[test]
 fn match_number_only() {
     let words = vec![
@@ -269,7 +298,7 @@ fn match_ignores_case() {
     assert_eq!(matches, ["Cat", "cat", "cot"]);
 }
 
-fn print_matches(words : Vec<&str>, num_runs : u32, duration : &Duration) {
+fn print_matches(words : Vec<&str>, num_runs : u32, duration : Duration) {
     if words.is_empty() {
         println!("\t<No Results>");
     } else {
@@ -283,10 +312,10 @@ fn print_matches(words : Vec<&str>, num_runs : u32, duration : &Duration) {
         match num_runs {
             1 => println!("\t => {} results in {}μs",
                           len,
-                          duration.num_microseconds().unwrap()),
+                          duration.as_micros()),
             _ => println!("\t => {} results in {}μs ({} runs)",
                           len,
-                          duration.num_microseconds().unwrap(),
+                          duration.as_micros(),
                           num_runs)
         }
     }
@@ -302,25 +331,39 @@ fn main() {
         "num_runs",
         "the number of times to run the match per line",
         "COUNT");
+    opts.optflag(
+        "s",
+        "summary",
+        "print a summary of counts and phase timings at exit");
     let matches = opts.parse(&args[1..]).unwrap();
     let num_runs : u32 = match matches.opt_str("n") {
         Some(n) => n.parse().unwrap(),
         None => 1
     };
-
+    let mut metrics = Metrics::new();
 
     let dictionary_path = Path::new("/usr/share/dict/words");
-    let words = load_dictionary(&dictionary_path);
-    let length_to_trie = build_length_to_trie_map(&words);
+    let words = time(metrics.backend(BACKEND), Phase::Load,
+                     || load_dictionary(dictionary_path));
+    let length_to_trie = time(metrics.backend(BACKEND), Phase::Index,
+                              || build_length_to_trie_map(&words));
 
     let stdin = io::stdin();
     for line in stdin.lock().lines() {
-        let start = PreciseTime::now();
+        let start = Instant::now();
         let word = line.unwrap();
+        let recorder = metrics.backend(BACKEND);
+        // Only the reported run is recorded, so each pattern counts once.
         for _ in 0..num_runs {
             match_pattern(&word, &length_to_trie);
         }
-        let matches = match_pattern(&word, &length_to_trie);
-        print_matches(matches, num_runs, &start.to(PreciseTime::now()));
+        let matches = match_pattern_recorded(&word, &length_to_trie, recorder);
+        let elapsed = start.elapsed();
+        time(recorder, Phase::Format,
+             || print_matches(matches, num_runs, elapsed));
+    }
+
+    if matches.opt_present("s") {
+        print!("{}", metrics);
     }
 }
@@ -1,11 +1,10 @@
 extern crate getopts;
+extern crate metrics;
 extern crate regex;
-extern crate time;
 
 use getopts::Options;
+use metrics::{time, Metrics, Phase, Recorder};
 use std::env;
-use std::ascii::AsciiExt;
-use std::error::Error;
 use std::fs::File;
 use std::io;
 use std::io::BufReader;
@@ -13,17 +12,25 @@ use std::io::prelude::*;
 use std::path::Path;
 use std::collections::HashMap;
 use std::collections::HashSet;
+use std::time::{Duration, Instant};
 use regex::Regex;
-use time::{Duration, PreciseTime};
+
+/// The name that this matcher reports its metrics under.
+const BACKEND : &str = "sets";
+
+/// Maps (character, index, word length) to the set of words with that
+/// character at that index.
+type ChPositionLengthMap<'a> = HashMap<(char, usize, usize), HashSet<&'a str>>;
+
+/// Maps word length to the list of words of that length.
+type LengthMap<'a> = HashMap<usize, Vec<&'a str>>;
 
 /// Load a dictionary containing one word per line (e.g. from /usr/share/dict/).
 fn load_dictionary(dictionary_path: &Path) -> Vec<String> {
     let display = dictionary_path.display();
 
     let file = match File::open(dictionary_path) {
-        Err(why) => panic!("couldn't open {}: {}",
-                           display,
-                           Error::description(&why)),
+        Err(why) => panic!("couldn't open {}: {}", display, why),
         Ok(file) => file,
     };
     let reader = BufReader::new(file);
@@ -31,15 +38,13 @@ fn load_dictionary(dictionary_path: &Path) -> Vec<String> {
 
     for readline in reader.lines() {
         let line = match readline {
-            Err(why) => panic!("couldn't read from {}: {}",
-                                display,
-                                Error::description(&why)),
+            Err(why) => panic!("couldn't read from {}: {}", display, why),
             Ok(readline) => readline,
         };
 
         words.push(line.trim().to_string());
     }
-    return words;
+    words
 }
 
 /// Build the data structures needed for the matching algorithm given a list of
@@ -54,22 +59,17 @@ fn load_dictionary(dictionary_path: &Path) -> Vec<String> {
 /// For example:
 ///   1 => ["a", "I", etc.]
 ///   3 => ["art", "can", con", "mat", etc.]
-fn build_maps(words: &[String]) -> (
-        HashMap<(char, usize, usize), HashSet<&str>>,
-        HashMap<usize, Vec<&str>>) {
-    let mut ch_position_length_map :
-        HashMap<(char, usize, usize), HashSet<&str>> = HashMap::new();
-    let mut length_map : HashMap<usize, Vec<&str>> = HashMap::new();
+fn build_maps(words: &[String]) -> (ChPositionLengthMap<'_>, LengthMap<'_>) {
+    let mut ch_position_length_map : ChPositionLengthMap = HashMap::new();
+    let mut length_map : LengthMap = HashMap::new();
 
     for word in words.iter() {
         for (index, ch) in word.to_ascii_lowercase().chars().enumerate() {
             let key = (ch, index, word.len());
 
-            ch_position_length_map.entry(key).or_insert_with(
-                || HashSet::new()).insert(word);
+            ch_position_length_map.entry(key).or_default().insert(word);
         }
-        length_map.entry(word.len()).or_insert_with(
-            || Vec::new()).push(word);
+        length_map.entry(word.len()).or_default().push(word);
     }
     (ch_position_length_map, length_map)
 }
@@ -84,8 +84,17 @@ fn build_maps(words: &[String]) -> (
 /// maps (character, index, word length) to a list of words. The second maps
 /// word length to a list of words.
 fn match_pattern<'a>(pattern : &str,
-         ch_position_length_map : &HashMap<(char, usize, usize), HashSet<&'a str>>,
-         length_map : &HashMap<usize, Vec<&'a str>>) -> Vec<&'a str> {
+         ch_position_length_map : &ChPositionLengthMap<'a>,
+         length_map : &LengthMap<'a>) -> Vec<&'a str> {
+    let (pattern_length, ch_and_index) = parse_pattern(pattern);
+    find_matches(pattern_length, &ch_and_index,
+                 ch_position_length_map, length_map)
+}
+
+/// Convert the input pattern into its total length and a list of the known
+/// (lowercase) characters and their index. For example, "c1t" becomes
+/// (3, vec![('c', 0), ('t', 2)]).
+fn parse_pattern(pattern : &str) -> (usize, Vec<(char, usize)>) {
     let pattern_parser = Regex::new(
         r"(?P<number>\d+)|(?P<letter>[A-Za-z])").unwrap();
 
@@ -94,18 +103,25 @@ fn match_pattern<'a>(pattern : &str,
 
     for number_or_letter in pattern_parser.captures_iter(
             &pattern.to_ascii_lowercase()) {
-        if number_or_letter.name("number").is_some() {
-            let number_as_string = number_or_letter.name("number").unwrap();
-            let number: usize = number_as_string.parse().unwrap();
+        if let Some(number_as_string) = number_or_letter.name("number") {
+            let number: usize = number_as_string.as_str().parse().unwrap();
             pattern_length += number;
         } else {
             let match_word = number_or_letter.name("letter").unwrap();
-            let ch = match_word.char_indices().next().unwrap().1;
+            let ch = match_word.as_str().chars().next().unwrap();
             ch_and_index.push((ch, pattern_length));
             pattern_length += 1;
         }
     }
+    (pattern_length, ch_and_index)
+}
 
+/// Return the words of length `pattern_length` that have every character in
+/// `ch_and_index` at the given index.
+fn find_matches<'a>(pattern_length : usize,
+                    ch_and_index : &[(char, usize)],
+                    ch_position_length_map : &ChPositionLengthMap<'a>,
+                    length_map : &LengthMap<'a>) -> Vec<&'a str> {
     if ch_and_index.is_empty() {
         // No characters were given so return all the words of the specified
         // length. This handles the case where the pattern is purely digits.
@@ -121,7 +137,7 @@ fn match_pattern<'a>(pattern : &str,
     // pattern.
     let mut word_sets : Vec<&HashSet<&str>> = Vec::new();
     for &(ch, index) in ch_and_index.iter() {
-        let key = (ch, index as usize, pattern_length as usize);
+        let key = (ch, index, pattern_length);
         match ch_position_length_map.get(&key) {
             // The division was valid
             Some(word_set) => word_sets.push(word_set),
@@ -138,7 +154,7 @@ fn match_pattern<'a>(pattern : &str,
 
     // Intersect the word sets from smallest set to largest set to minimize
     // intersection time.
-    word_sets.sort_by(|a, b| a.len().cmp(&b.len()));
+    word_sets.sort_by_key(|a| a.len());
     let mut refined_word_set : HashSet<&str> =
         word_sets[0].intersection(
             word_sets[1]).cloned().collect();
@@ -148,7 +164,25 @@ fn match_pattern<'a>(pattern : &str,
             word_set).cloned().collect();
     }
 
-    return refined_word_set.iter().cloned().collect();
+    refined_word_set.iter().cloned().collect()
+}
+
+/// Like `match_pattern` but records the time spent parsing the pattern and
+/// looking up words, and counts patterns and matches.
+fn match_pattern_recorded<'a, R: Recorder>(
+        pattern : &str,
+        ch_position_length_map : &ChPositionLengthMap<'a>,
+        length_map : &LengthMap<'a>,
+        recorder : &mut R) -> Vec<&'a str> {
+    let (pattern_length, ch_and_index) = time(
+        recorder, Phase::Parse, || parse_pattern(pattern));
+    let matches = time(
+        recorder, Phase::Lookup,
+        || find_matches(pattern_length, &ch_and_index,
+                        ch_position_length_map, length_map));
+    recorder.increment("patterns", 1);
+    recorder.increment("matches", matches.len() as u64);
+    matches
 }
 
 #[test]
@@ -269,7 +303,7 @@ fn match_ignores_case() {
     assert_eq!(matches, ["Cat", "cat", "cot"]);
 }
 
-fn print_matches(words : Vec<&str>, num_runs : u32, duration : &Duration) {
+fn print_matches(words : Vec<&str>, num_runs : u32, duration : Duration) {
     if words.is_empty() {
         println!("\t<No Results>");
     } else {
@@ -283,10 +317,10 @@ fn print_matches(words : Vec<&str>, num_runs : u32, duration : &Duration) {
         match num_runs {
             1 => println!("\t => {} results in {}μs",
                           len,
-                          duration.num_microseconds().unwrap()),
+                          duration.as_micros()),
             _ => println!("\t => {} results in {}μs ({} runs)",
                           len,
-                          duration.num_microseconds().unwrap(),
+                          duration.as_micros(),
                           num_runs)
         }
     }
@@ -295,32 +329,49 @@ fn print_matches(words : Vec<&str>, num_runs : u32, duration : &Duration) {
 #[allow(dead_code)]
 fn main() {
     let args: Vec<String> = env::args().collect();
+
     let mut opts = Options::new();
     opts.optopt(
         "n",
         "num_runs",
         "the number of times to run the match per line",
         "COUNT");
+    opts.optflag(
+        "s",
+        "summary",
+        "print a summary of counts and phase timings at exit");
     let matches = opts.parse(&args[1..]).unwrap();
     let num_runs : u32 = match matches.opt_str("n") {
         Some(n) => n.parse().unwrap(),
         None => 1
     };
+    let mut metrics = Metrics::new();
 
     let dictionary_path = Path::new("/usr/share/dict/words");
-    let words = load_dictionary(&dictionary_path);
-    let (ch_position_length_map, length_map) = build_maps(&words);
+    let words = time(metrics.backend(BACKEND), Phase::Load,
+                     || load_dictionary(dictionary_path));
+    let (ch_position_length_map, length_map) = time(
+        metrics.backend(BACKEND), Phase::Index, || build_maps(&words));
 
     let stdin = io::stdin();
     for line in stdin.lock().lines() {
-        let start = PreciseTime::now();
+        let start = Instant::now();
         let word = line.unwrap();
+        let recorder = metrics.backend(BACKEND);
+        // Only the reported run is recorded, so each pattern counts once.
         for _ in 0..num_runs {
             match_pattern(&word, &ch_position_length_map, &length_map);
         }
-        let matches = match_pattern(&word,
-                                    &ch_position_length_map,
-                                    &length_map);
-        print_matches(matches, num_runs, &start.to(PreciseTime::now()));
+        let matches = match_pattern_recorded(&word,
+                                             &ch_position_length_map,
+                                             &length_map,
+                                             recorder);
+        let elapsed = start.elapsed();
+        time(recorder, Phase::Format,
+             || print_matches(matches, num_runs, elapsed));
+    }
+
+    if matches.opt_present("s") {
+        print!("{}", metrics);
     }
 }
@@ -0,0 +1,70 @@
+//! Times `SlidingWindows::sliding_window_min`, built on a two-stack
+//! `MinQueue`, against the usual monotonic deque.
+//!
+//! Run with `cargo bench`.
+
+extern crate minstack;
+
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+use minstack::SlidingWindows;
+
+const VALUES: usize = 1_000_000;
+const RUNS: u32 = 5;
+
+/// The minimum of each window of `size` values, keeping a deque of the
+/// positions of values that are smaller than every value after them.
+fn monotonic_deque_min(values: &[u64], size: usize) -> Vec<u64> {
+    let mut mins = Vec::with_capacity(values.len().saturating_sub(size - 1));
+    let mut deque: VecDeque<usize> = VecDeque::new();
+    for (i, &value) in values.iter().enumerate() {
+        while deque.back().is_some_and(|&j| values[j] > value) {
+            deque.pop_back();
+        }
+        deque.push_back(i);
+        if deque[0] + size <= i {
+            deque.pop_front();
+        }
+        if i + 1 >= size {
+            mins.push(values[deque[0]]);
+        }
+    }
+    mins
+}
+
+fn two_stack_min(values: &[u64], size: usize) -> Vec<u64> {
+    values.iter().cloned().sliding_window_min(size).collect()
+}
+
+/// The fastest of `RUNS` runs of `f`, along with its result.
+fn fastest<F>(f: F) -> (Duration, Vec<u64>) where F: Fn() -> Vec<u64> {
+    let mut best = Duration::MAX;
+    let mut result = Vec::new();
+    for _ in 0..RUNS {
+        let start = Instant::now();
+        result = f();
+        best = best.min(start.elapsed());
+    }
+    (best, result)
+}
+
+fn main() {
+    let mut state: u64 = 1;
+    let values: Vec<u64> = (0..VALUES)
+        .map(|_| {
+            state = state.wrapping_mul(6364136223846793005)
+                         .wrapping_add(1442695040888963407);
+            state >> 33
+        })
+        .collect();
+    println!("{} random values, fastest of {} runs", VALUES, RUNS);
+    println!("{:>8} {:>14} {:>16}", "window", "two stacks", "monotonic deque");
+    for &size in [2, 16, 256, 4096, 65536].iter() {
+        let (stacks, from_stacks) = fastest(|| two_stack_min(&values, size));
+        let (deque, from_deque) =
+            fastest(|| monotonic_deque_min(&values, size));
+        assert_eq!(from_deque, from_stacks);
+        println!("{:>8} {:>14.2?} {:>16.2?}", size, stacks, deque);
+    }
+}
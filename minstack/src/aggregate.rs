@@ -0,0 +1,313 @@
+//! Associative aggregates of the elements of a stack, and how an
+//! `AggregateStack` records their values.
+
+use std::ops;
+
+use {AggregateStack, Prefixes};
+
+/// An associative way of combining values of type `T`, such as their sum or
+/// their greatest common divisor.
+///
+/// `combine` must be associative, so that combining `a` with `b` and then
+/// `c` gives the same result as combining `a` with the combination of `b`
+/// and `c`. It doesn't need to be commutative: `earlier` always aggregates
+/// elements that were pushed before those of `later`.
+pub trait Aggregate<T> {
+    /// The combined value.
+    type Output;
+
+    /// Returns the aggregate of `value` on its own.
+    fn lift(&self, value: &T) -> Self::Output;
+
+    /// Returns the aggregate of the elements aggregated by `earlier`
+    /// followed by those aggregated by `later`.
+    fn combine(&self, earlier: &Self::Output, later: &Self::Output)
+        -> Self::Output;
+}
+
+/// Adds values together.
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug, Default)]
+pub struct Sum;
+
+impl<T> Aggregate<T> for Sum where T: Clone + ops::Add<Output = T> {
+    type Output = T;
+
+    #[inline]
+    fn lift(&self, value: &T) -> T {
+        value.clone()
+    }
+
+    #[inline]
+    fn combine(&self, earlier: &T, later: &T) -> T {
+        earlier.clone() + later.clone()
+    }
+}
+
+/// Combines values with bitwise or.
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug, Default)]
+pub struct BitOr;
+
+impl<T> Aggregate<T> for BitOr where T: Clone + ops::BitOr<Output = T> {
+    type Output = T;
+
+    #[inline]
+    fn lift(&self, value: &T) -> T {
+        value.clone()
+    }
+
+    #[inline]
+    fn combine(&self, earlier: &T, later: &T) -> T {
+        earlier.clone() | later.clone()
+    }
+}
+
+/// Finds the greatest common divisor of unsigned integers. The greatest
+/// common divisor of zeros is zero.
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug, Default)]
+pub struct Gcd;
+
+macro_rules! gcd_aggregate {
+    ($($t:ty)*) => ($(
+        impl Aggregate<$t> for Gcd {
+            type Output = $t;
+
+            #[inline]
+            fn lift(&self, value: &$t) -> $t {
+                *value
+            }
+
+            #[inline]
+            fn combine(&self, earlier: &$t, later: &$t) -> $t {
+                let (mut a, mut b) = (*earlier, *later);
+                while b != 0 {
+                    let r = a % b;
+                    a = b;
+                    b = r;
+                }
+                a
+            }
+        }
+    )*)
+}
+
+gcd_aggregate! { u8 u16 u32 u64 u128 usize }
+
+/// Aggregates values with a pair of functions: the first turns a value into
+/// an aggregate, and the second combines two aggregates. The second must be
+/// associative.
+///
+/// # Examples
+///
+/// ```
+/// use minstack::{AggregateStack, Fold};
+///
+/// let mut stack = AggregateStack::with_aggregate(Fold(
+///     |word: &&str| word.to_string(),
+///     |a: &String, b: &String| format!("{} {}", a, b)));
+/// stack.push("the");
+/// stack.push("last");
+/// stack.push("word");
+/// assert_eq!(Some(&"the last word".to_string()), stack.aggregate());
+/// ```
+#[derive(Copy, Clone, Debug)]
+pub struct Fold<L, F>(pub L, pub F);
+
+impl<T, O, L, F> Aggregate<T> for Fold<L, F>
+        where L: Fn(&T) -> O, F: Fn(&O, &O) -> O {
+    type Output = O;
+
+    #[inline]
+    fn lift(&self, value: &T) -> O {
+        (self.0)(value)
+    }
+
+    #[inline]
+    fn combine(&self, earlier: &O, later: &O) -> O {
+        (self.1)(earlier, later)
+    }
+}
+
+/// The aggregate `A` of the elements up to and including each element of an
+/// `AggregateStack`, so popping never needs to recompute anything.
+pub struct Values<T, A : Aggregate<T>> {
+    values: Vec<A::Output>,
+    aggregate: A,
+    // Whether each element pushed comes before those below it.
+    reversed: bool
+}
+
+impl<T, A : Aggregate<T>> Values<T, A> {
+    #[inline]
+    pub(crate) fn new(aggregate: A) -> Values<T, A> {
+        Values { values: Vec::new(), aggregate, reversed: false }
+    }
+}
+
+impl<T, A : Aggregate<T>> Prefixes<T> for Values<T, A> {
+    #[inline]
+    fn push(&mut self, _stack: &[T], value: &T) {
+        let lifted = self.aggregate.lift(value);
+        let prefix = match self.values.last() {
+            Some(below) if self.reversed => {
+                self.aggregate.combine(&lifted, below)
+            }
+            Some(below) => self.aggregate.combine(below, &lifted),
+            None => lifted,
+        };
+        self.values.push(prefix);
+    }
+
+    #[inline]
+    fn pop(&mut self, _len: usize) {
+        self.values.pop();
+    }
+
+    #[inline]
+    fn clear(&mut self) {
+        self.values.clear();
+    }
+
+    #[inline]
+    fn reversed(self) -> Values<T, A> {
+        Values { reversed: true, ..self }
+    }
+}
+
+impl<T, A> Clone for Values<T, A>
+        where A: Aggregate<T> + Clone, A::Output: Clone {
+    fn clone(&self) -> Values<T, A> {
+        Values {
+            values: self.values.clone(),
+            aggregate: self.aggregate.clone(),
+            reversed: self.reversed
+        }
+    }
+}
+
+impl<T, A : Aggregate<T> + Default> Default for Values<T, A> {
+    #[inline]
+    fn default() -> Values<T, A> {
+        Values::new(A::default())
+    }
+}
+
+impl<T, A : Aggregate<T>> AggregateStack<T, Values<T, A>> {
+    /// Constructs a new, empty stack that keeps track of `aggregate`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use minstack::{AggregateStack, Sum};
+    ///
+    /// let mut stack = AggregateStack::with_aggregate(Sum);
+    /// stack.push(3);
+    /// stack.push(8);
+    /// assert_eq!(Some(&11), stack.aggregate());
+    /// ```
+    #[inline]
+    pub fn with_aggregate(aggregate: A) -> AggregateStack<T, Values<T, A>> {
+        AggregateStack::with_prefixes(Values::new(aggregate))
+    }
+
+    /// Returns the aggregate of every element in the stack, or `None` if it
+    /// is empty.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use minstack::{AggregateStack, BitOr};
+    ///
+    /// let mut stack = AggregateStack::with_aggregate(BitOr);
+    /// assert_eq!(None, stack.aggregate());
+    /// stack.push(0b0100);
+    /// stack.push(0b0001);
+    /// assert_eq!(Some(&0b0101), stack.aggregate());
+    /// ```
+    #[inline]
+    pub fn aggregate(&self) -> Option<&A::Output> {
+        self.prefixes.values.last()
+    }
+
+    /// Returns the way that the stack combines its elements.
+    #[inline]
+    pub(crate) fn aggregator(&self) -> &A {
+        &self.prefixes.aggregate
+    }
+}
+
+#[cfg(test)]
+use std::fmt;
+#[cfg(test)]
+use Lcg;
+
+/// Checks that each prefix aggregate is what folding the elements up to it
+/// from scratch gives.
+#[cfg(test)]
+pub(crate) fn check_prefixes<T, A>(stack: &AggregateStack<T, Values<T, A>>)
+        where A: Aggregate<T>, A::Output: PartialEq + fmt::Debug {
+    let prefixes = &stack.prefixes;
+    assert_eq!(stack.stack.len(), prefixes.values.len());
+    let mut expected: Option<A::Output> = None;
+    for (value, prefix) in stack.stack.iter().zip(&prefixes.values) {
+        let lifted = prefixes.aggregate.lift(value);
+        expected = Some(match expected {
+            Some(below) if prefixes.reversed => {
+                prefixes.aggregate.combine(&lifted, &below)
+            }
+            Some(below) => prefixes.aggregate.combine(&below, &lifted),
+            None => lifted,
+        });
+        assert_eq!(expected.as_ref(), Some(prefix));
+    }
+}
+
+#[test]
+fn every_aggregate_matches_a_vec() {
+    for seed in 0..20 {
+        let mut rng = Lcg(seed);
+        let mut sums = AggregateStack::with_aggregate(Sum);
+        let mut gcds = AggregateStack::with_aggregate(Gcd);
+        let mut ors = AggregateStack::with_aggregate(BitOr);
+        let mut words = AggregateStack::with_aggregate(Fold(
+            |&v: &u64| v.to_string(),
+            |a: &String, b: &String| format!("{}{}", a, b)));
+        let mut model: Vec<u64> = Vec::new();
+        for _ in 0..200 {
+            if rng.below(5) < 3 {
+                let value = rng.below(4) * 6;
+                sums.push(value);
+                gcds.push(value);
+                ors.push(value);
+                words.push(value);
+                model.push(value);
+            } else {
+                let expected = model.pop();
+                assert_eq!(expected, sums.pop());
+                assert_eq!(expected, gcds.pop());
+                assert_eq!(expected, ors.pop());
+                assert_eq!(expected, words.pop());
+            }
+            check_prefixes(&gcds);
+            check_prefixes(&words);
+            let expected = |f: &dyn Fn(u64, u64) -> u64| {
+                model.iter().cloned().fold(None, |acc, v| {
+                    Some(acc.map_or(v, |acc| f(acc, v)))
+                })
+            };
+            assert_eq!(expected(&|a, b| a + b), sums.aggregate().cloned());
+            assert_eq!(expected(&|a, b| a | b), ors.aggregate().cloned());
+            let gcd = if model.is_empty() {
+                None
+            } else if model.iter().all(|&v| v == 0) {
+                Some(0)
+            } else {
+                (1..19).rev().find(|d| model.iter().all(|v| v % d == 0))
+            };
+            assert_eq!(gcd, gcds.aggregate().cloned());
+            let concatenated: String = model.iter()
+                .map(|v| v.to_string())
+                .collect();
+            assert_eq!(model.first().map(|_| &concatenated), words.aggregate());
+        }
+    }
+}
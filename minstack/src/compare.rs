@@ -0,0 +1,55 @@
+//! Orderings that an `OrderedStack` can track the first element of.
+
+use std::cmp::Ordering;
+
+/// An ordering of values of type `T`. An `OrderedStack` keeps track of the
+/// element that comes first in its ordering.
+pub trait Compare<T> {
+    /// Returns how `a` is ordered relative to `b`.
+    fn compare(&self, a: &T, b: &T) -> Ordering;
+}
+
+/// Orders values from smallest to largest, so that the first is the minimum.
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug, Default)]
+pub struct Min;
+
+impl<T : Ord> Compare<T> for Min {
+    #[inline]
+    fn compare(&self, a: &T, b: &T) -> Ordering {
+        a.cmp(b)
+    }
+}
+
+/// Orders values from largest to smallest, so that the first is the maximum.
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug, Default)]
+pub struct Max;
+
+impl<T : Ord> Compare<T> for Max {
+    #[inline]
+    fn compare(&self, a: &T, b: &T) -> Ordering {
+        b.cmp(a)
+    }
+}
+
+/// Orders values with a comparison function.
+#[derive(Copy, Clone, Debug)]
+pub struct By<F>(pub F);
+
+impl<T, F> Compare<T> for By<F> where F: Fn(&T, &T) -> Ordering {
+    #[inline]
+    fn compare(&self, a: &T, b: &T) -> Ordering {
+        (self.0)(a, b)
+    }
+}
+
+/// Orders values by the key that a function extracts from them, smallest
+/// key first.
+#[derive(Copy, Clone, Debug)]
+pub struct ByKey<F>(pub F);
+
+impl<T, K, F> Compare<T> for ByKey<F> where F: Fn(&T) -> K, K: Ord {
+    #[inline]
+    fn compare(&self, a: &T, b: &T) -> Ordering {
+        (self.0)(a).cmp(&(self.0)(b))
+    }
+}
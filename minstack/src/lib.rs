@@ -1,20 +1,172 @@
-//! A stack type with heap-allocated contents.
+//! Stack types with heap-allocated contents.
 //!
 //! MinStacks have amortized `O(1)` push, pop and min (return the minimum value
-//! in the stack).
+//! in the stack). MaxStacks do the same for the maximum, `MinMaxStack` for
+//! both at once, and an `OrderedStack` for the first value in any ordering,
+//! such as the value with the smallest key. Each of these is an
+//! `AggregateStack`, which does the same for any associative `Aggregate` of
+//! its elements, such as their sum or greatest common divisor.
+//!
+//! A `MinQueue` made from two MinStacks has amortized `O(1)` push, pop and
+//! min as a first-in, first-out queue, which gives the minimum or maximum of
+//! every window of an iterator through `SlidingWindows`. An `AggregateQueue`
+//! does the same for any aggregate.
+
+use std::cmp::Ordering;
+use std::fmt;
+use std::iter::FromIterator;
+use std::slice;
+use std::vec;
+
+mod aggregate;
+mod compare;
+mod min_max;
+mod queue;
+
+pub use aggregate::{Aggregate, BitOr, Fold, Gcd, Sum, Values};
+pub use compare::{By, ByKey, Compare, Max, Min};
+pub use min_max::MinMaxStack;
+pub use queue::{AggregateQueue, MaxQueue, MinQueue, OrderedQueue,
+                SlidingWindow, SlidingWindows};
+
+/// How an `AggregateStack` records the aggregate of the elements up to and
+/// including each element.
+///
+/// `Values` stores each of those aggregates. `Extremes` only stores the
+/// positions of the elements that come first in an ordering, so the
+/// elements are never copied.
+pub trait Prefixes<T> {
+    /// Records `value`, which is about to be pushed on top of `stack`.
+    fn push(&mut self, stack: &[T], value: &T);
+
+    /// Forgets the value that was popped from the stack, leaving `len`
+    /// elements.
+    fn pop(&mut self, len: usize);
+
+    /// Forgets every value.
+    fn clear(&mut self);
+
+    /// Returns these prefixes, which must be empty, set up for a stack that
+    /// is pushed in the opposite order to the sequence that it aggregates,
+    /// such as the stack that a queue pops from.
+    fn reversed(self) -> Self;
+}
+
+/// The positions in a stack of the values that came first in an ordering,
+/// among the values below them, when they were pushed.
+#[derive(Clone, Default)]
+pub struct Extremes<C> {
+    // Maintain a parallel stack of locations of extreme values.
+    // Whenever a value is pushed to the stack, also push its location to `indices` if
+    // the value is ordered no later than the value at the top of `indices`.
+    // Whenever a value is popped from the stack, also pop `indices` if the
+    // position == the value at the top of `indices`.
+    indices: Vec<usize>,
+    compare: C,
+    // Whether a value ordered equally to the extreme below it is skipped, so
+    // that the first pushed of equal values stays first.
+    strict: bool
+}
 
-pub struct MinStack<T> {
+impl<C> Extremes<C> {
+    #[inline]
+    fn new(compare: C) -> Extremes<C> {
+        Extremes { indices: Vec::new(), compare, strict: false }
+    }
+
+    /// Returns true if `value` takes over as the extreme from `extreme`,
+    /// which was pushed before it.
+    #[inline]
+    fn precedes<T>(&self, value: &T, extreme: &T) -> bool
+            where C: Compare<T> {
+        match self.compare.compare(value, extreme) {
+            Ordering::Less => true,
+            Ordering::Equal => !self.strict,
+            Ordering::Greater => false,
+        }
+    }
+
+    /// Returns the first value of `stack` in the ordering.
+    #[inline]
+    fn get<'a, T>(&self, stack: &'a [T]) -> Option<&'a T> {
+        self.indices.last().map(|&i| &stack[i])
+    }
+}
+
+impl<T, C : Compare<T>> Prefixes<T> for Extremes<C> {
+    #[inline]
+    fn push(&mut self, stack: &[T], value: &T) {
+        if self.indices.last().is_none_or(|&i| {
+            self.precedes(value, &stack[i])
+        }) {
+            self.indices.push(stack.len());
+        }
+    }
+
+    #[inline]
+    fn pop(&mut self, len: usize) {
+        if self.indices.last() == Some(&len) {
+            self.indices.pop();
+        }
+    }
+
+    #[inline]
+    fn clear(&mut self) {
+        self.indices.clear();
+    }
+
+    // The stack holds the last pushed of a sequence at the bottom, so the
+    // first pushed of equal values is the last in the sequence.
+    #[inline]
+    fn reversed(self) -> Extremes<C> {
+        Extremes { strict: true, ..self }
+    }
+}
+
+/// A stack that keeps track of an aggregate of its elements, recorded by
+/// `P`, with amortized `O(1)` push, pop and aggregate.
+///
+/// With `Values` of an `Aggregate`, such as a `Sum`, the stack stores the
+/// aggregate of each element and every element below it. An `OrderedStack`
+/// uses `Extremes` to keep track of the element that comes first in the
+/// ordering `C`. If several elements are ordered equally, the last pushed is
+/// first.
+///
+/// # Examples
+///
+/// ```
+/// use minstack::{AggregateStack, Gcd};
+///
+/// let mut stack = AggregateStack::with_aggregate(Gcd);
+/// stack.push(12u32);
+/// stack.push(18);
+/// assert_eq!(Some(&6), stack.aggregate());
+/// stack.push(8);
+/// assert_eq!(Some(&2), stack.aggregate());
+/// stack.pop();
+/// assert_eq!(Some(&6), stack.aggregate());
+/// ```
+#[derive(Clone)]
+pub struct AggregateStack<T, P> {
     stack: Vec<T>,
-    // Maintain a parallel stack of locations of minimum values.
-    // Whenever a value is pushed to `stack`, also push its location to `min_stack` if
-    // the value is <= the value at the top of `min_stack`.
-    // Whenever a value is popped from `stack`, also pop `min_stack` if the 
-    // position == the value at the top of `min_stack`.
-    min_stack: Vec<usize>
+    prefixes: P
 }
 
-impl<T : Ord> MinStack<T> {
-    /// Constructs a new, empty `MinStack<T>`.
+/// A stack that keeps track of the element that comes first in the ordering
+/// `C`.
+///
+/// `MinStack` and `MaxStack` order elements by value. Use `by` or `by_key`
+/// for other orderings.
+pub type OrderedStack<T, C> = AggregateStack<T, Extremes<C>>;
+
+/// A stack that keeps track of its smallest element.
+pub type MinStack<T> = OrderedStack<T, Min>;
+
+/// A stack that keeps track of its largest element.
+pub type MaxStack<T> = OrderedStack<T, Max>;
+
+impl<T, P : Default> AggregateStack<T, P> {
+    /// Constructs a new, empty stack.
     ///
     /// # Examples
     ///
@@ -24,13 +176,192 @@ impl<T : Ord> MinStack<T> {
     /// let mut stack: MinStack<i32> = MinStack::new();
     /// ```
     #[inline]
-    pub fn new() -> MinStack<T> {
-        MinStack {
-            stack: Vec::new(),
-            min_stack: Vec::new()
+    pub fn new() -> AggregateStack<T, P> {
+        AggregateStack::with_capacity(0)
+    }
+
+    /// Constructs a new, empty stack with room for `capacity` elements
+    /// before reallocating.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use minstack::MinStack;
+    ///
+    /// let stack: MinStack<i32> = MinStack::with_capacity(10);
+    /// assert!(stack.capacity() >= 10);
+    /// assert_eq!(0, stack.len());
+    /// ```
+    #[inline]
+    pub fn with_capacity(capacity: usize) -> AggregateStack<T, P> {
+        AggregateStack {
+            stack: Vec::with_capacity(capacity),
+            prefixes: P::default()
         }
     }
+}
+
+impl<T, F> OrderedStack<T, By<F>> where F: Fn(&T, &T) -> Ordering {
+    /// Constructs a new, empty stack that keeps track of the first element
+    /// according to `compare`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use minstack::OrderedStack;
+    ///
+    /// let mut stack = OrderedStack::by(|a: &f64, b: &f64| {
+    ///     a.partial_cmp(b).unwrap()
+    /// });
+    /// stack.push(2.5);
+    /// stack.push(-1.0);
+    /// assert_eq!(Some(&-1.0), stack.min());
+    /// ```
+    #[inline]
+    pub fn by(compare: F) -> OrderedStack<T, By<F>> {
+        OrderedStack::with_compare(By(compare))
+    }
+}
+
+impl<T, K, F> OrderedStack<T, ByKey<F>> where F: Fn(&T) -> K, K : Ord {
+    /// Constructs a new, empty stack that keeps track of the element with the
+    /// smallest key, as extracted by `key`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use minstack::OrderedStack;
+    ///
+    /// let mut stack = OrderedStack::by_key(|word: &&str| word.len());
+    /// stack.push("three");
+    /// stack.push("one");
+    /// stack.push("two");
+    /// assert_eq!(Some(&"two"), stack.min());
+    /// stack.pop();
+    /// assert_eq!(Some(&"one"), stack.min());
+    /// ```
+    #[inline]
+    pub fn by_key(key: F) -> OrderedStack<T, ByKey<F>> {
+        OrderedStack::with_compare(ByKey(key))
+    }
+}
+
+impl<T, C> OrderedStack<T, C> {
+    /// Constructs a new, empty stack that keeps track of the first element
+    /// in the ordering `compare`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use minstack::{Max, OrderedStack};
+    ///
+    /// let mut stack = OrderedStack::with_compare(Max);
+    /// stack.push(3);
+    /// stack.push(8);
+    /// assert_eq!(Some(&8), stack.max());
+    /// ```
+    #[inline]
+    pub fn with_compare(compare: C) -> OrderedStack<T, C> {
+        AggregateStack::with_prefixes(Extremes::new(compare))
+    }
+}
+
+impl<T, P> AggregateStack<T, P> {
+    #[inline]
+    fn with_prefixes(prefixes: P) -> AggregateStack<T, P> {
+        AggregateStack { stack: Vec::new(), prefixes }
+    }
 
+    /// Returns the number of elements the stack can hold without
+    /// reallocating.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use minstack::MinStack;
+    ///
+    /// let stack: MinStack<i32> = MinStack::with_capacity(10);
+    /// assert!(stack.capacity() >= 10);
+    /// ```
+    #[inline]
+    pub fn capacity(&self) -> usize {
+        self.stack.capacity()
+    }
+
+    /// Returns the number of elements in the stack.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use minstack::MinStack;
+    ///
+    /// let mut stack = MinStack::new();
+    /// stack.push(3);
+    /// stack.push(1);
+    /// assert_eq!(2, stack.len());
+    /// ```
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.stack.len()
+    }
+
+    /// Returns true if the stack contains no elements.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use minstack::MinStack;
+    ///
+    /// let mut stack = MinStack::new();
+    /// assert!(stack.is_empty());
+    /// stack.push(3);
+    /// assert!(!stack.is_empty());
+    /// ```
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.stack.is_empty()
+    }
+
+    /// Returns the last element pushed onto the stack without removing it, or
+    /// `None` if it is empty.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use minstack::MinStack;
+    ///
+    /// let mut stack = MinStack::new();
+    /// assert_eq!(None, stack.peek());
+    /// stack.push(1);
+    /// stack.push(3);
+    /// assert_eq!(Some(&3), stack.peek());
+    /// ```
+    #[inline]
+    pub fn peek(&self) -> Option<&T> {
+        self.stack.last()
+    }
+
+    /// Returns an iterator over the elements of the stack, from the first
+    /// pushed to the last.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use minstack::MinStack;
+    ///
+    /// let mut stack = MinStack::new();
+    /// stack.push(3);
+    /// stack.push(1);
+    /// stack.push(2);
+    /// assert_eq!(vec![&3, &1, &2], stack.iter().collect::<Vec<_>>());
+    /// ```
+    #[inline]
+    pub fn iter(&self) -> Iter<'_, T> {
+        Iter { inner: self.stack.iter() }
+    }
+}
+
+impl<T, P : Prefixes<T>> AggregateStack<T, P> {
     /// Appends an element to end of the stack.
     ///
     /// # Panics
@@ -48,16 +379,7 @@ impl<T : Ord> MinStack<T> {
     /// ```
     #[inline]
     pub fn push(&mut self, value: T) {
-        let idx = self.stack.len();
-
-        match self.min_stack.last() {
-            Some(&min) if value <= self.stack[min] =>
-                self.min_stack.push(idx),
-            None =>
-                self.min_stack.push(idx),
-            _ => { }
-        }
-
+        self.prefixes.push(&self.stack, &value);
         self.stack.push(value);
     }
 
@@ -77,17 +399,52 @@ impl<T : Ord> MinStack<T> {
     #[inline]
     pub fn pop(&mut self) -> Option<T> {
         let value = self.stack.pop();
+        self.prefixes.pop(self.stack.len());
+        value
+    }
 
-        match self.min_stack.last() {
-            Some(&min) if min == self.stack.len() => {
-                self.min_stack.pop();
-            }
-            _ => { }
-        }
+    /// Removes every element from the stack.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use minstack::MinStack;
+    ///
+    /// let mut stack = MinStack::new();
+    /// stack.push(3);
+    /// stack.clear();
+    /// assert!(stack.is_empty());
+    /// assert_eq!(None, stack.min());
+    /// ```
+    #[inline]
+    pub fn clear(&mut self) {
+        self.stack.clear();
+        self.prefixes.clear();
+    }
+}
 
-        value
+impl<T, C : Compare<T>> OrderedStack<T, C> {
+    /// Returns the element that comes first in the stack's ordering, or
+    /// `None` if it is empty.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use minstack::MaxStack;
+    ///
+    /// let mut stack = MaxStack::new();
+    /// stack.push(3);
+    /// stack.push(83);
+    /// stack.push(1);
+    /// assert_eq!(Some(&83), stack.extreme());
+    /// ```
+    #[inline]
+    pub fn extreme(&self) -> Option<&T> {
+        self.prefixes.get(&self.stack)
     }
+}
 
+impl<T : Ord> MinStack<T> {
     /// Returns the smallest element in the stack, or `None` if it is empty.
     ///
     /// # Examples
@@ -104,10 +461,230 @@ impl<T : Ord> MinStack<T> {
     /// ```
     #[inline]
     pub fn min(&self) -> Option<&T> {
-        self.min_stack.last().map(|&n| &self.stack[n])
+        self.extreme()
+    }
+}
+
+impl<T, F> OrderedStack<T, By<F>> where F: Fn(&T, &T) -> Ordering {
+    /// Returns the smallest element in the stack according to its
+    /// comparison function, or `None` if it is empty.
+    #[inline]
+    pub fn min(&self) -> Option<&T> {
+        self.extreme()
+    }
+}
+
+impl<T, K, F> OrderedStack<T, ByKey<F>> where F: Fn(&T) -> K, K : Ord {
+    /// Returns the element in the stack with the smallest key, or `None` if
+    /// it is empty.
+    #[inline]
+    pub fn min(&self) -> Option<&T> {
+        self.extreme()
+    }
+}
+
+impl<T : Ord> MaxStack<T> {
+    /// Returns the largest element in the stack, or `None` if it is empty.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use minstack::MaxStack;
+    ///
+    /// let mut stack = MaxStack::new();
+    /// assert_eq!(None, stack.max());
+    /// stack.push(3);
+    /// stack.push(83);
+    /// stack.push(1);
+    /// assert_eq!(Some(&83), stack.max());
+    /// ```
+    #[inline]
+    pub fn max(&self) -> Option<&T> {
+        self.extreme()
+    }
+}
+
+impl<T, P : Default> Default for AggregateStack<T, P> {
+    /// Constructs a new, empty stack.
+    #[inline]
+    fn default() -> AggregateStack<T, P> {
+        AggregateStack::new()
+    }
+}
+
+impl<T : fmt::Debug, P> fmt::Debug for AggregateStack<T, P> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+// The aggregates follow from the elements, so they don't need comparing.
+impl<T : PartialEq, P> PartialEq for AggregateStack<T, P> {
+    #[inline]
+    fn eq(&self, other: &AggregateStack<T, P>) -> bool {
+        self.stack == other.stack
+    }
+}
+
+impl<T : Eq, P> Eq for AggregateStack<T, P> {}
+
+impl<T, P : Prefixes<T> + Default> FromIterator<T> for AggregateStack<T, P> {
+    /// Builds a stack by pushing each element in turn.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use minstack::MinStack;
+    ///
+    /// let stack: MinStack<i32> = vec![3, 1, 2].into_iter().collect();
+    /// assert_eq!(Some(&1), stack.min());
+    /// assert_eq!(Some(&2), stack.peek());
+    /// ```
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> AggregateStack<T, P> {
+        let mut stack = AggregateStack::new();
+        stack.extend(iter);
+        stack
+    }
+}
+
+impl<T, P : Prefixes<T>> Extend<T> for AggregateStack<T, P> {
+    /// Pushes each element in turn.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use minstack::MinStack;
+    ///
+    /// let mut stack = MinStack::new();
+    /// stack.push(2);
+    /// stack.extend(vec![3, 1]);
+    /// assert_eq!(Some(&1), stack.min());
+    /// assert_eq!(3, stack.len());
+    /// ```
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        let iter = iter.into_iter();
+        self.stack.reserve(iter.size_hint().0);
+        for value in iter {
+            self.push(value);
+        }
+    }
+}
+
+/// An iterator over the elements of a stack, from the first pushed to
+/// the last.
+///
+/// Created by `AggregateStack::iter`.
+#[derive(Clone, Debug)]
+pub struct Iter<'a, T: 'a> {
+    inner: slice::Iter<'a, T>
+}
+
+impl<'a, T> Iterator for Iter<'a, T> {
+    type Item = &'a T;
+
+    #[inline]
+    fn next(&mut self) -> Option<&'a T> {
+        self.inner.next()
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl<'a, T> DoubleEndedIterator for Iter<'a, T> {
+    #[inline]
+    fn next_back(&mut self) -> Option<&'a T> {
+        self.inner.next_back()
+    }
+}
+
+impl<'a, T> ExactSizeIterator for Iter<'a, T> {}
+
+/// An iterator that moves the elements out of a stack, from the first
+/// pushed to the last.
+///
+/// Created by `AggregateStack::into_iter`.
+#[derive(Clone, Debug)]
+pub struct IntoIter<T> {
+    inner: vec::IntoIter<T>
+}
+
+impl<T> Iterator for IntoIter<T> {
+    type Item = T;
+
+    #[inline]
+    fn next(&mut self) -> Option<T> {
+        self.inner.next()
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
     }
 }
 
+impl<T> DoubleEndedIterator for IntoIter<T> {
+    #[inline]
+    fn next_back(&mut self) -> Option<T> {
+        self.inner.next_back()
+    }
+}
+
+impl<T> ExactSizeIterator for IntoIter<T> {}
+
+impl<T, P> IntoIterator for AggregateStack<T, P> {
+    type Item = T;
+    type IntoIter = IntoIter<T>;
+
+    /// Moves the elements out of the stack, from the first pushed to the
+    /// last.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use minstack::MinStack;
+    ///
+    /// let stack: MinStack<i32> = vec![3, 1, 2].into_iter().collect();
+    /// assert_eq!(vec![3, 1, 2], stack.into_iter().collect::<Vec<_>>());
+    /// ```
+    #[inline]
+    fn into_iter(self) -> IntoIter<T> {
+        IntoIter { inner: self.stack.into_iter() }
+    }
+}
+
+impl<'a, T, P> IntoIterator for &'a AggregateStack<T, P> {
+    type Item = &'a T;
+    type IntoIter = Iter<'a, T>;
+
+    #[inline]
+    fn into_iter(self) -> Iter<'a, T> {
+        self.iter()
+    }
+}
+
+/// Checks that `extremes` holds exactly the positions that pushing `stack`
+/// in order would have recorded.
+#[cfg(test)]
+fn check_extremes<T, C : Compare<T>>(stack: &[T], extremes: &Extremes<C>) {
+    let mut expected: Vec<usize> = Vec::new();
+    for (i, value) in stack.iter().enumerate() {
+        if expected.last().is_none_or(|&first| {
+            extremes.precedes(value, &stack[first])
+        }) {
+            expected.push(i);
+        }
+    }
+    assert_eq!(expected, extremes.indices);
+}
+
+#[cfg(test)]
+fn check_invariant<T, C : Compare<T>>(stack: &OrderedStack<T, C>) {
+    check_extremes(&stack.stack, &stack.prefixes);
+}
+
 #[test]
 fn empty_stack() {
     let mut stack : MinStack<i32> = MinStack::new();
@@ -162,3 +739,149 @@ fn duplicate_push() {
     assert_eq!(None, stack.min());
     assert_eq!(None, stack.pop());
 }
+
+#[test]
+fn collection_methods() {
+    let mut stack: MinStack<i32> = MinStack::with_capacity(4);
+    assert!(stack.is_empty());
+    assert_eq!(None, stack.peek());
+    stack.extend(vec![4, 2, 5]);
+    assert_eq!(3, stack.len());
+    assert_eq!(Some(&5), stack.peek());
+    assert_eq!(vec![&4, &2, &5], (&stack).into_iter().collect::<Vec<_>>());
+    assert_eq!(vec![&5, &2, &4], stack.iter().rev().collect::<Vec<_>>());
+    assert_eq!(3, stack.iter().len());
+    assert_eq!("[4, 2, 5]", format!("{:?}", stack));
+
+    let copy = stack.clone();
+    assert_eq!(copy, stack);
+    stack.pop();
+    assert_ne!(copy, stack);
+    stack.clear();
+    assert_eq!(MinStack::default(), stack);
+    assert_eq!(None, stack.min());
+    check_invariant(&stack);
+    assert_eq!(vec![4, 2, 5], copy.into_iter().collect::<Vec<_>>());
+}
+
+#[test]
+fn equal_stacks_have_equal_minimums() {
+    let a: MinStack<i32> = vec![3, 1, 2].into_iter().collect();
+    let mut b = MinStack::new();
+    b.push(3);
+    b.push(0);
+    b.pop();
+    b.push(1);
+    b.push(2);
+    assert_eq!(a, b);
+    assert_eq!(a.min(), b.min());
+}
+
+/// A small pseudo-random number generator, so that the property test below
+/// is repeatable without any dependencies.
+#[cfg(test)]
+struct Lcg(u64);
+
+#[cfg(test)]
+impl Lcg {
+    fn below(&mut self, n: u64) -> u64 {
+        self.0 = self.0.wrapping_mul(6364136223846793005)
+                       .wrapping_add(1442695040888963407);
+        (self.0 >> 33) % n
+    }
+}
+
+#[test]
+fn every_mutator_keeps_the_invariant() {
+    for seed in 0..50 {
+        let mut rng = Lcg(seed);
+        let mut stack = MinStack::new();
+        let mut model: Vec<u64> = Vec::new();
+        for _ in 0..200 {
+            match rng.below(10) {
+                0..=3 => {
+                    let value = rng.below(8);
+                    stack.push(value);
+                    model.push(value);
+                }
+                4..=6 => assert_eq!(model.pop(), stack.pop()),
+                7 => {
+                    let values: Vec<u64> = (0..rng.below(4))
+                        .map(|_| rng.below(8))
+                        .collect();
+                    stack.extend(values.clone());
+                    model.extend(values);
+                }
+                8 => {
+                    stack = stack.clone().into_iter().collect();
+                }
+                _ => if rng.below(5) == 0 {
+                    stack.clear();
+                    model.clear();
+                }
+            }
+            check_invariant(&stack);
+            assert_eq!(model.len(), stack.len());
+            assert_eq!(model.last(), stack.peek());
+            assert_eq!(model.iter().min(), stack.min());
+            assert!(model.iter().eq(stack.iter()));
+        }
+    }
+}
+
+#[test]
+fn max_stack_tracks_the_largest() {
+    let mut stack: MaxStack<i32> = vec![2, 5, 1, 5].into_iter().collect();
+    assert_eq!(Some(&5), stack.max());
+    stack.pop();
+    assert_eq!(Some(&5), stack.max());
+    stack.pop();
+    stack.pop();
+    assert_eq!(Some(&2), stack.max());
+    check_invariant(&stack);
+}
+
+#[test]
+fn equal_keys_report_the_last_pushed() {
+    let mut stack = OrderedStack::by_key(|&(key, _): &(u8, char)| key);
+    stack.extend(vec![(2, 'a'), (1, 'b'), (3, 'c'), (1, 'd')]);
+    assert_eq!(Some(&(1, 'd')), stack.min());
+    stack.pop();
+    assert_eq!(Some(&(1, 'b')), stack.min());
+    check_invariant(&stack);
+
+    let mut stack = OrderedStack::by(|a: &i32, b: &i32| {
+        a.abs().cmp(&b.abs())
+    });
+    stack.extend(vec![-4, 3, -3]);
+    assert_eq!(Some(&-3), stack.min());
+    stack.pop();
+    assert_eq!(Some(&3), stack.min());
+}
+
+#[test]
+fn every_ordering_matches_a_vec() {
+    for seed in 0..20 {
+        let mut rng = Lcg(seed);
+        let mut max_stack = MaxStack::new();
+        let mut key_stack = OrderedStack::by_key(|&v: &u64| v % 3);
+        let mut model: Vec<u64> = Vec::new();
+        for _ in 0..200 {
+            if rng.below(2) == 0 {
+                let value = rng.below(8);
+                max_stack.push(value);
+                key_stack.push(value);
+                model.push(value);
+            } else {
+                let expected = model.pop();
+                assert_eq!(expected, max_stack.pop());
+                assert_eq!(expected, key_stack.pop());
+            }
+            check_invariant(&max_stack);
+            check_invariant(&key_stack);
+            assert_eq!(model.iter().max(), max_stack.max());
+            let smallest_key = model.iter().rev().min_by_key(|&&v| v % 3);
+            assert_eq!(smallest_key, key_stack.min());
+        }
+    }
+}
@@ -0,0 +1,107 @@
+//! A stack that keeps track of both its smallest and largest elements.
+
+use {AggregateStack, Extremes, Max, Min, Prefixes};
+
+/// A stack with amortized `O(1)` push, pop, min and max.
+///
+/// # Examples
+///
+/// ```
+/// use minstack::MinMaxStack;
+///
+/// let mut stack: MinMaxStack<i32> = vec![5, 3, 27].into_iter().collect();
+/// assert_eq!(Some(&3), stack.min());
+/// assert_eq!(Some(&27), stack.max());
+/// stack.pop();
+/// assert_eq!(Some(&5), stack.max());
+/// ```
+pub type MinMaxStack<T> = AggregateStack<T, (Extremes<Min>, Extremes<Max>)>;
+
+/// Records two aggregates of the same stack, such as both of the extremes
+/// of a `MinMaxStack`.
+impl<T, P : Prefixes<T>, Q : Prefixes<T>> Prefixes<T> for (P, Q) {
+    #[inline]
+    fn push(&mut self, stack: &[T], value: &T) {
+        self.0.push(stack, value);
+        self.1.push(stack, value);
+    }
+
+    #[inline]
+    fn pop(&mut self, len: usize) {
+        self.0.pop(len);
+        self.1.pop(len);
+    }
+
+    #[inline]
+    fn clear(&mut self) {
+        self.0.clear();
+        self.1.clear();
+    }
+
+    #[inline]
+    fn reversed(self) -> (P, Q) {
+        (self.0.reversed(), self.1.reversed())
+    }
+}
+
+impl<T : Ord> MinMaxStack<T> {
+    /// Returns the smallest element in the stack, or `None` if it is empty.
+    #[inline]
+    pub fn min(&self) -> Option<&T> {
+        self.prefixes.0.get(&self.stack)
+    }
+
+    /// Returns the largest element in the stack, or `None` if it is empty.
+    #[inline]
+    pub fn max(&self) -> Option<&T> {
+        self.prefixes.1.get(&self.stack)
+    }
+}
+
+#[cfg(test)]
+use {check_extremes, Lcg};
+
+#[test]
+fn tracks_both_ends() {
+    let mut stack = MinMaxStack::new();
+    for &value in [4, 7, 1, 9, 1].iter() {
+        stack.push(value);
+    }
+    assert_eq!((Some(&1), Some(&9)), (stack.min(), stack.max()));
+    stack.pop();
+    stack.pop();
+    assert_eq!((Some(&1), Some(&7)), (stack.min(), stack.max()));
+    stack.pop();
+    assert_eq!((Some(&4), Some(&7)), (stack.min(), stack.max()));
+    assert_eq!("[4, 7]", format!("{:?}", stack));
+    stack.clear();
+    assert_eq!((None, None), (stack.min(), stack.max()));
+    assert_eq!(MinMaxStack::default(), stack);
+}
+
+#[test]
+fn matches_a_vec() {
+    for seed in 0..50 {
+        let mut rng = Lcg(seed);
+        let mut stack = MinMaxStack::new();
+        let mut model: Vec<u64> = Vec::new();
+        for _ in 0..200 {
+            match rng.below(10) {
+                0..=4 => {
+                    let value = rng.below(8);
+                    stack.push(value);
+                    model.push(value);
+                }
+                5..=8 => assert_eq!(model.pop(), stack.pop()),
+                _ => {
+                    stack = stack.clone().into_iter().collect();
+                }
+            }
+            check_extremes(&stack.stack, &stack.prefixes.0);
+            check_extremes(&stack.stack, &stack.prefixes.1);
+            assert_eq!(model.iter().min(), stack.min());
+            assert_eq!(model.iter().max(), stack.max());
+            assert!(model.iter().eq(&stack));
+        }
+    }
+}
@@ -0,0 +1,531 @@
+//! First-in, first-out queues built from two aggregate stacks, and sliding
+//! window minimums and maximums built from those.
+
+use std::cmp::Ordering;
+use std::fmt;
+use std::iter::FromIterator;
+
+use {Aggregate, AggregateStack, By, ByKey, Compare, Extremes, Max, Min,
+     Prefixes, Values};
+
+/// A queue that keeps track of an aggregate of its elements, recorded by
+/// `P`, with amortized `O(1)` push, pop and aggregate.
+///
+/// Elements are pushed onto one stack and popped from another. When the
+/// stack to pop from is empty, every element is moved across, which reverses
+/// them into the order that they were pushed. Each element is only moved
+/// once, and the aggregate of the queue combines the two stacks' aggregates.
+///
+/// # Examples
+///
+/// ```
+/// use minstack::{AggregateQueue, Fold};
+///
+/// // Composes affine functions `x -> a * x + b`, which doesn't commute.
+/// let mut queue = AggregateQueue::with_aggregate(Fold(
+///     |&f: &(i64, i64)| f,
+///     |&(a, b): &(i64, i64), &(c, d): &(i64, i64)| (a * c, c * b + d)));
+/// queue.push((2, 0));
+/// queue.push((1, 3));
+/// assert_eq!(Some((2, 3)), queue.aggregate());
+/// queue.pop();
+/// queue.push((5, 0));
+/// assert_eq!(Some((5, 15)), queue.aggregate());
+/// ```
+#[derive(Clone)]
+pub struct AggregateQueue<T, P> {
+    // Popped from, with the oldest element on top.
+    front: AggregateStack<T, P>,
+    // Pushed onto, with the newest element on top.
+    back: AggregateStack<T, P>
+}
+
+/// A queue that keeps track of the element that comes first in the ordering
+/// `C`, with amortized `O(1)` push, pop and extreme.
+///
+/// # Examples
+///
+/// ```
+/// use minstack::MinQueue;
+///
+/// let mut queue = MinQueue::new();
+/// queue.push(4);
+/// queue.push(1);
+/// queue.push(3);
+/// assert_eq!(Some(&1), queue.min());
+/// assert_eq!(Some(4), queue.pop());
+/// assert_eq!(Some(1), queue.pop());
+/// assert_eq!(Some(&3), queue.min());
+/// ```
+pub type OrderedQueue<T, C> = AggregateQueue<T, Extremes<C>>;
+
+/// A queue that keeps track of its smallest element.
+pub type MinQueue<T> = OrderedQueue<T, Min>;
+
+/// A queue that keeps track of its largest element.
+pub type MaxQueue<T> = OrderedQueue<T, Max>;
+
+impl<T, P : Prefixes<T> + Default> AggregateQueue<T, P> {
+    /// Constructs a new, empty queue.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use minstack::MinQueue;
+    ///
+    /// let mut queue: MinQueue<i32> = MinQueue::new();
+    /// ```
+    #[inline]
+    pub fn new() -> AggregateQueue<T, P> {
+        AggregateQueue::with_prefixes(P::default(), P::default())
+    }
+}
+
+impl<T, C : Compare<T> + Clone> OrderedQueue<T, C> {
+    /// Constructs a new, empty queue that keeps track of the first element
+    /// in the ordering `compare`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use minstack::{ByKey, OrderedQueue};
+    ///
+    /// let mut queue = OrderedQueue::with_compare(ByKey(|s: &&str| s.len()));
+    /// queue.push("three");
+    /// queue.push("four");
+    /// assert_eq!(Some(&"four"), queue.extreme());
+    /// ```
+    #[inline]
+    pub fn with_compare(compare: C) -> OrderedQueue<T, C> {
+        AggregateQueue::with_prefixes(Extremes::new(compare.clone()),
+                                      Extremes::new(compare))
+    }
+}
+
+impl<T, A : Aggregate<T> + Clone> AggregateQueue<T, Values<T, A>> {
+    /// Constructs a new, empty queue that keeps track of `aggregate`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use minstack::{AggregateQueue, Sum};
+    ///
+    /// let mut queue = AggregateQueue::with_aggregate(Sum);
+    /// queue.push(3);
+    /// queue.push(4);
+    /// queue.pop();
+    /// assert_eq!(Some(4), queue.aggregate());
+    /// ```
+    #[inline]
+    pub fn with_aggregate(aggregate: A) -> AggregateQueue<T, Values<T, A>> {
+        AggregateQueue::with_prefixes(Values::new(aggregate.clone()),
+                                      Values::new(aggregate))
+    }
+}
+
+impl<T, P> AggregateQueue<T, P> {
+    /// Returns the number of elements in the queue.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.front.len() + self.back.len()
+    }
+
+    /// Returns true if the queue contains no elements.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.front.is_empty() && self.back.is_empty()
+    }
+
+    /// Returns the element that has been in the queue longest without
+    /// removing it, or `None` if it is empty.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use minstack::MinQueue;
+    ///
+    /// let mut queue = MinQueue::new();
+    /// assert_eq!(None, queue.peek());
+    /// queue.push(3);
+    /// queue.push(1);
+    /// assert_eq!(Some(&3), queue.peek());
+    /// ```
+    #[inline]
+    pub fn peek(&self) -> Option<&T> {
+        self.front.peek().or_else(|| self.back.stack.first())
+    }
+
+    /// Returns an iterator over the elements of the queue, from the first
+    /// pushed to the last.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use minstack::MinQueue;
+    ///
+    /// let mut queue: MinQueue<i32> = vec![1, 2, 3].into_iter().collect();
+    /// queue.pop();
+    /// queue.push(4);
+    /// assert_eq!(vec![&2, &3, &4], queue.iter().collect::<Vec<_>>());
+    /// ```
+    #[inline]
+    pub fn iter(&self) -> impl DoubleEndedIterator<Item = &T> {
+        self.front.iter().rev().chain(self.back.iter())
+    }
+}
+
+impl<T, P : Prefixes<T>> AggregateQueue<T, P> {
+    #[inline]
+    fn with_prefixes(front: P, back: P) -> AggregateQueue<T, P> {
+        AggregateQueue {
+            front: AggregateStack::with_prefixes(front.reversed()),
+            back: AggregateStack::with_prefixes(back)
+        }
+    }
+
+    /// Removes every element from the queue.
+    #[inline]
+    pub fn clear(&mut self) {
+        self.front.clear();
+        self.back.clear();
+    }
+
+    /// Appends an element to the back of the queue.
+    #[inline]
+    pub fn push(&mut self, value: T) {
+        self.back.push(value);
+    }
+
+    /// Removes the element at the front of the queue and returns it, or
+    /// `None` if it is empty.
+    #[inline]
+    pub fn pop(&mut self) -> Option<T> {
+        if self.front.is_empty() {
+            while let Some(value) = self.back.pop() {
+                self.front.push(value);
+            }
+        }
+        self.front.pop()
+    }
+}
+
+impl<T, C : Compare<T>> OrderedQueue<T, C> {
+    /// Returns the element that comes first in the queue's ordering, or
+    /// `None` if it is empty. If several elements are ordered equally, the
+    /// last pushed is returned.
+    #[inline]
+    pub fn extreme(&self) -> Option<&T> {
+        match (self.front.extreme(), self.back.extreme()) {
+            (Some(front), Some(back)) => {
+                let compare = &self.back.prefixes.compare;
+                match compare.compare(back, front) {
+                    Ordering::Greater => Some(front),
+                    _ => Some(back),
+                }
+            }
+            (front, None) => front,
+            (None, back) => back,
+        }
+    }
+}
+
+impl<T : Ord> MinQueue<T> {
+    /// Returns the smallest element in the queue, or `None` if it is empty.
+    #[inline]
+    pub fn min(&self) -> Option<&T> {
+        self.extreme()
+    }
+}
+
+impl<T, F> OrderedQueue<T, By<F>> where F: Fn(&T, &T) -> Ordering {
+    /// Returns the smallest element in the queue according to its
+    /// comparison function, or `None` if it is empty.
+    #[inline]
+    pub fn min(&self) -> Option<&T> {
+        self.extreme()
+    }
+}
+
+impl<T, K, F> OrderedQueue<T, ByKey<F>> where F: Fn(&T) -> K, K : Ord {
+    /// Returns the element in the queue with the smallest key, or `None` if
+    /// it is empty.
+    #[inline]
+    pub fn min(&self) -> Option<&T> {
+        self.extreme()
+    }
+}
+
+impl<T : Ord> MaxQueue<T> {
+    /// Returns the largest element in the queue, or `None` if it is empty.
+    #[inline]
+    pub fn max(&self) -> Option<&T> {
+        self.extreme()
+    }
+}
+
+impl<T, A : Aggregate<T>> AggregateQueue<T, Values<T, A>> {
+    /// Returns the aggregate of every element in the queue, from the first
+    /// pushed to the last, or `None` if it is empty.
+    #[inline]
+    pub fn aggregate(&self) -> Option<A::Output> where A::Output: Clone {
+        match (self.front.aggregate(), self.back.aggregate()) {
+            (Some(front), Some(back)) => {
+                Some(self.back.aggregator().combine(front, back))
+            }
+            (front, None) => front.cloned(),
+            (None, back) => back.cloned(),
+        }
+    }
+}
+
+impl<T, P : Prefixes<T> + Default> Default for AggregateQueue<T, P> {
+    /// Constructs a new, empty queue.
+    #[inline]
+    fn default() -> AggregateQueue<T, P> {
+        AggregateQueue::new()
+    }
+}
+
+impl<T : fmt::Debug, P> fmt::Debug for AggregateQueue<T, P> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+impl<T, P : Prefixes<T> + Default> FromIterator<T> for AggregateQueue<T, P> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> AggregateQueue<T, P> {
+        let mut queue = AggregateQueue::new();
+        queue.extend(iter);
+        queue
+    }
+}
+
+impl<T, P : Prefixes<T>> Extend<T> for AggregateQueue<T, P> {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        self.back.extend(iter);
+    }
+}
+
+/// An iterator over the extreme of each window of `size` consecutive
+/// elements of another iterator.
+///
+/// Created by `SlidingWindows::sliding_window_min` and
+/// `SlidingWindows::sliding_window_max`.
+#[derive(Clone, Debug)]
+pub struct SlidingWindow<I : Iterator, C> {
+    iter: I,
+    size: usize,
+    window: OrderedQueue<I::Item, C>
+}
+
+impl<I, C> Iterator for SlidingWindow<I, C>
+        where I: Iterator, I::Item: Clone, C: Compare<I::Item> {
+    type Item = I::Item;
+
+    fn next(&mut self) -> Option<I::Item> {
+        if self.window.len() == self.size {
+            self.window.pop();
+        }
+        while self.window.len() < self.size {
+            let value = self.iter.next()?;
+            self.window.push(value);
+        }
+        self.window.extreme().cloned()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        // Until the window is full, its first extreme needs more elements.
+        let missing = self.size - 1 - self.window.len().min(self.size - 1);
+        let (low, high) = self.iter.size_hint();
+        (low.saturating_sub(missing), high.map(|h| h.saturating_sub(missing)))
+    }
+}
+
+/// Sliding window minimums and maximums for any iterator.
+pub trait SlidingWindows : Iterator + Sized {
+    /// Returns an iterator over the smallest element of each window of
+    /// `size` consecutive elements. If there are fewer than `size` elements
+    /// there are no windows.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `size` is 0.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use minstack::SlidingWindows;
+    ///
+    /// let values = vec![4, 2, 12, 3, 8, 1, 7];
+    /// let mins: Vec<i32> = values.into_iter().sliding_window_min(3).collect();
+    /// assert_eq!(vec![2, 2, 3, 1, 1], mins);
+    /// ```
+    fn sliding_window_min(self, size: usize) -> SlidingWindow<Self, Min>
+            where Self::Item: Ord + Clone {
+        assert!(size > 0, "window size must be non-zero");
+        SlidingWindow { iter: self, size, window: OrderedQueue::new() }
+    }
+
+    /// Returns an iterator over the largest element of each window of
+    /// `size` consecutive elements. If there are fewer than `size` elements
+    /// there are no windows.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `size` is 0.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use minstack::SlidingWindows;
+    ///
+    /// let values = vec![4, 2, 12, 3, 8, 1, 7];
+    /// let maxes: Vec<i32> = values.into_iter().sliding_window_max(3).collect();
+    /// assert_eq!(vec![12, 12, 12, 8, 8], maxes);
+    /// ```
+    fn sliding_window_max(self, size: usize) -> SlidingWindow<Self, Max>
+            where Self::Item: Ord + Clone {
+        assert!(size > 0, "window size must be non-zero");
+        SlidingWindow { iter: self, size, window: OrderedQueue::new() }
+    }
+}
+
+impl<I : Iterator> SlidingWindows for I {}
+
+#[cfg(test)]
+use aggregate::check_prefixes;
+#[cfg(test)]
+use std::collections::VecDeque;
+#[cfg(test)]
+use {check_invariant, Lcg};
+
+#[test]
+fn matches_a_vec_deque() {
+    for seed in 0..50 {
+        let mut rng = Lcg(seed);
+        let mut min_queue = MinQueue::new();
+        let mut max_queue = MaxQueue::new();
+        let mut model: VecDeque<u64> = VecDeque::new();
+        for _ in 0..200 {
+            if rng.below(5) < 3 {
+                let value = rng.below(10);
+                min_queue.push(value);
+                max_queue.push(value);
+                model.push_back(value);
+            } else {
+                let expected = model.pop_front();
+                assert_eq!(expected, min_queue.pop());
+                assert_eq!(expected, max_queue.pop());
+            }
+            check_invariant(&min_queue.front);
+            check_invariant(&min_queue.back);
+            assert_eq!(model.len(), min_queue.len());
+            assert_eq!(model.front(), min_queue.peek());
+            assert_eq!(model.iter().min(), min_queue.min());
+            assert_eq!(model.iter().max(), max_queue.max());
+            assert!(model.iter().eq(min_queue.iter()));
+        }
+    }
+}
+
+#[test]
+fn equal_extremes_report_the_last_pushed() {
+    let mut queue = OrderedQueue::with_compare(ByKey(|&(k, _): &(u8, u8)| k));
+    queue.extend(vec![(1, 0), (2, 1)]);
+    queue.pop();
+    queue.push((1, 2));
+    queue.push((1, 3));
+    assert_eq!(Some(&(1, 3)), queue.min());
+    assert_eq!("[(2, 1), (1, 2), (1, 3)]", format!("{:?}", queue));
+    queue.pop();
+    assert_eq!(Some(&(1, 3)), queue.min());
+    check_invariant(&queue.front);
+
+    let mut queue = OrderedQueue::with_compare(ByKey(|&(k, _): &(u8, u8)| k));
+    queue.extend(vec![(0, 0), (1, 1), (1, 2)]);
+    queue.pop();
+    assert_eq!(Some(&(1, 2)), queue.min());
+    queue.clear();
+    assert!(queue.is_empty());
+    assert_eq!(None, queue.min());
+}
+
+#[test]
+fn sliding_windows_match_brute_force() {
+    let mut rng = Lcg(7);
+    let values: Vec<u64> = (0..300).map(|_| rng.below(1000)).collect();
+    for size in 1..40 {
+        let mins: Vec<u64> = values.iter().cloned()
+            .sliding_window_min(size)
+            .collect();
+        let maxes: Vec<u64> = values.iter().cloned()
+            .sliding_window_max(size)
+            .collect();
+        let windows = values.windows(size);
+        assert_eq!(windows.clone().map(|w| *w.iter().min().unwrap())
+                       .collect::<Vec<_>>(),
+                   mins);
+        assert_eq!(windows.map(|w| *w.iter().max().unwrap())
+                       .collect::<Vec<_>>(),
+                   maxes);
+    }
+}
+
+#[test]
+fn short_inputs_have_no_windows() {
+    let mut windows = vec![1, 2].into_iter().sliding_window_min(3);
+    assert_eq!((0, Some(0)), windows.size_hint());
+    assert_eq!(None, windows.next());
+    let windows = (0..10).sliding_window_max(4);
+    assert_eq!((7, Some(7)), windows.size_hint());
+    assert_eq!(7, windows.count());
+}
+
+#[test]
+#[should_panic(expected = "non-zero")]
+fn empty_windows_panic() {
+    (0..10).sliding_window_min(0);
+}
+
+#[test]
+fn aggregates_keep_queue_order() {
+    use {Fold, Gcd};
+
+    for seed in 0..50 {
+        let mut rng = Lcg(seed);
+        let mut words = AggregateQueue::with_aggregate(Fold(
+            |&v: &u64| v.to_string(),
+            |a: &String, b: &String| format!("{}{}", a, b)));
+        let mut gcds = AggregateQueue::with_aggregate(Gcd);
+        let mut model: VecDeque<u64> = VecDeque::new();
+        for _ in 0..200 {
+            if rng.below(5) < 3 {
+                let value = rng.below(10) * 4;
+                words.push(value);
+                gcds.push(value);
+                model.push_back(value);
+            } else {
+                let expected = model.pop_front();
+                assert_eq!(expected, words.pop());
+                assert_eq!(expected, gcds.pop());
+            }
+            check_prefixes(&words.front);
+            check_prefixes(&words.back);
+            let concatenated: String = model.iter()
+                .map(|v| v.to_string())
+                .collect();
+            assert_eq!(model.front().map(|_| concatenated), words.aggregate());
+            let gcd = model.iter().fold(None, |acc: Option<u64>, &v| {
+                let (mut a, mut b) = (acc.unwrap_or(v), v);
+                while b != 0 {
+                    let r = a % b;
+                    a = b;
+                    b = r;
+                }
+                Some(a)
+            });
+            assert_eq!(gcd, gcds.aggregate());
+            assert_eq!(format!("{:?}", model), format!("{:?}", words));
+        }
+    }
+}